@@ -0,0 +1,157 @@
+// 对比逐字节扫描(Scanner::Scalar，即原先的Lexer)与按块扫描的Lexer
+// cargo bench --bench lexer [-- file.json ...]
+// 语料为main.rs中的示例、由示例生成的大文件，以及命令行给出的文件
+
+use std::time::{Duration, Instant};
+use json::Lexer;
+use json::scan::Scanner;
+
+const SAMPLE: &str = include_str!("data/sample.json");
+
+fn scanners() -> Vec<Scanner> {
+    let mut scanners = vec![Scanner::Scalar, Scanner::Portable];
+    if cfg!(target_arch = "x86_64") {
+        scanners.push(Scanner::Sse2);
+    }
+    if Scanner::detect() == Scanner::Avx2 {
+        scanners.push(Scanner::Avx2);
+    }
+    scanners
+}
+
+// 由示例拼成约size字节的数组
+fn repeated(size: usize) -> String {
+    let mut out = String::from("[");
+    while out.len() < size {
+        if out.len() > 1 {
+            out.push_str(",\n");
+        }
+        out.push_str(SAMPLE.trim());
+    }
+    out.push(']');
+    out
+}
+
+// 长字符串为主，偶尔带转义
+fn strings(size: usize) -> String {
+    let text = "雨课堂V6.2版本基于AI的一键出题功能 lorem ipsum dolor sit amet, consectetur adipiscing elit ".repeat(12);
+    let mut out = String::from("[");
+    let mut i = 0;
+    while out.len() < size {
+        if i > 0 {
+            out.push(',');
+        }
+        out.push('"');
+        out.push_str(&text);
+        if i % 4 == 0 {
+            out.push_str("\\\"quoted\\\"\\n");
+        }
+        out.push('"');
+        i += 1;
+    }
+    out.push(']');
+    out
+}
+
+// 深层缩进，空白为主
+fn indented(size: usize) -> String {
+    let mut out = String::new();
+    let mut depth = 0;
+    while out.len() < size {
+        out.push_str(&" ".repeat(depth * 4));
+        out.push_str("{\"k\": [1, 2, 3],\n");
+        out.push_str(&" ".repeat(depth * 4 + 4));
+        out.push_str("\"child\":\n");
+        depth += 1;
+    }
+    out.push_str(&" ".repeat(depth * 4));
+    out.push_str("null");
+    for d in (0..depth).rev() {
+        out.push('\n');
+        out.push_str(&" ".repeat(d * 4));
+        out.push('}');
+    }
+    out
+}
+
+fn lex_all(json_str: &str, scanner: Scanner) -> usize {
+    let mut lexer = Lexer::new(json_str).with_scanner(scanner);
+    let mut tokens = 0;
+    while !lexer.at_end().unwrap() {
+        lexer.lex().unwrap();
+        tokens += 1;
+    }
+    tokens
+}
+
+// 至少运行3次且累计超过200ms，取最快的一次
+fn measure(mut f: impl FnMut()) -> Duration {
+    let mut best = Duration::MAX;
+    let mut total = Duration::ZERO;
+    let mut runs = 0;
+    while runs < 3 || total < Duration::from_millis(200) {
+        let start = Instant::now();
+        f();
+        let elapsed = start.elapsed();
+        best = best.min(elapsed);
+        total += elapsed;
+        runs += 1;
+    }
+    best
+}
+
+fn throughput(bytes: usize, time: Duration) -> f64 {
+    bytes as f64 / time.as_secs_f64() / (1024.0 * 1024.0)
+}
+
+fn bench(name: &str, json_str: &str) {
+    let expected = lex_all(json_str, Scanner::Scalar);
+    let base = measure(|| {
+        lex_all(json_str, Scanner::Scalar);
+    });
+    for scanner in scanners() {
+        assert_eq!(lex_all(json_str, scanner), expected, "{:?} disagrees with Scalar", scanner);
+        let time = measure(|| {
+            lex_all(json_str, scanner);
+        });
+        println!(
+            "| {:<16} | {:>10} | {:<10} | {:>9.1} | {:>6.2}x |",
+            name, json_str.len(), format!("{:?}", scanner), throughput(json_str.len(), time),
+            base.as_secs_f64() / time.as_secs_f64(),
+        );
+    }
+    let bytes = json_str.as_bytes();
+    let std = measure(|| {
+        std::str::from_utf8(bytes).unwrap();
+    });
+    let scanner = Scanner::detect();
+    let time = measure(|| {
+        scanner.validate_utf8(bytes).unwrap();
+    });
+    println!(
+        "| {:<16} | {:>10} | {:<10} | {:>9.1} | {:>6.2}x |",
+        name, bytes.len(), "utf8", throughput(bytes.len(), time), std.as_secs_f64() / time.as_secs_f64(),
+    );
+}
+
+fn main() {
+    let size = 8 << 20;
+    let mut corpora = vec![
+        ("sample".to_string(), SAMPLE.to_string()),
+        ("repeated".to_string(), repeated(size)),
+        ("strings".to_string(), strings(size)),
+        ("indented".to_string(), indented(size)),
+    ];
+    // cargo bench会传入--bench
+    for path in std::env::args().skip(1).filter(|a| !a.starts_with("--")) {
+        let text = std::fs::read_to_string(&path).unwrap_or_else(|e| panic!("{}: {}", path, e));
+        corpora.push((path, text));
+    }
+    println!("detected: {:?}", Scanner::detect());
+    println!("| corpus           |      bytes | scanner    |      MB/s | vs base |");
+    println!("| ---------------- | ---------- | ---------- | --------- | ------- |");
+    for (name, text) in &corpora {
+        bench(name, text);
+    }
+    println!("vs base: 相对Scalar的加速比；utf8一行相对std::str::from_utf8");
+}
@@ -0,0 +1,217 @@
+// 对比各个parser的吞吐量和内存分配次数
+// cargo bench --bench parsers [-- file.json ...]
+// 语料全部在本地生成: 深层嵌套、宽对象、长字符串、数字数组，以及main.rs中的示例
+//
+// ValueList和PairList在递归下降的parser中每个元素递归一层，因此在栈足够大的线程中运行
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+use json::{DefiniteParser, IndefiniteParser, Lexer, LRParser, TableDrivenParser, Tape};
+
+const SAMPLE: &str = include_str!("data/sample.json");
+const SIZE: usize = 1 << 20;
+
+// 统计分配次数和字节数
+struct Counting;
+
+static ALLOCS: AtomicUsize = AtomicUsize::new(0);
+static BYTES: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCS.fetch_add(1, Ordering::Relaxed);
+        BYTES.fetch_add(layout.size(), Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCS.fetch_add(1, Ordering::Relaxed);
+        BYTES.fetch_add(new_size, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+// 返回解析结果中的节点数，用于确认各个parser的结果一致
+type Parse = fn(&str) -> Result<usize, String>;
+
+fn count(value: &json::Value) -> usize {
+    match value {
+        json::Value::Array(arr) => 1 + arr.iter().map(count).sum::<usize>(),
+        json::Value::Object(obj) => 1 + obj.values().map(count).sum::<usize>(),
+        _ => 1,
+    }
+}
+
+fn parsers() -> Vec<(&'static str, Parse)> {
+    vec![
+        ("indefinite", |s| IndefiniteParser::new(Lexer::new(s)).parse().map(|v| count(&v))),
+        ("definite", |s| DefiniteParser::new(Lexer::new(s)).parse().map(|v| count(&v))),
+        ("table", |s| TableDrivenParser::new(Lexer::new(s)).parse().map(|v| count(&v))),
+        ("lr", |s| LRParser::new(Lexer::new(s)).parse().map(|v| count(&v))),
+        ("tape", |s| Tape::parse(s).map(|t| t.len())),
+    ]
+}
+
+// [[[...]]]，每层一个数字
+fn deep(size: usize) -> String {
+    let depth = size / 6;
+    let mut out = String::with_capacity(size);
+    for i in 0..depth {
+        out.push_str(&format!("[{},", i % 10));
+    }
+    out.push_str("null");
+    out.push_str(&"]".repeat(depth));
+    out
+}
+
+// 一个对象，大量短键
+fn wide(size: usize) -> String {
+    let mut out = String::from("{");
+    let mut i = 0;
+    while out.len() < size {
+        if i > 0 {
+            out.push_str(", ");
+        }
+        out.push_str(&format!("\"key_{}\": {}", i, if i % 3 == 0 { "true" } else { "null" }));
+        i += 1;
+    }
+    out.push('}');
+    out
+}
+
+fn strings(size: usize) -> String {
+    let text = "突破人的思维框架 the quick brown fox jumps over the lazy dog \\\"quoted\\\" ".repeat(30);
+    let mut out = String::from("[");
+    while out.len() < size {
+        if out.len() > 1 {
+            out.push(',');
+        }
+        out.push('"');
+        out.push_str(&text);
+        out.push('"');
+    }
+    out.push(']');
+    out
+}
+
+// 用线性同余生成器得到确定的伪随机数
+fn numbers(size: usize) -> String {
+    let mut out = String::from("[");
+    let mut x: u64 = 42;
+    while out.len() < size {
+        if out.len() > 1 {
+            out.push(',');
+        }
+        x = x.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+        match x >> 62 {
+            0 => out.push_str(&format!("{}", (x >> 40) as i64 - (1 << 22))),
+            1 => out.push_str(&format!("{:.6}", (x >> 11) as f64 / (1u64 << 53) as f64 * 1e4)),
+            2 => out.push_str(&format!("{:e}", (x >> 11) as f64 / (1u64 << 40) as f64)),
+            _ => out.push_str(&format!("{}", x >> 32)),
+        }
+    }
+    out.push(']');
+    out
+}
+
+fn repeated(size: usize) -> String {
+    let mut out = String::from("[");
+    while out.len() < size {
+        if out.len() > 1 {
+            out.push(',');
+        }
+        out.push_str(SAMPLE.trim());
+    }
+    out.push(']');
+    out
+}
+
+// 至少运行3次且累计超过300ms，取最快的一次
+fn measure(parse: Parse, json_str: &str) -> Duration {
+    let mut best = Duration::MAX;
+    let mut total = Duration::ZERO;
+    let mut runs = 0;
+    while runs < 3 || total < Duration::from_millis(300) {
+        let start = Instant::now();
+        let _ = parse(json_str);
+        let elapsed = start.elapsed();
+        best = best.min(elapsed);
+        total += elapsed;
+        runs += 1;
+    }
+    best
+}
+
+fn bench(name: &str, json_str: &str) {
+    let mut expected = None;
+    for (parser, parse) in parsers() {
+        ALLOCS.store(0, Ordering::Relaxed);
+        BYTES.store(0, Ordering::Relaxed);
+        let result = parse(json_str);
+        let (allocs, bytes) = (ALLOCS.load(Ordering::Relaxed), BYTES.load(Ordering::Relaxed));
+        let nodes = match result {
+            Ok(nodes) => nodes,
+            Err(msg) => {
+                println!("| {:<10} | {:>9} | {:<10} | error: {}", name, json_str.len(), parser, msg);
+                continue;
+            }
+        };
+        if *expected.get_or_insert(nodes) != nodes {
+            println!("| {:<10} | {:>9} | {:<10} | {} nodes, expected {}", name, json_str.len(), parser, nodes, expected.unwrap());
+            continue;
+        }
+        let time = measure(parse, json_str);
+        println!(
+            "| {:<10} | {:>9} | {:<10} | {:>8.1} | {:>9} | {:>11} | {:>8.2} |",
+            name,
+            json_str.len(),
+            parser,
+            json_str.len() as f64 / time.as_secs_f64() / (1024.0 * 1024.0),
+            allocs,
+            bytes,
+            allocs as f64 / nodes as f64,
+        );
+    }
+}
+
+fn run() {
+    // 预先由文法生成TableDrivenParser和LRParser的分析表，不计入第一次解析的分配
+    TableDrivenParser::grammar();
+    LRParser::grammar();
+    let mut corpora = vec![
+        ("sample".to_string(), SAMPLE.to_string()),
+        ("repeated".to_string(), repeated(SIZE)),
+        ("deep".to_string(), deep(SIZE / 16)),
+        ("wide".to_string(), wide(SIZE)),
+        ("strings".to_string(), strings(SIZE)),
+        ("numbers".to_string(), numbers(SIZE)),
+    ];
+    // cargo bench会传入--bench
+    for path in std::env::args().skip(1).filter(|a| !a.starts_with("--")) {
+        let text = std::fs::read_to_string(&path).unwrap_or_else(|e| panic!("{}: {}", path, e));
+        corpora.push((path, text));
+    }
+    println!("| corpus     |     bytes | parser     |     MB/s |    allocs | alloc bytes | per node |");
+    println!("| ---------- | --------- | ---------- | -------- | --------- | ----------- | -------- |");
+    for (name, text) in &corpora {
+        bench(name, text);
+    }
+    println!("allocs/alloc bytes为解析一次的分配次数和字节数(含realloc)，per node为每个节点的分配次数");
+}
+
+fn main() {
+    std::thread::Builder::new()
+        .stack_size(1 << 30)
+        .spawn(run)
+        .unwrap()
+        .join()
+        .unwrap();
+}
@@ -0,0 +1,234 @@
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{parse_macro_input, parse_quote, Data, DeriveInput, Error, Fields, LitStr, Path, Result};
+
+// json::convert中ToJson和FromJson的derive宏，只支持具名字段的结构体
+// 容器属性: #[json(crate = "path")] 指定json模块的路径，默认为::json
+// 字段属性: #[json(rename = "name")] #[json(default)] #[json(skip)] #[json(flatten)]
+
+#[proc_macro_derive(ToJson, attributes(json))]
+pub fn derive_to_json(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand_to_json(&input).unwrap_or_else(Error::into_compile_error).into()
+}
+
+#[proc_macro_derive(FromJson, attributes(json))]
+pub fn derive_from_json(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand_from_json(&input).unwrap_or_else(Error::into_compile_error).into()
+}
+
+#[derive(Default)]
+struct FieldAttrs {
+    rename: Option<String>,
+    default: bool,
+    skip: bool,
+    flatten: bool,
+}
+
+struct Field<'i> {
+    ident: &'i syn::Ident,
+    ty: &'i syn::Type,
+    key: String,
+    attrs: FieldAttrs,
+}
+
+fn krate(input: &DeriveInput) -> Result<Path> {
+    let mut path = parse_quote!(::json);
+    for attr in input.attrs.iter().filter(|a| a.path().is_ident("json")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("crate") {
+                path = meta.value()?.parse::<LitStr>()?.parse()?;
+                Ok(())
+            } else {
+                Err(meta.error("unsupported json attribute"))
+            }
+        })?;
+    }
+    Ok(path)
+}
+
+fn fields(input: &DeriveInput) -> Result<Vec<Field<'_>>> {
+    let named = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(named) => &named.named,
+            _ => return Err(Error::new_spanned(input, "only structs with named fields are supported")),
+        },
+        _ => return Err(Error::new_spanned(input, "only structs with named fields are supported")),
+    };
+    let mut fields = vec![];
+    for field in named {
+        let mut attrs = FieldAttrs::default();
+        for attr in field.attrs.iter().filter(|a| a.path().is_ident("json")) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("rename") {
+                    attrs.rename = Some(meta.value()?.parse::<LitStr>()?.value());
+                } else if meta.path.is_ident("default") {
+                    attrs.default = true;
+                } else if meta.path.is_ident("skip") {
+                    attrs.skip = true;
+                } else if meta.path.is_ident("flatten") {
+                    attrs.flatten = true;
+                } else {
+                    return Err(meta.error("unsupported json attribute"));
+                }
+                Ok(())
+            })?;
+        }
+        if attrs.flatten && (attrs.rename.is_some() || attrs.skip) {
+            return Err(Error::new_spanned(field, "flatten cannot be combined with rename or skip"));
+        }
+        let ident = field.ident.as_ref().unwrap();
+        let key = attrs.rename.clone().unwrap_or_else(|| {
+            let name = ident.to_string();
+            name.strip_prefix("r#").map(str::to_string).unwrap_or(name)
+        });
+        fields.push(Field { ident, ty: &field.ty, key, attrs });
+    }
+    Ok(fields)
+}
+
+// 为每个类型参数加上trait约束
+fn generics(input: &DeriveInput, bound: Path) -> syn::Generics {
+    let mut generics = input.generics.clone();
+    for param in generics.type_params_mut() {
+        param.bounds.push(parse_quote!(#bound));
+    }
+    generics
+}
+
+fn expand_to_json(input: &DeriveInput) -> Result<TokenStream2> {
+    let krate = krate(input)?;
+    let fields = fields(input)?;
+    let name = &input.ident;
+    let generics = generics(input, parse_quote!(#krate::convert::ToJson));
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    let members = fields.iter().filter(|f| !f.attrs.skip).map(|f| {
+        let ident = f.ident;
+        let key = &f.key;
+        if f.attrs.flatten {
+            quote! {
+                #krate::convert::ToJson::write_members(&self.#ident, out, first);
+            }
+        } else {
+            quote! {
+                #krate::convert::write_key(out, first, #key);
+                #krate::convert::ToJson::write_json(&self.#ident, out);
+            }
+        }
+    });
+
+    Ok(quote! {
+        impl #impl_generics #krate::convert::ToJson for #name #ty_generics #where_clause {
+            fn write_json(&self, out: &mut ::std::string::String) {
+                out.push('{');
+                let mut first = true;
+                #krate::convert::ToJson::write_members(self, out, &mut first);
+                out.push('}');
+            }
+
+            fn write_members(&self, out: &mut ::std::string::String, first: &mut bool) {
+                #(#members)*
+            }
+        }
+    })
+}
+
+fn expand_from_json(input: &DeriveInput) -> Result<TokenStream2> {
+    let krate = krate(input)?;
+    let fields = fields(input)?;
+    let name = &input.ident;
+    let generics = generics(input, parse_quote!(#krate::convert::FromJson));
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    // 普通字段使用的键，其余的成员留给flatten的字段
+    let claimed: Vec<&String> = fields.iter().filter(|f| !f.attrs.skip && !f.attrs.flatten).map(|f| &f.key).collect();
+    let inits = fields.iter().map(|f| {
+        let ident = f.ident;
+        let ty = f.ty;
+        let key = &f.key;
+        let value = if f.attrs.skip {
+            quote!(::std::default::Default::default())
+        } else if f.attrs.flatten {
+            quote! {
+                <#ty as #krate::convert::FromJson>::from_json(&#krate::convert::unclaimed(value, &[#(#claimed),*]))?
+            }
+        } else {
+            let missing = if f.attrs.default {
+                quote!(::std::default::Default::default())
+            } else {
+                quote! {
+                    <#ty as #krate::convert::FromJson>::missing()
+                        .ok_or_else(|| #krate::convert::ConvertError::missing_field(#key))?
+                }
+            };
+            quote! {
+                match #krate::convert::field(value, #key) {
+                    ::std::option::Option::Some(v) => <#ty as #krate::convert::FromJson>::from_json(v)
+                        .map_err(|e| e.at_key(#key))?,
+                    ::std::option::Option::None => #missing,
+                }
+            }
+        };
+        quote!(#ident: #value)
+    });
+
+    Ok(quote! {
+        impl #impl_generics #krate::convert::FromJson for #name #ty_generics #where_clause {
+            fn from_json(value: &#krate::Value) -> ::std::result::Result<Self, #krate::convert::ConvertError> {
+                if !matches!(value, #krate::Value::Object(_)) {
+                    return ::std::result::Result::Err(#krate::convert::ConvertError::expected("object", value));
+                }
+                ::std::result::Result::Ok(#name {
+                    #(#inits,)*
+                })
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error(input: DeriveInput) -> String {
+        expand_from_json(&input).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn default_crate_path() {
+        let input: DeriveInput = parse_quote!(struct A { x: u8 });
+        let code = expand_to_json(&input).unwrap().to_string();
+        assert!(code.contains(":: json :: convert :: ToJson"), "{}", code);
+        let input: DeriveInput = parse_quote!(#[json(crate = "crate::json")] struct A { x: u8 });
+        let code = expand_from_json(&input).unwrap().to_string();
+        assert!(code.contains("crate :: json :: convert :: FromJson"), "{}", code);
+    }
+
+    #[test]
+    fn flatten_gets_unclaimed_keys() {
+        let input: DeriveInput = parse_quote! {
+            struct A {
+                #[json(rename = "b")]
+                a: u8,
+                #[json(skip)]
+                c: u8,
+                #[json(flatten)]
+                rest: Map,
+            }
+        };
+        let code = expand_from_json(&input).unwrap().to_string();
+        assert!(code.contains(r#"unclaimed (value , & ["b"])"#), "{}", code);
+    }
+
+    #[test]
+    fn invalid_input() {
+        assert_eq!(error(parse_quote!(enum A { B })), "only structs with named fields are supported");
+        assert_eq!(error(parse_quote!(struct A(u8);)), "only structs with named fields are supported");
+        assert_eq!(error(parse_quote!(struct A { #[json(flatten, skip)] a: u8 })), "flatten cannot be combined with rename or skip");
+        assert_eq!(error(parse_quote!(struct A { #[json(other)] a: u8 })), "unsupported json attribute");
+        assert_eq!(error(parse_quote!(#[json(other = "x")] struct A { a: u8 })), "unsupported json attribute");
+    }
+}
@@ -0,0 +1,186 @@
+use crate::json::span::pointer;
+use crate::json::{unescape, write_canonical_string, Value, Writer};
+
+// 两个文档的结构化比较，结果与键的顺序和空白无关
+// - 对象按还原转义后的键比较，按键排序输出，每次运行的结果相同
+// - 数组按下标比较，多出的元素为增加或删除，删除从后往前列出，便于按顺序应用JSON Patch
+// - 数字之差的绝对值不超过tolerance时视为相等
+// - 类型不同或标量不等时为修改，不再深入比较
+
+type Result<T> = std::result::Result<T, String>;
+
+#[derive(Debug, Clone)]
+pub enum Change<'a> {
+    Added(String, Value<'a>),
+    Removed(String, Value<'a>),
+    // 原来的值和新的值
+    Changed(String, Value<'a>, Value<'a>),
+}
+
+impl Change<'_> {
+    pub fn pointer(&self) -> &str {
+        match self {
+            Change::Added(pointer, _) | Change::Removed(pointer, _) | Change::Changed(pointer, _, _) => pointer,
+        }
+    }
+}
+
+/// 由a到b的所有变化
+pub fn diff<'a>(a: &Value<'a>, b: &Value<'a>, tolerance: f64) -> Result<Vec<Change<'a>>> {
+    let mut changes = vec![];
+    let mut path = vec![];
+    compare(a, b, tolerance, &mut path, &mut changes)?;
+    Ok(changes)
+}
+
+fn compare<'a>(a: &Value<'a>, b: &Value<'a>, tolerance: f64, path: &mut Vec<String>, changes: &mut Vec<Change<'a>>) -> Result<()> {
+    match (a, b) {
+        (Value::Object(x), Value::Object(y)) => {
+            let mut keys = x.keys().chain(y.keys()).map(|k| unescape(k)).collect::<Result<Vec<_>>>()?;
+            keys.sort();
+            keys.dedup();
+            for key in keys {
+                path.push(key.to_string());
+                match (a.get(&key), b.get(&key)) {
+                    (Some(a), Some(b)) => compare(a, b, tolerance, path, changes)?,
+                    (Some(a), None) => changes.push(Change::Removed(pointer(path), a.clone())),
+                    (None, Some(b)) => changes.push(Change::Added(pointer(path), b.clone())),
+                    (None, None) => unreachable!(),
+                }
+                path.pop();
+            }
+        }
+        (Value::Array(x), Value::Array(y)) => {
+            for (i, (a, b)) in x.iter().zip(y).enumerate() {
+                path.push(i.to_string());
+                compare(a, b, tolerance, path, changes)?;
+                path.pop();
+            }
+            for (i, b) in y.iter().enumerate().skip(x.len()) {
+                path.push(i.to_string());
+                changes.push(Change::Added(pointer(path), b.clone()));
+                path.pop();
+            }
+            for (i, a) in x.iter().enumerate().skip(y.len()).rev() {
+                path.push(i.to_string());
+                changes.push(Change::Removed(pointer(path), a.clone()));
+                path.pop();
+            }
+        }
+        (Value::Number(x), Value::Number(y)) if (x - y).abs() <= tolerance || a == b => {}
+        (Value::String(_), Value::String(_)) | (Value::Bool(_), Value::Bool(_)) | (Value::Null, Value::Null) if a == b => {}
+        _ => changes.push(Change::Changed(pointer(path), a.clone(), b.clone())),
+    }
+    Ok(())
+}
+
+/// 每行一个变化: + 增加，- 删除，~ 修改，color为true时用ANSI颜色区分
+pub fn human(changes: &[Change], color: bool) -> Result<String> {
+    let writer = Writer::compact();
+    let paint = |code: &str, line: String| if color { format!("\x1b[{}m{}\x1b[0m", code, line) } else { line };
+    let mut out = String::new();
+    for change in changes {
+        let pointer = if change.pointer().is_empty() { "(root)" } else { change.pointer() };
+        let line = match change {
+            Change::Added(_, b) => paint("32", format!("+ {}: {}", pointer, writer.write(b)?)),
+            Change::Removed(_, a) => paint("31", format!("- {}: {}", pointer, writer.write(a)?)),
+            Change::Changed(_, a, b) => paint("33", format!("~ {}: {} -> {}", pointer, writer.write(a)?, writer.write(b)?)),
+        };
+        out.push_str(&line);
+        out.push('\n');
+    }
+    Ok(out)
+}
+
+/// 输出为JSON Patch(RFC 6902)，每行一个操作
+pub fn patch(changes: &[Change], indent: &str) -> Result<String> {
+    if changes.is_empty() {
+        return Ok("[]\n".to_string());
+    }
+    let writer = Writer::compact();
+    let mut ops = vec![];
+    for change in changes {
+        let (op, value) = match change {
+            Change::Added(_, b) => ("add", Some(b)),
+            Change::Removed(_, _) => ("remove", None),
+            Change::Changed(_, _, b) => ("replace", Some(b)),
+        };
+        let mut line = format!("{}{{\"op\":\"{}\",\"path\":", indent, op);
+        write_canonical_string(change.pointer(), &mut line);
+        if let Some(value) = value {
+            line.push_str(",\"value\":");
+            line.push_str(&writer.write(value)?);
+        }
+        line.push('}');
+        ops.push(line);
+    }
+    Ok(format!("[\n{}\n]\n", ops.join(",\n")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::json::from_str;
+
+    fn changes(a: &str, b: &str, tolerance: f64) -> Vec<String> {
+        let (a, b) = (from_str(a).unwrap(), from_str(b).unwrap());
+        human(&diff(&a, &b, tolerance).unwrap(), false).unwrap().lines().map(String::from).collect()
+    }
+
+    #[test]
+    fn tolerance() {
+        assert!(changes("[1.0, 2]", "[1.05, 2]", 0.1).is_empty());
+        assert_eq!(changes("[1.0, 2]", "[1.5, 2]", 0.1), ["~ /0: 1 -> 1.5"]);
+        assert_eq!(changes("1", "1.000001", 0.0), ["~ (root): 1 -> 1.000001"]);
+        // 只作用于数字
+        assert_eq!(changes(r#""1""#, "1", 10.0), [r#"~ (root): "1" -> 1"#]);
+    }
+
+    #[test]
+    fn key_order_and_escapes() {
+        assert!(changes(r#"{"a": 1, "b": [true, null]}"#, r#"{"b": [true, null], "a": 1}"#, 0.0).is_empty());
+        let escaped = format!(r#"{{"{}u0061": "{}u00e9"}}"#, '\\', '\\');
+        assert!(changes(r#"{"a": "é"}"#, &escaped, 0.0).is_empty());
+        // 按键排序输出，键中的/和~转义
+        assert_eq!(
+            changes(r#"{"z": 1, "a/b": 1, "m~": 1}"#, r#"{"z": 2, "a/b": 2, "m~": 1, "c": 3}"#, 0.0),
+            ["~ /a~1b: 1 -> 2", "+ /c: 3", "~ /z: 1 -> 2"],
+        );
+    }
+
+    #[test]
+    fn arrays() {
+        assert_eq!(changes("[1]", "[1, 2, 3]", 0.0), ["+ /1: 2", "+ /2: 3"]);
+        // 删除从后往前
+        assert_eq!(changes("[1, 2, 3]", "[0]", 0.0), ["~ /0: 1 -> 0", "- /2: 3", "- /1: 2"]);
+        assert_eq!(changes(r#"{"a": [1, {"b": 1}]}"#, r#"{"a": [1, {"b": 2}]}"#, 0.0), ["~ /a/1/b: 1 -> 2"]);
+    }
+
+    #[test]
+    fn root_replace() {
+        let (a, b) = (from_str("[1]").unwrap(), from_str(r#"{"a": 1}"#).unwrap());
+        let found = diff(&a, &b, 0.0).unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].pointer(), "");
+        assert_eq!(human(&found, false).unwrap(), "~ (root): [1] -> {\"a\":1}\n");
+        assert_eq!(human(&found, true).unwrap(), "\x1b[33m~ (root): [1] -> {\"a\":1}\x1b[0m\n");
+    }
+
+    #[test]
+    fn json_patch() {
+        let (a, b) = (from_str(r#"{"a": [1, 2, 3], "b": "x", "c/d": 1}"#).unwrap(), from_str(r#"{"a": [1], "b": "y", "e": null}"#).unwrap());
+        let found = diff(&a, &b, 0.0).unwrap();
+        assert_eq!(patch(&found, "  ").unwrap(), "\
+[
+  {\"op\":\"remove\",\"path\":\"/a/2\"},
+  {\"op\":\"remove\",\"path\":\"/a/1\"},
+  {\"op\":\"replace\",\"path\":\"/b\",\"value\":\"y\"},
+  {\"op\":\"remove\",\"path\":\"/c~1d\"},
+  {\"op\":\"add\",\"path\":\"/e\",\"value\":null}
+]
+");
+        assert_eq!(patch(&[], "  ").unwrap(), "[]\n");
+        let root = diff(&from_str("1").unwrap(), &from_str("2").unwrap(), 0.0).unwrap();
+        assert_eq!(patch(&root, "").unwrap(), "[\n{\"op\":\"replace\",\"path\":\"\",\"value\":2}\n]\n");
+    }
+}
@@ -0,0 +1,545 @@
+use std::io::{IsTerminal, Read, Write};
+use std::ops::Range;
+use crate::json::{from_str_with, unescape, Options, ParseError, ParserKind, Span, Tape, Value, Writer};
+
+pub mod diff;
+pub mod query;
+
+// 命令行工具，不依赖第三方库，可以直接用作pre-commit检查
+// - 输入为若干文件，没有文件或文件名为-时读取标准输入
+// - 退出码: 0成功，1输入不是合法的JSON(或fmt --check时未格式化、查询出错)，2用法错误或读写失败
+// - diff与diff(1)相同: 0相同，1有差异，2出错(包括输入不是合法的JSON)
+// - 解析错误输出为 文件:行:列: 消息，并在下一行用^指出位置
+
+pub const USAGE: &str = "\
+usage: json <command> [options] [FILE...]
+       json query [options] <FILTER> [FILE...]
+       json diff [options] <A> <B>
+
+commands:
+    fmt         pretty print
+    minify      print without whitespace
+    validate    check syntax, report errors with file:line:col
+    query       print values matching a jq-like filter, e.g. '.data.results[] | select(.id == 7) | .title'
+    diff        compare two documents, ignoring key order and whitespace
+
+options:
+    --parser <indefinite|definite|table|lr>    parser implementation, default table
+    --lenient                 accept JSON5 (comments, trailing commas, ...)
+    --max-depth <N>           max nesting depth, default 128
+    --indent <N>              indent with N spaces, default 4
+    --tab                     indent with tabs
+    --sort-keys               sort object keys, default keep source order
+    --check                   fmt: fail if a file is not formatted, print nothing
+    -w, --write               fmt, minify: rewrite files in place
+    -q, --quiet               validate: print nothing, only exit code
+    -r, --raw                 query: print strings without quotes
+    -c, --compact             query: print each value on one line
+    --ndjson                  query: read one value per line, print one value per line
+    --tolerance <X>           diff: numbers differing by at most X are equal
+    --patch                   diff: print a JSON Patch (RFC 6902)
+    --color <auto|always|never>    diff: colored output, auto when stdout is a terminal and NO_COLOR is unset
+    -h, --help                print this message
+    --                        treat the remaining arguments as filter and files
+
+FILE defaults to stdin, - also means stdin
+exit status: 0 ok, 1 invalid input, 2 usage or I/O error; diff: 0 same, 1 different, 2 error";
+
+pub const OK: i32 = 0;
+// 输入不合法
+pub const INVALID: i32 = 1;
+// 用法错误或读写失败
+pub const FAILURE: i32 = 2;
+// diff: 两个文档不同
+pub const DIFFERENT: i32 = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Command {
+    Fmt,
+    Minify,
+    Validate,
+    Query,
+    Diff,
+}
+
+#[derive(Debug)]
+pub struct Args {
+    pub command: Command,
+    pub files: Vec<String>,
+    pub parser: ParserKind,
+    pub options: Options,
+    pub indent: String,
+    pub sort_keys: bool,
+    pub check: bool,
+    pub write: bool,
+    pub quiet: bool,
+    // query的过滤器
+    pub filter: Option<String>,
+    pub raw: bool,
+    pub compact: bool,
+    pub ndjson: bool,
+    // diff的选项，color为None时自动判断
+    pub tolerance: f64,
+    pub patch: bool,
+    pub color: Option<bool>,
+    // 选项中有-h或--help
+    pub help: bool,
+}
+
+impl Args {
+    pub fn parse(args: &[String]) -> Result<Args, String> {
+        let mut iter = args.iter();
+        let command = match iter.next().map(|s| s.as_str()) {
+            Some("fmt") => Command::Fmt,
+            Some("minify") => Command::Minify,
+            Some("validate") => Command::Validate,
+            Some("query") => Command::Query,
+            Some("diff") => Command::Diff,
+            Some(other) => return Err(format!("unknown command {}", other)),
+            None => return Err("expect command".to_string()),
+        };
+        let mut args = Args {
+            command,
+            files: vec![],
+            parser: ParserKind::default(),
+            options: Options::default(),
+            indent: "    ".to_string(),
+            sort_keys: false,
+            check: false,
+            write: false,
+            quiet: false,
+            filter: None,
+            raw: false,
+            compact: false,
+            ndjson: false,
+            tolerance: 0.0,
+            patch: false,
+            color: None,
+            help: false,
+        };
+        // --之后都不是选项
+        let mut options = true;
+        while let Some(arg) = iter.next() {
+            if !options || arg == "-" || !arg.starts_with('-') {
+                // query的第一个参数为过滤器
+                if command == Command::Query && args.filter.is_none() {
+                    args.filter = Some(arg.clone());
+                } else {
+                    args.files.push(arg.clone());
+                }
+                continue;
+            }
+            let mut value = |name: &str| iter.next().ok_or_else(|| format!("expect value after {}", name));
+            match arg.as_str() {
+                "--" => options = false,
+                "-h" | "--help" => args.help = true,
+                "--parser" => args.parser = value(arg)?.parse()?,
+                "--lenient" => args.options.lenient = true,
+                "--max-depth" => args.options.max_depth = number(arg, value(arg)?)?,
+                "--indent" => args.indent = " ".repeat(number(arg, value(arg)?)?),
+                "--tab" => args.indent = "\t".to_string(),
+                "--sort-keys" => args.sort_keys = true,
+                "--check" => args.check = true,
+                "-w" | "--write" => args.write = true,
+                "-q" | "--quiet" => args.quiet = true,
+                "-r" | "--raw" => args.raw = true,
+                "-c" | "--compact" => args.compact = true,
+                "--ndjson" => {
+                    args.ndjson = true;
+                    args.compact = true;
+                }
+                "--tolerance" => {
+                    let v = value(arg)?;
+                    args.tolerance = v.parse().ok().filter(|t: &f64| *t >= 0.0)
+                        .ok_or_else(|| format!("expect a non-negative number after --tolerance, found {}", v))?;
+                }
+                "--patch" => args.patch = true,
+                "--color" => args.color = match value(arg)?.as_str() {
+                    "auto" => None,
+                    "always" => Some(true),
+                    "never" => Some(false),
+                    other => return Err(format!("unknown color {}, expect auto | always | never", other)),
+                },
+                _ => return Err(format!("unknown option {}", arg)),
+            }
+        }
+        if args.help {
+            return Ok(args);
+        }
+        if command == Command::Query && args.filter.is_none() {
+            return Err("expect filter".to_string());
+        }
+        if command == Command::Diff && args.files.len() != 2 {
+            return Err(format!("diff expects 2 files, found {}", args.files.len()));
+        }
+        if args.write && args.check {
+            return Err("--write conflicts with --check".to_string());
+        }
+        if args.write && args.files.iter().any(|file| file == "-") {
+            return Err("cannot --write stdin".to_string());
+        }
+        Ok(args)
+    }
+}
+
+fn number(name: &str, value: &str) -> Result<usize, String> {
+    value.parse().map_err(|_| format!("expect a number after {}, found {}", name, value))
+}
+
+/// 一个输入文件及其内容
+pub struct Input {
+    // 标准输入为<stdin>
+    pub name: String,
+    pub path: Option<String>,
+    pub text: String,
+}
+
+impl Input {
+    pub fn read(file: &str) -> Result<Input, String> {
+        let (name, path, bytes) = if file == "-" {
+            let mut bytes = vec![];
+            std::io::stdin().read_to_end(&mut bytes).map_err(|e| format!("<stdin>: {}", e))?;
+            ("<stdin>".to_string(), None, bytes)
+        } else {
+            let bytes = std::fs::read(file).map_err(|e| format!("{}: {}", file, e))?;
+            (file.to_string(), Some(file.to_string()), bytes)
+        };
+        match String::from_utf8(bytes) {
+            Ok(text) => Ok(Input { name, path, text }),
+            Err(e) => Err(format!("{}: invalid UTF-8 at position {}", name, e.utf8_error().valid_up_to())),
+        }
+    }
+}
+
+/// 运行命令，返回退出码
+pub fn run(args: &[String]) -> i32 {
+    if args.is_empty() || args[0] == "-h" || args[0] == "--help" {
+        println!("{}", USAGE);
+        return if args.is_empty() { FAILURE } else { OK };
+    }
+    let args = match Args::parse(args) {
+        Ok(args) => args,
+        Err(msg) => {
+            eprintln!("json: {}\n\n{}", msg, USAGE);
+            return FAILURE;
+        }
+    };
+    if args.help {
+        println!("{}", USAGE);
+        return OK;
+    }
+    let filter = match args.filter.as_deref().map(query::parse).transpose() {
+        Ok(filter) => filter,
+        Err(error) => {
+            let src = args.filter.as_deref().unwrap();
+            eprintln!("{}", diagnostic("<filter>", src, 0..src.len(), &error));
+            return FAILURE;
+        }
+    };
+    if args.command == Command::Diff {
+        return diff(&args);
+    }
+    let files = if args.files.is_empty() { vec!["-".to_string()] } else { args.files.clone() };
+    let mut code = OK;
+    let mut stdout = std::io::stdout().lock();
+    for file in &files {
+        let input = match Input::read(file) {
+            Ok(input) => input,
+            Err(msg) => {
+                eprintln!("json: {}", msg);
+                code = code.max(FAILURE);
+                continue;
+            }
+        };
+        let result = match args.command {
+            Command::Validate => validate(&args, &input),
+            Command::Fmt | Command::Minify => format(&args, &input, &mut stdout),
+            Command::Query => query(&args, filter.as_ref().unwrap(), &input, &mut stdout),
+            Command::Diff => unreachable!(),
+        };
+        code = code.max(result);
+    }
+    code
+}
+
+fn validate(args: &Args, input: &Input) -> i32 {
+    match from_str_with(args.parser, &args.options, &input.text) {
+        Ok(_) => OK,
+        Err(error) => {
+            if !args.quiet {
+                eprintln!("{}", diagnostic(&input.name, &input.text, 0..input.text.len(), &error));
+            }
+            INVALID
+        }
+    }
+}
+
+fn format(args: &Args, input: &Input, stdout: &mut impl Write) -> i32 {
+    if let Err(error) = from_str_with(args.parser, &args.options, &input.text) {
+        eprintln!("{}", diagnostic(&input.name, &input.text, 0..input.text.len(), &error));
+        return INVALID;
+    }
+    // Value的对象没有成员的顺序，由Tape按源文本中的顺序输出，输入已由所选的parser检查过
+    let tape = match args.options.lexer(&input.text).and_then(Tape::parse_lexer) {
+        Ok(tape) => tape,
+        Err(msg) => {
+            eprintln!("{}: {}", input.name, msg);
+            return INVALID;
+        }
+    };
+    let writer = match args.command {
+        Command::Minify => Writer::compact(),
+        _ => Writer::pretty(&args.indent),
+    };
+    let mut output = match writer.sort_keys(args.sort_keys).write(&tape.root()) {
+        Ok(output) => output,
+        Err(msg) => {
+            eprintln!("{}: {}", input.name, msg);
+            return INVALID;
+        }
+    };
+    output.push('\n');
+    if args.check {
+        if output != input.text {
+            eprintln!("{}: not formatted", input.name);
+            return INVALID;
+        }
+        return OK;
+    }
+    let result = match &input.path {
+        Some(path) if args.write => {
+            if output == input.text {
+                return OK;
+            }
+            std::fs::write(path, output)
+        }
+        _ => stdout.write_all(output.as_bytes()),
+    };
+    match result {
+        Ok(()) => OK,
+        Err(e) => {
+            eprintln!("json: {}: {}", input.name, e);
+            FAILURE
+        }
+    }
+}
+
+fn query(args: &Args, filter: &query::Filter, input: &Input, stdout: &mut impl Write) -> i32 {
+    // NDJSON每个非空行为一个值，否则整个输入为一个值
+    let mut parts = vec![];
+    if args.ndjson {
+        let mut start = 0;
+        for line in input.text.split_inclusive('\n') {
+            let end = start + line.trim_end().len();
+            if !line.trim().is_empty() {
+                parts.push(start..end);
+            }
+            start += line.len();
+        }
+    } else {
+        parts.push(0..input.text.len());
+    }
+    let writer = if args.compact { Writer::compact() } else { Writer::pretty(&args.indent) }.sort_keys(args.sort_keys);
+    let mut code = OK;
+    for part in parts {
+        let value = match from_str_with(args.parser, &args.options, &input.text[part.clone()]) {
+            Ok(value) => value,
+            Err(error) => {
+                eprintln!("{}", diagnostic(&input.name, &input.text, part, &error));
+                code = INVALID;
+                continue;
+            }
+        };
+        let result = query::eval(filter, &value).and_then(|outputs| {
+            let mut out = String::new();
+            for output in &outputs {
+                match output {
+                    Value::String(s) if args.raw => out.push_str(&unescape(s)?),
+                    _ => out.push_str(&writer.write(output)?),
+                }
+                out.push('\n');
+            }
+            Ok(out)
+        });
+        match result {
+            Ok(out) => {
+                if let Err(e) = stdout.write_all(out.as_bytes()) {
+                    eprintln!("json: {}", e);
+                    return FAILURE;
+                }
+            }
+            Err(msg) => {
+                let (line, _) = Span { start: part.start, end: part.start }.line_col(&input.text);
+                let name = if args.ndjson { format!("{}:{}", input.name, line) } else { input.name.clone() };
+                eprintln!("{}: {}", name, msg);
+                code = INVALID;
+            }
+        }
+    }
+    code
+}
+
+fn diff(args: &Args) -> i32 {
+    let mut inputs = vec![];
+    for file in &args.files {
+        match Input::read(file) {
+            Ok(input) => inputs.push(input),
+            Err(msg) => {
+                eprintln!("json: {}", msg);
+                return FAILURE;
+            }
+        }
+    }
+    // 与validate相同，不合法的输入逐个报告，但与diff(1)一样以FAILURE退出，与有差异区分
+    let mut values = vec![];
+    for input in &inputs {
+        match from_str_with(args.parser, &args.options, &input.text) {
+            Ok(value) => values.push(value),
+            Err(error) => eprintln!("{}", diagnostic(&input.name, &input.text, 0..input.text.len(), &error)),
+        }
+    }
+    if values.len() < inputs.len() {
+        return FAILURE;
+    }
+    let color = args.color.unwrap_or_else(|| std::io::stdout().is_terminal() && std::env::var_os("NO_COLOR").is_none());
+    let result = diff::diff(&values[0], &values[1], args.tolerance).and_then(|changes| {
+        let out = if args.patch { diff::patch(&changes, &args.indent)? } else { diff::human(&changes, color)? };
+        Ok((changes.is_empty(), out))
+    });
+    match result {
+        Ok((same, out)) => {
+            if let Err(e) = std::io::stdout().write_all(out.as_bytes()) {
+                eprintln!("json: {}", e);
+                return FAILURE;
+            }
+            if same { OK } else { DIFFERENT }
+        }
+        Err(msg) => {
+            eprintln!("json: {}", msg);
+            FAILURE
+        }
+    }
+}
+
+/// 文件:行:列: 消息，之后为出错的行和指向出错位置的^
+/// 错误的位置相对于src[part]，没有位置的错误只输出 文件: 消息
+pub fn diagnostic(name: &str, src: &str, part: Range<usize>, error: &ParseError) -> String {
+    let Some(position) = error.position else {
+        return format!("{}: {}", name, error.message);
+    };
+    // 输入不完整时指向最后一个非空白字符之后，而不是末尾的换行之后
+    let position = part.start + position.min(src[part].trim_end().len());
+    let (line, col) = Span { start: position, end: position }.line_col(src);
+    let text = src.lines().nth(line - 1).unwrap_or("");
+    // ^之前保留制表符，使其与出错的行对齐
+    let pad: String = text.chars().take(col - 1).map(|c| if c == '\t' { '\t' } else { ' ' }).collect();
+    let width = line.to_string().len();
+    format!(
+        "{}:{}:{}: {}\n{:w$} |\n{} | {}\n{:w$} | {}^",
+        name, line, col, error.message, "", line, text, "", pad,
+        w = width,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error(src: &str) -> ParseError {
+        from_str_with(ParserKind::default(), &Options::default(), src).unwrap_err()
+    }
+
+    #[test]
+    fn diagnostic_points_at_token() {
+        let src = "{\n\t\"a\": 1,\n\t\"b\" 2\n}\n";
+        assert_eq!(diagnostic("f.json", src, 0..src.len(), &error(src)), concat!(
+            "f.json:3:6: expect : at position: 17, found number\n",
+            "  |\n",
+            "3 | \t\"b\" 2\n",
+            "  | \t    ^",
+        ));
+    }
+
+    #[test]
+    fn diagnostic_at_end_of_input() {
+        let src = "[1,\n\n";
+        assert_eq!(diagnostic("f.json", src, 0..src.len(), &error(src)), concat!(
+            "f.json:1:4: expect [ | { | string | number | bool | null before end of input\n",
+            "  |\n",
+            "1 | [1,\n",
+            "  |    ^",
+        ));
+    }
+
+    #[test]
+    fn diagnostic_in_part() {
+        let src = "[1]\n[2 3]\n";
+        assert_eq!(diagnostic("f.ndjson", src, 4..9, &error(&src[4..9])), concat!(
+            "f.ndjson:2:4: expect ] | , at position: 4, found number\n",
+            "  |\n",
+            "2 | [2 3]\n",
+            "  |    ^",
+        ));
+    }
+
+    #[test]
+    fn diagnostic_without_position() {
+        let error = ParseError { message: "too large".to_string(), position: None };
+        assert_eq!(diagnostic("f.json", "[]", 0..2, &error), "f.json: too large");
+    }
+
+    #[test]
+    fn filter_diagnostic() {
+        let src = ".a | foo";
+        assert_eq!(diagnostic("<filter>", src, 0..src.len(), &query::parse(src).unwrap_err()), concat!(
+            "<filter>:1:6: unknown function foo at position 5\n",
+            "  |\n",
+            "1 | .a | foo\n",
+            "  |      ^",
+        ));
+    }
+
+    fn parse(args: &[&str]) -> Result<Args, String> {
+        Args::parse(&args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>())
+    }
+
+    #[test]
+    fn help_only_as_option() {
+        assert!(parse(&["fmt", "a.json", "-h"]).unwrap().help);
+        // 缺少过滤器也打印用法
+        assert!(parse(&["query", "--help"]).unwrap().help);
+        let args = parse(&["query", "--", "-h", "-h"]).unwrap();
+        assert!(!args.help);
+        assert_eq!(args.filter.as_deref(), Some("-h"));
+        assert_eq!(args.files, ["-h"]);
+        assert_eq!(parse(&["fmt", "-", "--", "--lenient"]).unwrap().files, ["-", "--lenient"]);
+        assert!(parse(&["fmt", "-x"]).is_err());
+    }
+
+    // 写入临时文件，返回路径
+    fn file(name: &str, text: &str) -> String {
+        let path = std::env::temp_dir().join(format!("json-cli-{}-{}", std::process::id(), name));
+        std::fs::write(&path, text).unwrap();
+        path.to_string_lossy().into_owned()
+    }
+
+    fn run_diff(a: &str, b: &str) -> i32 {
+        run(&["diff".to_string(), a.to_string(), b.to_string()])
+    }
+
+    #[test]
+    fn diff_exit_codes() {
+        let a = file("a.json", r#"{"x": [1, 2], "y": null}"#);
+        let b = file("b.json", r#"{"y": null, "x": [1, 2]}"#);
+        let c = file("c.json", r#"{"x": [1, 3]}"#);
+        let invalid = file("invalid.json", "{\"x\": [1, 2}");
+        assert_eq!(run_diff(&a, &b), OK);
+        assert_eq!(run_diff(&a, &c), DIFFERENT);
+        assert_eq!(run_diff(&a, &invalid), FAILURE);
+        assert_eq!(run_diff(&invalid, &invalid), FAILURE);
+        assert_eq!(run_diff(&a, &format!("{}.missing", a)), FAILURE);
+        for path in [a, b, c, invalid] {
+            std::fs::remove_file(path).unwrap();
+        }
+    }
+}
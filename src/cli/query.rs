@@ -0,0 +1,644 @@
+use std::collections::HashMap;
+use crate::expr::{Expr, OPGParser, TOKEN};
+use crate::json::{unescape, ParseError, Value};
+
+// jq风格的过滤器，对每个输入产生零个或多个输出
+//
+// Pipe   -> Comma ( | Comma )*
+// Comma  -> Or ( , Or )*
+// Or     -> And ( or And )*
+// And    -> Cmp ( and Cmp )*
+// Cmp    -> Arith ( (== | != | < | <= | > | >=) Arith )?
+// Arith  -> -? Term ( (+ | - | * | /) -? Term )*
+// Term   -> Primary ( .name | ."name" | [] | [N] | ["name"] | ? )*
+// Primary-> . | .name | "string" | number | true | false | null | ( Pipe ) | [ Pipe? ] | { Entry, ... }
+//         | select(Pipe) | map(Pipe) | length | keys | not | type | empty
+//
+// Arith的每个Term作为变量，交给expr模块的算符优先分析处理优先级和结合性，求值时各Term的输出取笛卡尔积
+
+type Result<T> = std::result::Result<T, String>;
+type ParseResult<T> = std::result::Result<T, ParseError>;
+
+fn error_at(message: String, position: usize) -> ParseError {
+    ParseError { message, position: Some(position) }
+}
+
+#[derive(Debug, Clone)]
+pub enum Filter<'q> {
+    // .
+    Identity,
+    // .name或."name"，键保持源文本中的写法
+    Field(&'q str),
+    // .[N]，负数从末尾数起
+    Index(i64),
+    // .[]
+    Iterate,
+    // f?
+    Optional(Box<Filter<'q>>),
+    Literal(Value<'q>),
+    Pipe(Box<Filter<'q>>, Box<Filter<'q>>),
+    Comma(Box<Filter<'q>>, Box<Filter<'q>>),
+    // 表达式及其中各变量对应的过滤器
+    Arith(Expr, Vec<Filter<'q>>),
+    Compare(Box<Filter<'q>>, &'static str, Box<Filter<'q>>),
+    And(Box<Filter<'q>>, Box<Filter<'q>>),
+    Or(Box<Filter<'q>>, Box<Filter<'q>>),
+    // [f]
+    Collect(Box<Filter<'q>>),
+    // {key: f, ...}
+    Object(Vec<(&'q str, Filter<'q>)>),
+    // 内置函数及其参数
+    Call(&'q str, Option<Box<Filter<'q>>>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Token<'q> {
+    Dot,
+    // .name
+    Field(&'q str),
+    Ident(&'q str),
+    // 引号之间的内容
+    Str(&'q str),
+    Number(f64),
+    // + - * / == != < <= > >=
+    Op(&'static str),
+    // [ ] ( ) { } | , : ?
+    Punct(char),
+    End,
+}
+
+const OPS: [&str; 10] = ["==", "!=", "<=", ">=", "<", ">", "+", "-", "*", "/"];
+
+// 返回token及其起始位置，最后一个为End
+fn tokenize(src: &str) -> ParseResult<Vec<(Token<'_>, usize)>> {
+    let bytes = src.as_bytes();
+    let mut tokens = vec![];
+    let mut index = 0;
+    let ident = |start: usize| {
+        let len = bytes[start..].iter().take_while(|b| b.is_ascii_alphanumeric() || **b == b'_').count();
+        &src[start..start + len]
+    };
+    let digits = |start: usize| bytes[start..].iter().take_while(|b| b.is_ascii_digit()).count();
+    while index < bytes.len() {
+        let start = index;
+        let token = match bytes[index] {
+            b' ' | b'\t' | b'\n' | b'\r' => {
+                index += 1;
+                continue;
+            }
+            b'.' if bytes.get(index + 1).is_some_and(|b| b.is_ascii_alphabetic() || *b == b'_') => {
+                let name = ident(index + 1);
+                index += 1 + name.len();
+                Token::Field(name)
+            }
+            b'.' => {
+                index += 1;
+                Token::Dot
+            }
+            b'"' => {
+                index += 1;
+                while index < bytes.len() && bytes[index] != b'"' {
+                    index += if bytes[index] == b'\\' { 2 } else { 1 };
+                }
+                if index >= bytes.len() {
+                    return Err(error_at(format!("unterminated string at position {}", start), start));
+                }
+                let s = &src[start + 1..index];
+                unescape(s).map_err(|msg| error_at(format!("{} at position {}", msg, start), start))?;
+                index += 1;
+                Token::Str(s)
+            }
+            b if b.is_ascii_digit() => {
+                index += digits(index);
+                if bytes.get(index) == Some(&b'.') {
+                    index += 1 + digits(index + 1);
+                }
+                // 指数可以带符号，如1e-5
+                if let Some(b'e' | b'E') = bytes.get(index) {
+                    let sign = matches!(bytes.get(index + 1), Some(b'+' | b'-')) as usize;
+                    index += 1 + sign + digits(index + 1 + sign);
+                }
+                let n = src[start..index].parse().map_err(|_| error_at(format!("invalid number at position {}", start), start))?;
+                Token::Number(n)
+            }
+            b if b.is_ascii_alphabetic() || b == b'_' => {
+                let name = ident(index);
+                index += name.len();
+                Token::Ident(name)
+            }
+            b'[' | b']' | b'(' | b')' | b'{' | b'}' | b'|' | b',' | b':' | b'?' => {
+                index += 1;
+                Token::Punct(bytes[start] as char)
+            }
+            _ => match OPS.iter().find(|op| src[index..].starts_with(**op)) {
+                Some(op) => {
+                    index += op.len();
+                    Token::Op(op)
+                }
+                None => return Err(error_at(format!("unexpected token at position {}", start), start)),
+            },
+        };
+        tokens.push((token, start));
+    }
+    tokens.push((Token::End, src.len()));
+    Ok(tokens)
+}
+
+/// 解析过滤器
+pub fn parse(src: &str) -> ParseResult<Filter<'_>> {
+    let mut parser = QueryParser { tokens: tokenize(src)?, index: 0 };
+    let filter = parser.parse_pipe()?;
+    if parser.peek() != Token::End {
+        return Err(parser.error("end of filter"));
+    }
+    Ok(filter)
+}
+
+struct QueryParser<'q> {
+    tokens: Vec<(Token<'q>, usize)>,
+    index: usize,
+}
+
+impl<'q> QueryParser<'q> {
+    fn peek(&self) -> Token<'q> {
+        self.tokens[self.index].0
+    }
+
+    fn next(&mut self) -> Token<'q> {
+        let token = self.peek();
+        if token != Token::End {
+            self.index += 1;
+        }
+        token
+    }
+
+    // 退回next读取的token，next不会越过End
+    fn unread(&mut self, token: Token<'q>) {
+        if token != Token::End {
+            self.index -= 1;
+        }
+    }
+
+    fn error(&self, expected: &str) -> ParseError {
+        let message = match self.tokens[self.index] {
+            (Token::End, _) => format!("expect {} before end of input", expected),
+            (_, position) => format!("expect {} at position {}", expected, position),
+        };
+        error_at(message, self.tokens[self.index].1)
+    }
+
+    fn expect(&mut self, punct: char) -> ParseResult<()> {
+        if self.peek() != Token::Punct(punct) {
+            return Err(self.error(&punct.to_string()));
+        }
+        self.next();
+        Ok(())
+    }
+
+    fn parse_pipe(&mut self) -> ParseResult<Filter<'q>> {
+        let mut filter = self.parse_comma()?;
+        while self.peek() == Token::Punct('|') {
+            self.next();
+            filter = Filter::Pipe(Box::new(filter), Box::new(self.parse_comma()?));
+        }
+        Ok(filter)
+    }
+
+    fn parse_comma(&mut self) -> ParseResult<Filter<'q>> {
+        let mut filter = self.parse_or()?;
+        while self.peek() == Token::Punct(',') {
+            self.next();
+            filter = Filter::Comma(Box::new(filter), Box::new(self.parse_or()?));
+        }
+        Ok(filter)
+    }
+
+    fn parse_or(&mut self) -> ParseResult<Filter<'q>> {
+        let mut filter = self.parse_and()?;
+        while self.peek() == Token::Ident("or") {
+            self.next();
+            filter = Filter::Or(Box::new(filter), Box::new(self.parse_and()?));
+        }
+        Ok(filter)
+    }
+
+    fn parse_and(&mut self) -> ParseResult<Filter<'q>> {
+        let mut filter = self.parse_compare()?;
+        while self.peek() == Token::Ident("and") {
+            self.next();
+            filter = Filter::And(Box::new(filter), Box::new(self.parse_compare()?));
+        }
+        Ok(filter)
+    }
+
+    fn parse_compare(&mut self) -> ParseResult<Filter<'q>> {
+        let filter = self.parse_arith()?;
+        match self.peek() {
+            Token::Op(op @ ("==" | "!=" | "<" | "<=" | ">" | ">=")) => {
+                self.next();
+                Ok(Filter::Compare(Box::new(filter), op, Box::new(self.parse_arith()?)))
+            }
+            _ => Ok(filter),
+        }
+    }
+
+    // 收集运算符和各Term，交给OPGParser
+    fn parse_arith(&mut self) -> ParseResult<Filter<'q>> {
+        let start = self.tokens[self.index].1;
+        let mut tokens = vec![];
+        let mut operands = vec![];
+        loop {
+            while self.peek() == Token::Op("-") {
+                self.next();
+                tokens.push(TOKEN::SUB);
+            }
+            // 数字直接交给expr，其余Term作为变量
+            match self.parse_term()? {
+                Filter::Literal(Value::Number(n)) => tokens.push(TOKEN::NUMBER(n)),
+                term => {
+                    tokens.push(TOKEN::VAR(operands.len()));
+                    operands.push(term);
+                }
+            }
+            tokens.push(match self.peek() {
+                Token::Op("+") => TOKEN::ADD,
+                Token::Op("-") => TOKEN::SUB,
+                Token::Op("*") => TOKEN::MUL,
+                Token::Op("/") => TOKEN::DIV,
+                _ => break,
+            });
+            self.next();
+        }
+        match tokens.as_slice() {
+            [TOKEN::VAR(_)] => return Ok(operands.pop().unwrap()),
+            [TOKEN::NUMBER(n)] => return Ok(Filter::Literal(Value::Number(*n))),
+            _ => {}
+        }
+        let expr = OPGParser::new(tokens.into_iter().map(Ok)).parse().map_err(|msg| error_at(msg, start))?;
+        Ok(Filter::Arith(expr, operands))
+    }
+
+    fn parse_term(&mut self) -> ParseResult<Filter<'q>> {
+        let mut filter = self.parse_primary()?;
+        loop {
+            let suffix = match self.peek() {
+                Token::Field(name) => {
+                    self.next();
+                    Filter::Field(name)
+                }
+                Token::Dot if matches!(self.tokens[self.index + 1].0, Token::Str(_) | Token::Punct('[')) => {
+                    self.next();
+                    continue;
+                }
+                Token::Str(name) if self.tokens[self.index - 1].0 == Token::Dot => {
+                    self.next();
+                    Filter::Field(name)
+                }
+                Token::Punct('[') => self.parse_bracket()?,
+                Token::Punct('?') => {
+                    self.next();
+                    filter = Filter::Optional(Box::new(filter));
+                    continue;
+                }
+                _ => return Ok(filter),
+            };
+            filter = Filter::Pipe(Box::new(filter), Box::new(suffix));
+        }
+    }
+
+    // [] [N] [-N] ["name"]
+    fn parse_bracket(&mut self) -> ParseResult<Filter<'q>> {
+        self.expect('[')?;
+        let filter = match self.next() {
+            Token::Punct(']') => return Ok(Filter::Iterate),
+            Token::Str(name) => Filter::Field(name),
+            Token::Number(n) if n.fract() == 0.0 => Filter::Index(n as i64),
+            Token::Op("-") => match self.next() {
+                Token::Number(n) if n.fract() == 0.0 => Filter::Index(-(n as i64)),
+                token => {
+                    self.unread(token);
+                    return Err(self.error("integer"));
+                }
+            },
+            token => {
+                self.unread(token);
+                return Err(self.error("] | integer | string"));
+            }
+        };
+        self.expect(']')?;
+        Ok(filter)
+    }
+
+    fn parse_primary(&mut self) -> ParseResult<Filter<'q>> {
+        let position = self.tokens[self.index].1;
+        Ok(match self.next() {
+            Token::Dot => match self.peek() {
+                Token::Str(name) => {
+                    self.next();
+                    Filter::Field(name)
+                }
+                _ => Filter::Identity,
+            },
+            Token::Field(name) => Filter::Field(name),
+            Token::Str(s) => Filter::Literal(Value::String(s)),
+            Token::Number(n) => Filter::Literal(Value::Number(n)),
+            Token::Ident("true") => Filter::Literal(Value::Bool(true)),
+            Token::Ident("false") => Filter::Literal(Value::Bool(false)),
+            Token::Ident("null") => Filter::Literal(Value::Null),
+            Token::Ident(name @ ("select" | "map")) => {
+                self.expect('(')?;
+                let arg = self.parse_pipe()?;
+                self.expect(')')?;
+                Filter::Call(name, Some(Box::new(arg)))
+            }
+            Token::Ident(name @ ("length" | "keys" | "not" | "type" | "empty")) => Filter::Call(name, None),
+            Token::Ident(name) => return Err(error_at(format!("unknown function {} at position {}", name, position), position)),
+            Token::Punct('(') => {
+                let filter = self.parse_pipe()?;
+                self.expect(')')?;
+                filter
+            }
+            Token::Punct('[') if self.peek() == Token::Punct(']') => {
+                self.next();
+                Filter::Literal(Value::Array(vec![]))
+            }
+            Token::Punct('[') => {
+                let filter = self.parse_pipe()?;
+                self.expect(']')?;
+                Filter::Collect(Box::new(filter))
+            }
+            Token::Punct('{') => self.parse_object()?,
+            token => {
+                self.unread(token);
+                return Err(self.error("filter"));
+            }
+        })
+    }
+
+    // {key: f, "key": f, key}，{key}即{key: .key}
+    fn parse_object(&mut self) -> ParseResult<Filter<'q>> {
+        let mut entries = vec![];
+        while self.peek() != Token::Punct('}') {
+            let key = match self.next() {
+                Token::Ident(key) | Token::Str(key) => key,
+                token => {
+                    self.unread(token);
+                    return Err(self.error("key"));
+                }
+            };
+            let value = if self.peek() == Token::Punct(':') {
+                self.next();
+                self.parse_or()?
+            } else {
+                Filter::Field(key)
+            };
+            entries.push((key, value));
+            if self.peek() != Token::Punct(',') {
+                break;
+            }
+            self.next();
+        }
+        self.expect('}')?;
+        Ok(Filter::Object(entries))
+    }
+}
+
+// false和null为假，其余为真
+fn truthy(value: &Value) -> bool {
+    !matches!(value, Value::Bool(false) | Value::Null)
+}
+
+// 对象的值按键还原转义后的内容排序，同Writer输出Value时的顺序
+fn values<'v>(obj: &HashMap<&'v str, Value<'v>>) -> Result<Vec<Value<'v>>> {
+    let mut pairs = obj.iter().map(|(k, v)| Ok((unescape(k)?, v))).collect::<Result<Vec<_>>>()?;
+    pairs.sort();
+    Ok(pairs.into_iter().map(|(_, v)| v.clone()).collect())
+}
+
+// 每个过滤器的输出取笛卡尔积
+fn product<'v>(filters: &[&Filter<'v>], input: &Value<'v>) -> Result<Vec<Vec<Value<'v>>>> {
+    let mut rows = vec![vec![]];
+    for filter in filters {
+        let outputs = eval(filter, input)?;
+        let mut next = Vec::with_capacity(rows.len() * outputs.len());
+        for row in &rows {
+            for output in &outputs {
+                let mut row = row.clone();
+                row.push(output.clone());
+                next.push(row);
+            }
+        }
+        rows = next;
+    }
+    Ok(rows)
+}
+
+/// 对输入求值，返回所有输出
+pub fn eval<'v>(filter: &Filter<'v>, input: &Value<'v>) -> Result<Vec<Value<'v>>> {
+    Ok(match filter {
+        Filter::Identity => vec![input.clone()],
+        Filter::Field(key) => match input {
+            Value::Object(_) => vec![input.get(&unescape(key)?).cloned().unwrap_or(Value::Null)],
+            Value::Null => vec![Value::Null],
+            _ => return Err(format!("cannot index {} with \"{}\"", input.type_name(), key)),
+        },
+        Filter::Index(i) => match input {
+            Value::Array(arr) => {
+                let i = if *i < 0 { arr.len() as i64 + i } else { *i };
+                vec![usize::try_from(i).ok().and_then(|i| arr.get(i)).cloned().unwrap_or(Value::Null)]
+            }
+            Value::Null => vec![Value::Null],
+            _ => return Err(format!("cannot index {} with number", input.type_name())),
+        },
+        Filter::Iterate => match input {
+            Value::Array(arr) => arr.clone(),
+            Value::Object(obj) => values(obj)?,
+            _ => return Err(format!("cannot iterate over {}", input.type_name())),
+        },
+        Filter::Optional(filter) => eval(filter, input).unwrap_or_default(),
+        Filter::Literal(value) => vec![value.clone()],
+        Filter::Pipe(a, b) => {
+            let mut outputs = vec![];
+            for value in eval(a, input)? {
+                outputs.extend(eval(b, &value)?);
+            }
+            outputs
+        }
+        Filter::Comma(a, b) => {
+            let mut outputs = eval(a, input)?;
+            outputs.extend(eval(b, input)?);
+            outputs
+        }
+        Filter::Arith(expr, operands) => {
+            let operands: Vec<&Filter> = operands.iter().collect();
+            let mut outputs = vec![];
+            for row in product(&operands, input)? {
+                let mut vars = Vec::with_capacity(row.len());
+                for value in &row {
+                    match value {
+                        Value::Number(n) => vars.push(*n),
+                        _ => return Err(format!("cannot apply arithmetic to {}", value.type_name())),
+                    }
+                }
+                outputs.push(Value::Number(expr.eval(&vars)?));
+            }
+            outputs
+        }
+        Filter::Compare(a, op, b) => {
+            let mut outputs = vec![];
+            for row in product(&[a.as_ref(), b.as_ref()], input)? {
+                let ordering = row[0].cmp(&row[1]);
+                outputs.push(Value::Bool(match *op {
+                    "==" => ordering.is_eq(),
+                    "!=" => ordering.is_ne(),
+                    "<" => ordering.is_lt(),
+                    "<=" => ordering.is_le(),
+                    ">" => ordering.is_gt(),
+                    _ => ordering.is_ge(),
+                }));
+            }
+            outputs
+        }
+        // 短路求值: 左边已能确定结果时不再求右边
+        Filter::And(a, b) | Filter::Or(a, b) => {
+            let and = matches!(filter, Filter::And(..));
+            let mut outputs = vec![];
+            for left in eval(a, input)? {
+                if truthy(&left) != and {
+                    outputs.push(Value::Bool(!and));
+                    continue;
+                }
+                for right in eval(b, input)? {
+                    outputs.push(Value::Bool(truthy(&right)));
+                }
+            }
+            outputs
+        }
+        Filter::Collect(filter) => vec![Value::Array(eval(filter, input)?)],
+        Filter::Object(entries) => {
+            let filters: Vec<&Filter> = entries.iter().map(|(_, f)| f).collect();
+            product(&filters, input)?
+                .into_iter()
+                .map(|row| Value::from_members(entries.iter().map(|(k, _)| *k).zip(row).collect()))
+                .collect()
+        }
+        Filter::Call(name, arg) => call(name, arg.as_deref(), input)?,
+    })
+}
+
+fn call<'v>(name: &str, arg: Option<&Filter<'v>>, input: &Value<'v>) -> Result<Vec<Value<'v>>> {
+    Ok(match (name, arg) {
+        ("select", Some(filter)) => eval(filter, input)?
+            .iter()
+            .filter(|value| truthy(value))
+            .map(|_| input.clone())
+            .collect(),
+        ("map", Some(filter)) => {
+            let mut outputs = vec![];
+            for value in eval(&Filter::Iterate, input)? {
+                outputs.extend(eval(filter, &value)?);
+            }
+            vec![Value::Array(outputs)]
+        }
+        ("length", None) => vec![Value::Number(match input {
+            Value::String(s) => unescape(s)?.chars().count() as f64,
+            Value::Array(arr) => arr.len() as f64,
+            Value::Object(obj) => obj.len() as f64,
+            Value::Number(n) => n.abs(),
+            Value::Null => 0.0,
+            Value::Bool(_) => return Err("bool has no length".to_string()),
+        })],
+        ("keys", None) => match input {
+            Value::Object(obj) => {
+                let mut keys: Vec<Value> = obj.keys().map(|k| Value::String(k)).collect();
+                keys.sort();
+                vec![Value::Array(keys)]
+            }
+            Value::Array(arr) => vec![Value::Array((0..arr.len()).map(|i| Value::Number(i as f64)).collect())],
+            _ => return Err(format!("{} has no keys", input.type_name())),
+        },
+        ("not", None) => vec![Value::Bool(!truthy(input))],
+        ("type", None) => vec![Value::String(input.type_name())],
+        ("empty", None) => vec![],
+        _ => return Err(format!("unknown function {}", name)),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::json::from_str;
+
+    const INPUT: &str = r#"{"items": [{"id": 1, "x": 0.5, "tags": ["a"]}, {"id": 2, "x": 2e-6, "tags": []}, {"id": 3, "x": null}], "name": "aé"}"#;
+
+    fn run(filter: &str) -> Vec<String> {
+        let value = from_str(INPUT).unwrap();
+        let filter = parse(filter).unwrap();
+        eval(&filter, &value).unwrap().iter().map(|v| v.to_canonical_string().unwrap()).collect()
+    }
+
+    #[test]
+    fn paths() {
+        assert_eq!(run(".name"), [r#""aé""#]);
+        assert_eq!(run(r#".items[0].id, .items[-1]["id"], ."name""#), ["1", "3", r#""aé""#]);
+        assert_eq!(run(".items[].id"), ["1", "2", "3"]);
+        assert_eq!(run(".items[5], .missing.x"), ["null", "null"]);
+        assert_eq!(run(".name[0]?, 1"), ["1"]);
+    }
+
+    #[test]
+    fn exponent_numbers() {
+        assert_eq!(run(".items[] | select(.x < 1e-5) | .id"), ["2", "3"]);
+        assert_eq!(run(".items[] | select(.x > 1E+3 or .x == 5e-1) | .id"), ["1"]);
+        assert_eq!(run("2.5e2 + 1"), ["251"]);
+        assert!(parse("1e").is_err());
+        assert!(parse("1e-").is_err());
+    }
+
+    #[test]
+    fn arithmetic() {
+        assert_eq!(run("1 + 2 * 3 - -4 / 2"), ["9"]);
+        assert_eq!(run(".items[0].id - .items[1].id - 1"), ["-2"]);
+        assert_eq!(run("[.items[].id] | map(. * 10)"), ["[10,20,30]"]);
+        assert_eq!(run("(.items[0].id, .items[1].id) + 1"), ["2", "3"]);
+    }
+
+    #[test]
+    fn functions_and_construction() {
+        assert_eq!(run(".items | length"), ["3"]);
+        assert_eq!(run(".name | length"), ["2"]);
+        assert_eq!(run(".items[0] | keys"), [r#"["id","tags","x"]"#]);
+        assert_eq!(run(".items[2].x | type, not"), [r#""null""#, "true"]);
+        assert_eq!(run("[.items[] | select(.tags | length > 0) | .id]"), ["[1]"]);
+        assert_eq!(run(".items[0] | {id, first: .tags[0]}"), [r#"{"first":"a","id":1}"#]);
+        assert_eq!(run("[empty], []"), ["[]", "[]"]);
+        // 对象的值按键的内容排列，与构造对象时的顺序无关
+        assert_eq!(run("{z: 1, a: 2, m: 3} | [.[]]"), ["[2,3,1]"]);
+        assert_eq!(run(".items[0] | [.[]] | length"), ["3"]);
+        assert_eq!(run(r#".name == "aé" and .name != "b""#), ["true"]);
+    }
+
+    #[test]
+    fn eval_errors() {
+        let value = from_str(INPUT).unwrap();
+        let error = |filter: &str| eval(&parse(filter).unwrap(), &value).unwrap_err();
+        assert_eq!(error(".items.id"), "cannot index array with \"id\"");
+        assert_eq!(error(".name[]"), "cannot iterate over string");
+        assert_eq!(error(".name + 1"), "cannot apply arithmetic to string");
+        assert_eq!(error("1 / (.items[0].id - 1)"), "1 cannot be divided by zero");
+    }
+
+    #[test]
+    fn parse_errors() {
+        let error = |filter: &str| {
+            let e = parse(filter).unwrap_err();
+            (e.message, e.position)
+        };
+        assert_eq!(error(".a |"), ("expect filter before end of input".to_string(), Some(4)));
+        assert_eq!(error(".a)"), ("expect end of filter at position 2".to_string(), Some(2)));
+        assert_eq!(error("select(.a"), ("expect ) before end of input".to_string(), Some(9)));
+        assert_eq!(error(".[1.5]"), ("expect ] | integer | string at position 2".to_string(), Some(2)));
+        assert_eq!(error(r#""abc"#), ("unterminated string at position 0".to_string(), Some(0)));
+        assert_eq!(error(". ; ."), ("unexpected token at position 2".to_string(), Some(2)));
+    }
+}
@@ -1,5 +1,5 @@
 use std::str::from_utf8;
-use crate::expr::TOKEN;
+use crate::expr::{Result, TOKEN};
 
 pub struct Lexer<'s> {
     expr: &'s [u8],
@@ -13,21 +13,21 @@ pub struct Lexer<'s> {
 // terminate symbol: NUMBER, +-*/()
 
 impl Lexer<'_> {
-    pub fn new(expr: &str) -> Lexer {
+    pub fn new(expr: &str) -> Lexer<'_> {
         Lexer {
             expr: expr.as_bytes(),
             index: 0,
             current_token_size: 0,
         }
     }
-    pub fn lex(&mut self) -> Option<TOKEN> {
-        if self.index >= self.expr.len() {
-            return None;
-        }
+    pub fn lex(&mut self) -> Result<Option<TOKEN>> {
         const BLANK: [u8; 4] = [b' ', b'\n', b'\t', b'\r'];
-        while BLANK.contains(&self.expr[self.index]) {
+        while self.index < self.expr.len() && BLANK.contains(&self.expr[self.index]) {
             self.index += 1;
         }
+        if self.index >= self.expr.len() {
+            return Ok(None);
+        }
         self.current_token_size = self.index;
         let ret = match self.expr[self.index] {
             b'(' => {
@@ -60,14 +60,21 @@ impl Lexer<'_> {
                     self.index += 1;
                 }
                 let end = self.index;
-                let err_msg = format!("invalid number: {:?}", &self.expr[start..end]);
-                let num = from_utf8(&self.expr[start..end])
-                    .expect(&err_msg)
-                    .parse::<f64>().expect(&err_msg);
+                let num = from_utf8(&self.expr[start..end]).unwrap()
+                    .parse::<f64>()
+                    .map_err(|_| format!("invalid number at position {}", start))?;
                 TOKEN::NUMBER(num)
             }
-            _ => return None,
+            _ => return Err(format!("unexpected token at position {}", self.index)),
         };
-        return Some(ret);
+        Ok(Some(ret))
     }
-}
\ No newline at end of file
+}
+
+impl Iterator for Lexer<'_> {
+    type Item = Result<TOKEN>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.lex().transpose()
+    }
+}
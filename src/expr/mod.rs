@@ -1,4 +1,5 @@
 mod lexer;
+pub use lexer::Lexer;
 type Result<T> = std::result::Result<T, String>;
 #[derive(Debug)]
 pub enum TOKEN {
@@ -8,15 +9,43 @@ pub enum TOKEN {
     SUB,
     MUL,
     DIV,
+    // 一元负号，lexer只产生SUB，由parser按上下文区分
+    NEG,
     NUMBER(f64),
+    // 变量，由调用者提供token流时使用(如query中的路径)，求值时按编号取值
+    VAR(usize),
 }
 
+#[derive(Debug, Clone)]
 pub enum Expr {
-    Add(Expr, Expr),
-    Sub(Expr, Expr),
-    Mul(Expr, Expr),
-    Div(Expr, Expr),
+    Add(Box<Expr>, Box<Expr>),
+    Sub(Box<Expr>, Box<Expr>),
+    Mul(Box<Expr>, Box<Expr>),
+    Div(Box<Expr>, Box<Expr>),
+    Neg(Box<Expr>),
     Number(f64),
+    Var(usize),
+}
+
+impl Expr {
+    /// vars[i]为VAR(i)的值，除数为0时报错
+    pub fn eval(&self, vars: &[f64]) -> Result<f64> {
+        Ok(match self {
+            Expr::Add(a, b) => a.eval(vars)? + b.eval(vars)?,
+            Expr::Sub(a, b) => a.eval(vars)? - b.eval(vars)?,
+            Expr::Mul(a, b) => a.eval(vars)? * b.eval(vars)?,
+            Expr::Div(a, b) => {
+                let (a, b) = (a.eval(vars)?, b.eval(vars)?);
+                if b == 0.0 {
+                    return Err(format!("{} cannot be divided by zero", a));
+                }
+                a / b
+            }
+            Expr::Neg(a) => -a.eval(vars)?,
+            Expr::Number(n) => *n,
+            Expr::Var(i) => *vars.get(*i).ok_or_else(|| format!("undefined variable {}", i))?,
+        })
+    }
 }
 
 // E -> A + A | A - A
@@ -24,15 +53,16 @@ pub enum Expr {
 // A -> (E) | NUMBER
 // B -> (E) | NUMBER
 
-
-struct OPGParser<'a> {
-    lexer: lexer::Lexer<'a>,
+// 算符优先分析: 运算符栈和表达式栈，栈底的(与输入结束时补上的)配对
+// 读入运算符时先归约栈顶优先级不低于它的运算符(左结合)，一元负号优先级最高且右结合
+pub struct OPGParser<I> {
+    lexer: I,
     stack: Vec<TOKEN>,
     expr_stack: Vec<Expr>,
 }
 
-impl OPGParser<'_> {
-    pub fn new(lexer: lexer::Lexer) -> OPGParser {
+impl<I: Iterator<Item=Result<TOKEN>>> OPGParser<I> {
+    pub fn new(lexer: I) -> OPGParser<I> {
         OPGParser {
             lexer,
             stack: vec![TOKEN::LPAREN],
@@ -40,5 +70,144 @@ impl OPGParser<'_> {
         }
     }
 
-    pub fn parse(&mut self) -> Result<Expr> {}
-}
\ No newline at end of file
+    pub fn parse(&mut self) -> Result<Expr> {
+        // 上一个是操作数，此时应读入二元运算符或)
+        let mut operand = false;
+        loop {
+            let (token, end) = match self.lexer.next().transpose()? {
+                Some(token) => (token, false),
+                None => (TOKEN::RPAREN, true),
+            };
+            match token {
+                TOKEN::NUMBER(_) | TOKEN::VAR(_) | TOKEN::LPAREN if operand => {
+                    return Err(format!("expect operator, found {:?}", token));
+                }
+                TOKEN::NUMBER(n) => {
+                    self.expr_stack.push(Expr::Number(n));
+                    operand = true;
+                }
+                TOKEN::VAR(i) => {
+                    self.expr_stack.push(Expr::Var(i));
+                    operand = true;
+                }
+                TOKEN::LPAREN => self.stack.push(TOKEN::LPAREN),
+                TOKEN::SUB | TOKEN::NEG if !operand => self.stack.push(TOKEN::NEG),
+                _ if !operand => {
+                    return Err(if end {
+                        "expect operand before end of expression".to_string()
+                    } else {
+                        format!("expect operand, found {:?}", token)
+                    });
+                }
+                TOKEN::RPAREN => {
+                    while !matches!(self.stack.last(), Some(TOKEN::LPAREN) | None) {
+                        self.reduce()?;
+                    }
+                    if self.stack.pop().is_none() {
+                        return Err("unmatched )".to_string());
+                    }
+                    if end {
+                        if !self.stack.is_empty() {
+                            return Err("unmatched (".to_string());
+                        }
+                        return self.expr_stack.pop().ok_or_else(|| "empty expression".to_string());
+                    }
+                }
+                op => {
+                    while self.stack.last().is_some_and(|top| priority(top) >= priority(&op)) {
+                        self.reduce()?;
+                    }
+                    self.stack.push(op);
+                    operand = false;
+                }
+            }
+        }
+    }
+
+    // 弹出栈顶的运算符及其操作数，压入归约得到的表达式
+    fn reduce(&mut self) -> Result<()> {
+        let op = self.stack.pop().unwrap();
+        let b = Box::new(self.expr_stack.pop().ok_or("expect operand")?);
+        let expr = if let TOKEN::NEG = op {
+            Expr::Neg(b)
+        } else {
+            let a = Box::new(self.expr_stack.pop().ok_or("expect operand")?);
+            match op {
+                TOKEN::ADD => Expr::Add(a, b),
+                TOKEN::SUB => Expr::Sub(a, b),
+                TOKEN::MUL => Expr::Mul(a, b),
+                TOKEN::DIV => Expr::Div(a, b),
+                _ => unreachable!(),
+            }
+        };
+        self.expr_stack.push(expr);
+        Ok(())
+    }
+}
+
+fn priority(token: &TOKEN) -> u8 {
+    match token {
+        TOKEN::ADD | TOKEN::SUB => 1,
+        TOKEN::MUL | TOKEN::DIV => 2,
+        TOKEN::NEG => 3,
+        _ => 0,
+    }
+}
+
+/// 解析算术表达式
+pub fn parse(expr: &str) -> Result<Expr> {
+    OPGParser::new(Lexer::new(expr)).parse()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn eval(expr: &str) -> f64 {
+        parse(expr).unwrap().eval(&[]).unwrap()
+    }
+
+    fn error(expr: &str) -> String {
+        parse(expr).unwrap_err()
+    }
+
+    #[test]
+    fn priority_and_associativity() {
+        assert_eq!(eval("1 + 2 * 3"), 7.0);
+        assert_eq!(eval("(1 + 2) * 3"), 9.0);
+        assert_eq!(eval("8 - 3 - 2"), 3.0);
+        assert_eq!(eval("16 / 4 / 2"), 2.0);
+        assert_eq!(eval("2 * (3 + 4) - 10 / 4"), 11.5);
+        assert_eq!(format!("{:?}", parse("1 - 2 - 3").unwrap()),
+                   "Sub(Sub(Number(1.0), Number(2.0)), Number(3.0))");
+    }
+
+    #[test]
+    fn negation() {
+        assert_eq!(eval("-2 * 3"), -6.0);
+        assert_eq!(eval("2 * -3"), -6.0);
+        assert_eq!(eval("--2"), 2.0);
+        assert_eq!(eval("-(1 + 2)"), -3.0);
+        assert_eq!(eval("1 - -1"), 2.0);
+    }
+
+    #[test]
+    fn variables() {
+        let tokens = [TOKEN::VAR(0), TOKEN::MUL, TOKEN::LPAREN, TOKEN::VAR(1), TOKEN::SUB, TOKEN::NUMBER(1.0), TOKEN::RPAREN];
+        let expr = OPGParser::new(tokens.into_iter().map(Ok)).parse().unwrap();
+        assert_eq!(expr.eval(&[2.0, 5.0]).unwrap(), 8.0);
+        assert_eq!(expr.eval(&[2.0]).unwrap_err(), "undefined variable 1");
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(error("1 +"), "expect operand before end of expression");
+        assert_eq!(error(""), "expect operand before end of expression");
+        assert_eq!(error("1 2"), "expect operator, found NUMBER(2.0)");
+        assert_eq!(error("* 2"), "expect operand, found MUL");
+        assert_eq!(error("(1 + 2"), "unmatched (");
+        assert_eq!(error("1 + 2)"), "unmatched )");
+        assert_eq!(error("1 $ 2"), "unexpected token at position 2");
+        assert_eq!(parse("1 / (2 - 2)").unwrap().eval(&[]).unwrap_err(), "1 cannot be divided by zero");
+    }
+}
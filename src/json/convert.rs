@@ -0,0 +1,380 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::{Display, Formatter};
+use std::hash::Hash;
+use super::{Value, canonical_number, unescape, write_canonical_string};
+
+pub use json_derive::{FromJson, ToJson};
+
+// Rust类型与JSON之间的转换
+// - FromJson从解析得到的Value构造Rust值，失败时的错误带有出错位置，如 data.results[0].id: expect number, found string
+// - ToJson直接输出紧凑的JSON文本。Value::String保存的是转义后的原文，无法引用新生成的字符串，因此不经过Value
+//
+// 为结构体实现时可以使用#[derive(ToJson, FromJson)]，字段上支持以下属性
+// - #[json(rename = "name")] 使用另一个键名
+// - #[json(default)] 缺少该键时使用Default::default()
+// - #[json(skip)] 不输出该字段，读取时使用Default::default()
+// - #[json(flatten)] 该字段的成员直接展开到外层对象中
+// 生成的代码通过::json引用本模块，在本crate内部使用时以#[json(crate = "crate::json")]指定
+
+#[derive(Debug, Clone, PartialEq)]
+enum Segment {
+    Key(String),
+    Index(usize),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConvertError {
+    // 由内向外记录，输出时反转
+    path: Vec<Segment>,
+    message: String,
+}
+
+impl ConvertError {
+    pub fn new(message: String) -> ConvertError {
+        ConvertError {
+            path: vec![],
+            message,
+        }
+    }
+
+    pub fn expected(expected: &str, found: &Value) -> ConvertError {
+        ConvertError::new(format!("expect {}, found {}", expected, found.type_name()))
+    }
+
+    pub fn missing_field(name: &str) -> ConvertError {
+        ConvertError::new(format!("missing field {:?}", name))
+    }
+
+    pub fn at_key(mut self, key: &str) -> ConvertError {
+        self.path.push(Segment::Key(key.to_string()));
+        self
+    }
+
+    pub fn at_index(mut self, index: usize) -> ConvertError {
+        self.path.push(Segment::Index(index));
+        self
+    }
+}
+
+impl Display for ConvertError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for (i, segment) in self.path.iter().rev().enumerate() {
+            match segment {
+                Segment::Key(key) if i == 0 => write!(f, "{}", key)?,
+                Segment::Key(key) => write!(f, ".{}", key)?,
+                Segment::Index(index) => write!(f, "[{}]", index)?,
+            }
+        }
+        if !self.path.is_empty() {
+            write!(f, ": ")?;
+        }
+        write!(f, "{}", self.message)
+    }
+}
+
+pub trait FromJson: Sized {
+    fn from_json(value: &Value) -> Result<Self, ConvertError>;
+
+    /// 对象中缺少该字段时的取值，None表示该字段必需
+    fn missing() -> Option<Self> {
+        None
+    }
+}
+
+pub trait ToJson {
+    fn write_json(&self, out: &mut String);
+
+    /// flatten时写出对象的成员，不含外层的`{}`，`first`表示尚未写出任何成员
+    /// 不是对象的值(如None对应的null)没有成员，不输出任何内容
+    fn write_members(&self, out: &mut String, first: &mut bool) {
+        let mut text = String::new();
+        self.write_json(&mut text);
+        let members = match text.strip_prefix('{').and_then(|t| t.strip_suffix('}')) {
+            Some(members) => members,
+            None => return,
+        };
+        if !members.is_empty() {
+            if !*first {
+                out.push(',');
+            }
+            out.push_str(members);
+            *first = false;
+        }
+    }
+
+    fn to_json(&self) -> String {
+        let mut out = String::new();
+        self.write_json(&mut out);
+        out
+    }
+}
+
+/// 按还原转义后的键名取对象的成员
+pub fn field<'v, 'a>(value: &'v Value<'a>, name: &str) -> Option<&'v Value<'a>> {
+    value.get(name)
+}
+
+/// flatten的字段只能看到没有被其他字段使用的成员
+pub fn unclaimed<'a>(value: &Value<'a>, claimed: &[&str]) -> Value<'a> {
+    match value {
+        Value::Object(obj) => Value::Object(obj.iter()
+            .filter(|(k, _)| !unescape(k).is_ok_and(|k| claimed.contains(&k.as_ref())))
+            .map(|(k, v)| (*k, v.clone()))
+            .collect()),
+        _ => value.clone(),
+    }
+}
+
+/// 写出对象成员的键和`:`，必要时先写`,`
+pub fn write_key(out: &mut String, first: &mut bool, key: &str) {
+    if !*first {
+        out.push(',');
+    }
+    *first = false;
+    write_canonical_string(key, out);
+    out.push(':');
+}
+
+impl FromJson for bool {
+    fn from_json(value: &Value) -> Result<Self, ConvertError> {
+        match value {
+            Value::Bool(b) => Ok(*b),
+            _ => Err(ConvertError::expected("bool", value)),
+        }
+    }
+}
+
+impl ToJson for bool {
+    fn write_json(&self, out: &mut String) {
+        out.push_str(if *self { "true" } else { "false" });
+    }
+}
+
+impl FromJson for f64 {
+    fn from_json(value: &Value) -> Result<Self, ConvertError> {
+        match value {
+            Value::Number(n) => Ok(*n),
+            _ => Err(ConvertError::expected("number", value)),
+        }
+    }
+}
+
+impl FromJson for f32 {
+    fn from_json(value: &Value) -> Result<Self, ConvertError> {
+        f64::from_json(value).map(|n| n as f32)
+    }
+}
+
+// NaN和Infinity不是合法的JSON，与JavaScript的JSON.stringify一样输出null
+impl ToJson for f64 {
+    fn write_json(&self, out: &mut String) {
+        match canonical_number(*self) {
+            Ok(n) => out.push_str(&n),
+            Err(_) => out.push_str("null"),
+        }
+    }
+}
+
+impl ToJson for f32 {
+    fn write_json(&self, out: &mut String) {
+        (*self as f64).write_json(out)
+    }
+}
+
+macro_rules! integer {
+    ($($t:ty),*) => {
+        $(
+            impl FromJson for $t {
+                fn from_json(value: &Value) -> Result<Self, ConvertError> {
+                    // MAX转为f64时可能向上舍入到2的幂，因此与MAX + 1比较
+                    let limit = if <$t>::MIN == 0 { 2f64.powi(<$t>::BITS as i32) } else { -(<$t>::MIN as f64) };
+                    match value {
+                        Value::Number(n) if n.fract() == 0.0 && *n >= <$t>::MIN as f64 && *n < limit => Ok(*n as $t),
+                        Value::Number(n) => Err(ConvertError::new(format!("expect {}, found {}", stringify!($t), n))),
+                        _ => Err(ConvertError::expected("number", value)),
+                    }
+                }
+            }
+
+            impl ToJson for $t {
+                fn write_json(&self, out: &mut String) {
+                    out.push_str(&self.to_string());
+                }
+            }
+        )*
+    };
+}
+
+integer!(i8, i16, i32, i64, isize, u8, u16, u32, u64, usize);
+
+impl FromJson for String {
+    fn from_json(value: &Value) -> Result<Self, ConvertError> {
+        match value {
+            Value::String(s) => unescape(s).map(|s| s.into_owned()).map_err(ConvertError::new),
+            _ => Err(ConvertError::expected("string", value)),
+        }
+    }
+}
+
+impl ToJson for String {
+    fn write_json(&self, out: &mut String) {
+        write_canonical_string(self, out);
+    }
+}
+
+impl ToJson for str {
+    fn write_json(&self, out: &mut String) {
+        write_canonical_string(self, out);
+    }
+}
+
+impl<T: ToJson + ?Sized> ToJson for &T {
+    fn write_json(&self, out: &mut String) {
+        (**self).write_json(out)
+    }
+}
+
+impl<T: FromJson> FromJson for Option<T> {
+    fn from_json(value: &Value) -> Result<Self, ConvertError> {
+        match value {
+            Value::Null => Ok(None),
+            _ => T::from_json(value).map(Some),
+        }
+    }
+
+    fn missing() -> Option<Self> {
+        Some(None)
+    }
+}
+
+impl<T: ToJson> ToJson for Option<T> {
+    fn write_json(&self, out: &mut String) {
+        match self {
+            Some(value) => value.write_json(out),
+            None => out.push_str("null"),
+        }
+    }
+}
+
+impl<T: FromJson> FromJson for Vec<T> {
+    fn from_json(value: &Value) -> Result<Self, ConvertError> {
+        match value {
+            Value::Array(arr) => arr.iter().enumerate()
+                .map(|(i, item)| T::from_json(item).map_err(|e| e.at_index(i)))
+                .collect(),
+            _ => Err(ConvertError::expected("array", value)),
+        }
+    }
+}
+
+impl<T: ToJson> ToJson for Vec<T> {
+    fn write_json(&self, out: &mut String) {
+        self.as_slice().write_json(out)
+    }
+}
+
+impl<T: ToJson> ToJson for [T] {
+    fn write_json(&self, out: &mut String) {
+        out.push('[');
+        for (i, item) in self.iter().enumerate() {
+            if i > 0 {
+                out.push(',');
+            }
+            item.write_json(out);
+        }
+        out.push(']');
+    }
+}
+
+fn from_object<M, T>(value: &Value) -> Result<M, ConvertError>
+where
+    M: FromIterator<(String, T)>,
+    T: FromJson,
+{
+    match value {
+        Value::Object(obj) => obj.iter()
+            .map(|(k, v)| {
+                let key = unescape(k).map_err(ConvertError::new)?.into_owned();
+                let value = T::from_json(v).map_err(|e| e.at_key(&key))?;
+                Ok((key, value))
+            })
+            .collect(),
+        _ => Err(ConvertError::expected("object", value)),
+    }
+}
+
+fn write_object<'m, K, T, I>(members: I, out: &mut String)
+where
+    K: AsRef<str> + 'm,
+    T: ToJson + 'm,
+    I: Iterator<Item = (&'m K, &'m T)>,
+{
+    out.push('{');
+    let mut first = true;
+    for (key, value) in members {
+        write_key(out, &mut first, key.as_ref());
+        value.write_json(out);
+    }
+    out.push('}');
+}
+
+impl<T: FromJson> FromJson for HashMap<String, T> {
+    fn from_json(value: &Value) -> Result<Self, ConvertError> {
+        from_object(value)
+    }
+}
+
+impl<K: AsRef<str> + Eq + Hash, T: ToJson> ToJson for HashMap<K, T> {
+    fn write_json(&self, out: &mut String) {
+        write_object(self.iter(), out)
+    }
+}
+
+impl<T: FromJson> FromJson for BTreeMap<String, T> {
+    fn from_json(value: &Value) -> Result<Self, ConvertError> {
+        from_object(value)
+    }
+}
+
+impl<K: AsRef<str> + Ord, T: ToJson> ToJson for BTreeMap<K, T> {
+    fn write_json(&self, out: &mut String) {
+        write_object(self.iter(), out)
+    }
+}
+
+// 元组对应定长数组
+macro_rules! tuple {
+    ($len:expr => $($name:ident $index:tt),+) => {
+        impl<$($name: FromJson),+> FromJson for ($($name,)+) {
+            fn from_json(value: &Value) -> Result<Self, ConvertError> {
+                match value {
+                    Value::Array(arr) if arr.len() == $len => Ok((
+                        $($name::from_json(&arr[$index]).map_err(|e| e.at_index($index))?,)+
+                    )),
+                    Value::Array(arr) => Err(ConvertError::new(format!("expect array of length {}, found length {}", $len, arr.len()))),
+                    _ => Err(ConvertError::expected("array", value)),
+                }
+            }
+        }
+
+        impl<$($name: ToJson),+> ToJson for ($($name,)+) {
+            fn write_json(&self, out: &mut String) {
+                out.push('[');
+                $(
+                    if $index > 0 {
+                        out.push(',');
+                    }
+                    self.$index.write_json(out);
+                )+
+                out.push(']');
+            }
+        }
+    };
+}
+
+tuple!(1 => A 0);
+tuple!(2 => A 0, B 1);
+tuple!(3 => A 0, B 1, C 2);
+tuple!(4 => A 0, B 1, C 2, D 3);
+tuple!(5 => A 0, B 1, C 2, D 3, E 4);
+tuple!(6 => A 0, B 1, C 2, D 3, E 4, F 5);
@@ -0,0 +1,498 @@
+use std::fmt::{Display, Formatter};
+use super::{Lexer, Result, TOKEN, unescape, write_canonical_string};
+
+// 无损的具体语法树(CST)
+// 每个token前的空白和注释(trivia)都挂在该token所在的节点上，原样打印即可逐字节还原输入
+// 例外是`,`之后与`,`同一行的trivia(如行尾注释)，归`,`前的元素或成员所有
+// 编辑操作只替换被修改的节点，其余部分保持不变
+//
+// Document -> Node trivia
+// Node -> trivia scalar
+//       | trivia [ Element* trivia ]
+//       | trivia { Member* trivia }
+// Element -> Node (trivia , trivia)?
+// Member -> trivia key trivia : Node (trivia , trivia)?
+
+#[derive(Debug, Clone)]
+pub struct Document {
+    root: Node,
+    trailing: String,
+}
+
+#[derive(Debug, Clone)]
+pub enum Node {
+    Scalar { leading: String, text: String },
+    Array { leading: String, items: Vec<Element>, close: String },
+    Object { leading: String, members: Vec<Member>, close: String },
+}
+
+#[derive(Debug, Clone)]
+pub struct Element {
+    pub value: Node,
+    pub comma: Option<String>,
+    // `,`之后同一行的trivia
+    pub trailing: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct Member {
+    pub leading: String,
+    pub key: String,
+    pub colon: String,
+    pub value: Node,
+    pub comma: Option<String>,
+    pub trailing: String,
+}
+
+pub struct CstParser<'s> {
+    lexer: Lexer<'s>,
+    prev_end: usize,
+}
+
+impl<'s> CstParser<'s> {
+    pub fn new(lexer: Lexer<'s>) -> CstParser<'s> {
+        CstParser {
+            lexer,
+            prev_end: 0,
+        }
+    }
+
+    pub fn parse(&mut self) -> Result<Document> {
+        let (trivia, token, text) = self.next()?;
+        let root = self.parse_node(trivia, token, text)?;
+        if !self.lexer.at_end()? {
+            return Err(format!("unexpected token after value at position: {}", self.lexer.index()));
+        }
+        let trailing = self.lexer.slice(self.prev_end, self.lexer.index()).to_string();
+        Ok(Document { root, trailing })
+    }
+
+    // 返回 (token前的trivia, token, token原文)
+    fn next(&mut self) -> Result<(String, TOKEN<'s>, &'s str)> {
+        let token = self.lexer.lex()?;
+        let start = self.lexer.token_start();
+        let trivia = self.lexer.slice(self.prev_end, start).to_string();
+        let text = self.lexer.slice(start, self.lexer.index());
+        self.prev_end = self.lexer.index();
+        Ok((trivia, token, text))
+    }
+
+    fn parse_node(&mut self, leading: String, token: TOKEN<'s>, text: &'s str) -> Result<Node> {
+        match token {
+            TOKEN::LBRACKET => self.parse_array(leading),
+            TOKEN::LBRACE => self.parse_object(leading),
+            TOKEN::STRING(_) | TOKEN::NUMBER(_) | TOKEN::BOOL(_) | TOKEN::NULL => {
+                Ok(Node::Scalar { leading, text: text.to_string() })
+            }
+            _ => Err(format!(
+                "expect [ | {{ | string | number | bool | null at position: {}",
+                self.lexer.index(),
+            ))
+        }
+    }
+
+    fn parse_array(&mut self, leading: String) -> Result<Node> {
+        let mut items = vec![];
+        let (mut trivia, mut token, mut text) = self.next()?;
+        loop {
+            // 空数组，或宽松模式下末尾多余的 ,
+            if let TOKEN::RBRACKET = token {
+                if items.is_empty() || self.lexer.is_lenient() {
+                    return Ok(Node::Array { leading, items, close: trivia });
+                }
+            }
+            let value = self.parse_node(trivia, token, text)?;
+            let (after, next, _) = self.next()?;
+            match next {
+                TOKEN::COMMA => {
+                    (trivia, token, text) = self.next()?;
+                    let trailing = split_line(&mut trivia);
+                    items.push(Element { value, comma: Some(after), trailing });
+                }
+                TOKEN::RBRACKET => {
+                    items.push(Element { value, comma: None, trailing: String::new() });
+                    return Ok(Node::Array { leading, items, close: after });
+                }
+                _ => return Err(format!("expect , | ] at position: {}", self.lexer.index())),
+            }
+        }
+    }
+
+    fn parse_object(&mut self, leading: String) -> Result<Node> {
+        let mut members = vec![];
+        let (mut trivia, mut token, mut text) = self.next()?;
+        loop {
+            if let TOKEN::RBRACE = token {
+                if members.is_empty() || self.lexer.is_lenient() {
+                    return Ok(Node::Object { leading, members, close: trivia });
+                }
+            }
+            if !matches!(token, TOKEN::STRING(_) | TOKEN::IDENT(_)) {
+                return Err(format!("expect string at position: {}", self.lexer.index()));
+            }
+            let key = text.to_string();
+            let (colon, next, _) = self.next()?;
+            if !matches!(next, TOKEN::COLON) {
+                return Err(format!("expect : at position: {}", self.lexer.index()));
+            }
+            let (value_trivia, value_token, value_text) = self.next()?;
+            let value = self.parse_node(value_trivia, value_token, value_text)?;
+            let (after, next, _) = self.next()?;
+            match next {
+                TOKEN::COMMA => {
+                    let leading = trivia;
+                    (trivia, token, text) = self.next()?;
+                    let trailing = split_line(&mut trivia);
+                    members.push(Member { leading, key, colon, value, comma: Some(after), trailing });
+                }
+                TOKEN::RBRACE => {
+                    members.push(Member { leading: trivia, key, colon, value, comma: None, trailing: String::new() });
+                    return Ok(Node::Object { leading, members, close: after });
+                }
+                _ => return Err(format!("expect , | }} at position: {}", self.lexer.index())),
+            }
+        }
+    }
+}
+
+/// 从trivia中分出第一个换行之前的部分，没有换行时不拆分，返回空串
+/// 块注释中的换行不算，`\r\n`整体留在剩余部分
+fn split_line(trivia: &mut String) -> String {
+    let bytes = trivia.as_bytes();
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i..].starts_with(b"/*") {
+            i = bytes[i + 2..].windows(2).position(|w| w == b"*/").map_or(bytes.len(), |end| i + 2 + end + 2);
+            continue;
+        }
+        // 行注释直到换行
+        if bytes[i..].starts_with(b"//") {
+            i = bytes[i..].iter().position(|&b| b == b'\n').map_or(bytes.len(), |end| i + end);
+            continue;
+        }
+        if bytes[i] == b'\n' {
+            let at = if i > 0 && bytes[i - 1] == b'\r' { i - 1 } else { i };
+            let rest = trivia.split_off(at);
+            return std::mem::replace(trivia, rest);
+        }
+        i += 1;
+    }
+    String::new()
+}
+
+impl Document {
+    pub fn root(&self) -> &Node {
+        &self.root
+    }
+
+    pub fn root_mut(&mut self) -> &mut Node {
+        &mut self.root
+    }
+}
+
+impl Display for Document {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}{}", self.root, self.trailing)
+    }
+}
+
+impl Member {
+    /// 键的内容，去掉引号并还原转义
+    pub fn name(&self) -> Result<String> {
+        match self.key.as_bytes()[0] {
+            b'"' | b'\'' => Ok(unescape(&self.key[1..self.key.len() - 1])?.into_owned()),
+            _ => Ok(self.key.clone()),
+        }
+    }
+}
+
+impl Node {
+    /// 以严格模式解析一段JSON文本作为新节点，用于编辑操作
+    pub fn parse(text: &str) -> Result<Node> {
+        let doc = CstParser::new(Lexer::new(text)).parse()?;
+        Ok(doc.root.with_leading(String::new()))
+    }
+
+    fn leading(&self) -> &str {
+        match self {
+            Node::Scalar { leading, .. } | Node::Array { leading, .. } | Node::Object { leading, .. } => leading,
+        }
+    }
+
+    fn set_leading(&mut self, trivia: String) {
+        match self {
+            Node::Scalar { leading, .. } | Node::Array { leading, .. } | Node::Object { leading, .. } => *leading = trivia,
+        }
+    }
+
+    fn with_leading(mut self, trivia: String) -> Node {
+        self.set_leading(trivia);
+        self
+    }
+
+    /// 标量节点的原文
+    pub fn text(&self) -> Option<&str> {
+        match self {
+            Node::Scalar { text, .. } => Some(text),
+            _ => None,
+        }
+    }
+
+    pub fn get(&self, key: &str) -> Option<&Node> {
+        match self {
+            Node::Object { members, .. } => members.iter()
+                .find(|m| m.name().is_ok_and(|name| name == key))
+                .map(|m| &m.value),
+            _ => None,
+        }
+    }
+
+    pub fn get_mut(&mut self, key: &str) -> Option<&mut Node> {
+        match self {
+            Node::Object { members, .. } => members.iter_mut()
+                .find(|m| m.name().is_ok_and(|name| name == key))
+                .map(|m| &mut m.value),
+            _ => None,
+        }
+    }
+
+    pub fn index(&self, index: usize) -> Option<&Node> {
+        match self {
+            Node::Array { items, .. } => items.get(index).map(|e| &e.value),
+            _ => None,
+        }
+    }
+
+    pub fn index_mut(&mut self, index: usize) -> Option<&mut Node> {
+        match self {
+            Node::Array { items, .. } => items.get_mut(index).map(|e| &mut e.value),
+            _ => None,
+        }
+    }
+
+    /// 用`text`替换当前节点，保留原节点前的trivia
+    pub fn replace(&mut self, text: &str) -> Result<()> {
+        let node = Node::parse(text)?;
+        *self = node.with_leading(self.leading().to_string());
+        Ok(())
+    }
+
+    /// 设置对象的键，已存在时原地替换值，否则追加到末尾并沿用上一个成员的缩进
+    pub fn set(&mut self, key: &str, text: &str) -> Result<()> {
+        if let Some(node) = self.get_mut(key) {
+            return node.replace(text);
+        }
+        let value = Node::parse(text)?;
+        match self {
+            Node::Object { members, .. } => {
+                let mut quoted = String::new();
+                write_canonical_string(key, &mut quoted);
+                let (leading, value_leading, comma) = match members.last_mut() {
+                    Some(last) => {
+                        let trailing_comma = last.comma.is_some();
+                        if !trailing_comma {
+                            last.comma = Some(String::new());
+                        }
+                        let comma = if trailing_comma { Some(String::new()) } else { None };
+                        (last.leading.clone(), last.value.leading().to_string(), comma)
+                    }
+                    None => (String::new(), " ".to_string(), None),
+                };
+                members.push(Member {
+                    leading,
+                    key: quoted,
+                    colon: String::new(),
+                    value: value.with_leading(value_leading),
+                    comma,
+                    trailing: String::new(),
+                });
+                Ok(())
+            }
+            _ => Err("expect object".to_string()),
+        }
+    }
+
+    /// 在数组的`index`处插入元素，沿用相邻元素的缩进
+    pub fn insert(&mut self, index: usize, text: &str) -> Result<()> {
+        let value = Node::parse(text)?;
+        match self {
+            Node::Array { items, .. } => {
+                if index > items.len() {
+                    return Err(format!("index {} out of range", index));
+                }
+                let leading = items.get(index).or(items.last())
+                    .map_or(String::new(), |e| e.value.leading().to_string());
+                let comma = if index < items.len() {
+                    Some(String::new())
+                } else {
+                    match items.last_mut() {
+                        Some(last) if last.comma.is_some() => Some(String::new()),
+                        Some(last) => {
+                            last.comma = Some(String::new());
+                            None
+                        }
+                        None => None,
+                    }
+                };
+                items.insert(index, Element { value: value.with_leading(leading), comma, trailing: String::new() });
+                Ok(())
+            }
+            _ => Err("expect array".to_string()),
+        }
+    }
+
+    pub fn push(&mut self, text: &str) -> Result<()> {
+        match self {
+            Node::Array { items, .. } => {
+                let len = items.len();
+                self.insert(len, text)
+            }
+            _ => Err("expect array".to_string()),
+        }
+    }
+
+    /// 删除对象的键，被删除成员前的trivia和行尾的trivia(包括注释)一并删除，前一个成员的行尾注释保留
+    pub fn remove(&mut self, key: &str) -> Option<Node> {
+        match self {
+            Node::Object { members, .. } => {
+                let index = members.iter().position(|m| m.name().is_ok_and(|name| name == key))?;
+                let member = members.remove(index);
+                if member.comma.is_none() && index > 0 {
+                    members[index - 1].comma = None;
+                }
+                // 删除首个成员时，新的首个成员沿用其缩进
+                if index == 0 && members.first().is_some_and(|m| m.leading.trim().is_empty()) {
+                    members[0].leading = member.leading;
+                }
+                Some(member.value)
+            }
+            _ => None,
+        }
+    }
+
+    pub fn remove_index(&mut self, index: usize) -> Option<Node> {
+        match self {
+            Node::Array { items, .. } if index < items.len() => {
+                let element = items.remove(index);
+                if element.comma.is_none() && index > 0 {
+                    items[index - 1].comma = None;
+                }
+                if index == 0 && items.first().is_some_and(|e| e.value.leading().trim().is_empty()) {
+                    items[0].value.set_leading(element.value.leading().to_string());
+                }
+                Some(element.value)
+            }
+            _ => None,
+        }
+    }
+}
+
+impl Display for Node {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Node::Scalar { leading, text } => write!(f, "{}{}", leading, text),
+            Node::Array { leading, items, close } => {
+                write!(f, "{}[", leading)?;
+                for element in items {
+                    write!(f, "{}", element.value)?;
+                    if let Some(trivia) = &element.comma {
+                        write!(f, "{},", trivia)?;
+                    }
+                    write!(f, "{}", element.trailing)?;
+                }
+                write!(f, "{}]", close)
+            }
+            Node::Object { leading, members, close } => {
+                write!(f, "{}{{", leading)?;
+                for member in members {
+                    write!(f, "{}{}{}:{}", member.leading, member.key, member.colon, member.value)?;
+                    if let Some(trivia) = &member.comma {
+                        write!(f, "{},", trivia)?;
+                    }
+                    write!(f, "{}", member.trailing)?;
+                }
+                write!(f, "{}}}", close)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SRC: &str = "// config\n{\n    \"name\": \"demo\", // inline\n    /* block */ port: 8080,\n    'tags': [1, 2,],\n}\n";
+
+    fn parse(src: &str) -> Document {
+        CstParser::new(Lexer::lenient(src)).parse().unwrap()
+    }
+
+    #[test]
+    fn round_trip() {
+        assert_eq!(parse(SRC).to_string(), SRC);
+        let strict = " [ 1 ,{\"a\" :null} ] ";
+        assert_eq!(CstParser::new(Lexer::new(strict)).parse().unwrap().to_string(), strict);
+    }
+
+    #[test]
+    fn lookup() {
+        let doc = parse(SRC);
+        assert_eq!(doc.root().get("name").and_then(Node::text), Some("\"demo\""));
+        assert_eq!(doc.root().get("port").and_then(Node::text), Some("8080"));
+        assert_eq!(doc.root().get("tags").and_then(|t| t.index(1)).and_then(Node::text), Some("2"));
+        assert!(doc.root().get("missing").is_none());
+    }
+
+    #[test]
+    fn replace_keeps_trivia() {
+        let mut doc = parse(SRC);
+        doc.root_mut().set("port", "9090").unwrap();
+        assert_eq!(doc.to_string(), SRC.replace("8080", "9090"));
+    }
+
+    #[test]
+    fn set_new_key() {
+        let mut doc = parse("{\n    \"a\": 1\n}");
+        doc.root_mut().set("b", "[true]").unwrap();
+        assert_eq!(doc.to_string(), "{\n    \"a\": 1,\n    \"b\": [true]\n}");
+        let mut doc = parse("{}");
+        doc.root_mut().set("a", "1").unwrap();
+        assert_eq!(doc.to_string(), "{\"a\": 1}");
+    }
+
+    #[test]
+    fn insert_and_remove() {
+        let mut doc = parse("[1, 2]");
+        doc.root_mut().insert(1, "3").unwrap();
+        doc.root_mut().push("4").unwrap();
+        assert_eq!(doc.to_string(), "[1, 3, 2, 4]");
+        assert_eq!(doc.root_mut().remove_index(3).unwrap().text(), Some("4"));
+        assert_eq!(doc.to_string(), "[1, 3, 2]");
+        assert!(doc.root_mut().insert(9, "0").is_err());
+
+        let mut doc = parse(SRC);
+        doc.root_mut().remove("port").unwrap();
+        // port前的块注释随port一起删除，name行尾的注释保留
+        assert_eq!(doc.to_string(), "// config\n{\n    \"name\": \"demo\", // inline\n    'tags': [1, 2,],\n}\n");
+        assert!(doc.root_mut().remove("port").is_none());
+        // 行尾注释随所在行的成员删除
+        doc.root_mut().remove("name").unwrap();
+        assert_eq!(doc.to_string(), "// config\n{\n    'tags': [1, 2,],\n}\n");
+
+        let src = "[\n  1, // 一 /* x\n  2, /* a\n b */\n  3,\r\n  4\n]";
+        let mut doc = parse(src);
+        assert_eq!(doc.to_string(), src);
+        doc.root_mut().remove_index(1).unwrap();
+        assert_eq!(doc.to_string(), "[\n  1, // 一 /* x\n  3,\r\n  4\n]");
+        doc.root_mut().insert(1, "5").unwrap();
+        assert_eq!(doc.to_string(), "[\n  1, // 一 /* x\n  5,\n  3,\r\n  4\n]");
+    }
+
+    #[test]
+    fn invalid_replacement() {
+        let mut doc = parse(SRC);
+        assert!(doc.root_mut().set("port", "{").is_err());
+        assert!(doc.root_mut().get_mut("port").unwrap().replace("1 2").is_err());
+        assert_eq!(doc.to_string(), SRC);
+    }
+}
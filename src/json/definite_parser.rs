@@ -1,5 +1,9 @@
 use std::collections::HashMap;
-use super::{Lexer, Value, TOKEN, Result};
+use super::{Lexer, Value, TOKEN, Result, Token, TokenStream, unescape};
+use super::span::{pointer, Span, SpanTable};
+use super::parse_tree::{ParseTree, TreeBuilder};
+use super::parser::Parser;
+use super::trace::{input, Step, StepKind, Tracer};
 
 
 /// to LL1:
@@ -10,11 +14,11 @@ use super::{Lexer, Value, TOKEN, Result};
 /// Object' -> } | Pair PairList }
 /// PairList -> , Pair PairList | ε
 /// Pair -> string : Value
-
+///
 /// | set    |     Value                          |     Array'                           |     Object'     |     ValueList | PairList | Pair     |
 /// | :- :   |           :-:                      |     :-:                              |     :-:         |     :-:       |  :-:     |  :-:     |
 /// | FIRST  |    \[\{ string number bool null    |    \]\[\{ string number bool null    |    \} string    |      , ε      |   , ε    |  string  |
-/// | FOLLOW |     , # \] \}                      |     # , \]                           |     , # ] }     |     \]        |     \}   |  , \}    |
+/// | FOLLOW |     , # \] \}                      |     , # \] \}                        |     , # ] }     |     \]        |     \}   |  , \}    |
 /// Value
 /// - SELECT(Value -> [ Array') = [
 /// - SELECT(Value -> { Object') = {
@@ -41,21 +45,251 @@ use super::{Lexer, Value, TOKEN, Result};
 ///
 /// Pair
 /// - SELECT(Pair -> string : Value) = string
-
+// 通过TokenStream向前看一个token决定使用的产生式，不需要退回重新读取
 pub struct DefiniteParser<'s> {
-    lexer: Lexer<'s>,
+    tokens: TokenStream<'s>,
+    // 仅在parse_with_spans时记录位置
+    spans: Option<SpanTable>,
+    path: Vec<String>,
+    // 错误恢复模式
+    recovering: bool,
+    diagnostics: Vec<String>,
+    // 尚未闭合的 [ { 对应的结束符
+    open: Vec<TOKEN<'static>>,
+    // 上一个token是否是在输入末尾补上的结束符
+    synthetic: bool,
+    tracer: Option<Tracer>,
+    // with_tracer时记录调用栈中的非终结符
+    rules: Vec<&'static str>,
+    // 仅在parse_tree时构造
+    tree: Option<TreeBuilder<'s>>,
+}
+
+// 错误恢复使用的同步符号集，取自上面的FOLLOW集，# 由补全的结束符代替
+const FOLLOW_VALUE: [TOKEN; 3] = [TOKEN::COMMA, TOKEN::RBRACKET, TOKEN::RBRACE];
+const FOLLOW_PAIR: [TOKEN; 2] = [TOKEN::COMMA, TOKEN::RBRACE];
+const SYNC_VALUE_LIST: [TOKEN; 2] = [TOKEN::COMMA, TOKEN::RBRACKET];
+
+fn same_token(a: &TOKEN, b: &TOKEN) -> bool {
+    std::mem::discriminant(a) == std::mem::discriminant(b)
 }
 
 impl<'s> DefiniteParser<'s> {
     pub fn new(lexer: Lexer) -> DefiniteParser {
         DefiniteParser {
-            lexer
+            tokens: TokenStream::new(lexer),
+            spans: None,
+            path: vec![],
+            recovering: false,
+            diagnostics: vec![],
+            open: vec![],
+            synthetic: false,
+            tracer: None,
+            rules: vec![],
+            tree: None,
         }
     }
 
+    /// 记录每次进入的非终结符和当时的调用栈
+    pub fn with_tracer(self, tracer: Tracer) -> DefiniteParser<'s> {
+        DefiniteParser { tracer: Some(tracer), ..self }
+    }
+
+    pub fn take_tracer(&mut self) -> Option<Tracer> {
+        self.tracer.take()
+    }
+
     pub fn parse(&mut self) -> Result<Value<'s>> {
         self.parse_value()
     }
+
+    /// 解析的同时记录每个值和键在源文本中的位置
+    pub fn parse_with_spans(&mut self) -> Result<(Value<'s>, SpanTable)> {
+        self.spans = Some(SpanTable::default());
+        let value = self.parse_value()?;
+        Ok((value, self.spans.take().unwrap()))
+    }
+
+    /// 解析的同时构造LL1文法的分析树
+    pub fn parse_tree(&mut self) -> Result<(Value<'s>, ParseTree<'s>)> {
+        self.tree = Some(TreeBuilder::new());
+        let value = self.parse_value();
+        let tree = self.tree.take().unwrap();
+        Ok((value?, tree.finish().unwrap()))
+    }
+
+    /// 错误恢复模式，遇到错误时不立即返回，而是记录下来继续解析
+    /// - 出错后跳过token直到遇到FOLLOW集中的符号(`,` `]` `}`)，跳过的部分以`Value::Null`占位
+    /// - 无法识别的字符被逐个跳过
+    /// - 输入提前结束时补全所有未闭合的`]`和`}`
+    /// - 值之后还有内容时报错，多余的内容被忽略
+    ///
+    /// 返回尽可能完整的`Value`和所有错误信息，没有错误时与`parse`的结果相同
+    pub fn parse_recovering(&mut self) -> (Value<'s>, Vec<String>) {
+        self.recovering = true;
+        let value = match self.parse_value() {
+            Ok(value) => {
+                match self.tokens.at_end() {
+                    Ok(true) => {}
+                    Ok(false) => {
+                        let end = self.tokens.peek_token().map_or(self.tokens.lexer().index() + 1, |token| token.span.end);
+                        self.diagnostics.push(format!("expect end of input at position: {}", end));
+                    }
+                    Err(msg) => self.diagnostics.push(msg),
+                }
+                value
+            }
+            Err(msg) => {
+                self.diagnostics.push(msg);
+                Value::Null
+            }
+        };
+        (value, std::mem::take(&mut self.diagnostics))
+    }
+
+    // 进入非终结符rule，返回时出栈
+    fn rule<T>(&mut self, rule: &'static str, parse: impl FnOnce(&mut Self) -> Result<T>) -> Result<T> {
+        if self.tracer.is_none() && self.tree.is_none() {
+            return parse(self);
+        }
+        if let Some(tracer) = &mut self.tracer {
+            self.rules.push(rule);
+            tracer.push(Step {
+                stack: self.rules.join(" "),
+                input: input(self.tokens.lexer().rest(self.tokens.position())),
+                kind: StepKind::Enter(rule),
+            });
+        }
+        if let Some(tree) = &mut self.tree {
+            tree.open(rule);
+        }
+        let result = parse(self);
+        if self.tracer.is_some() {
+            self.rules.pop();
+        }
+        if let Some(tree) = &mut self.tree {
+            tree.close(result.is_ok());
+        }
+        result
+    }
+
+    fn parse_value(&mut self) -> Result<Value<'s>> {
+        self.rule("Value", Self::value)
+    }
+
+    fn parse_array1(&mut self) -> Result<Value<'s>> {
+        self.rule("Array'", Self::array1)
+    }
+
+    fn parse_object1(&mut self) -> Result<Value<'s>> {
+        self.rule("Object'", Self::object1)
+    }
+
+    fn parse_pair(&mut self) -> Result<Option<(&'s str, Value<'s>)>> {
+        self.rule("Pair", Self::pair)
+    }
+
+    fn parse_pair_list(&mut self) -> Result<Vec<(&'s str, Value<'s>)>> {
+        self.rule("PairList", Self::pair_list)
+    }
+
+    fn parse_value_list(&mut self, index: usize) -> Result<Vec<Value<'s>>> {
+        self.rule("ValueList", |parser| parser.value_list(index))
+    }
+
+    // 只在记录位置时才生成路径
+    fn enter(&mut self, token: impl FnOnce() -> String) {
+        if self.spans.is_some() {
+            self.path.push(token());
+        }
+    }
+
+    fn leave(&mut self) {
+        if self.spans.is_some() {
+            self.path.pop();
+        }
+    }
+
+    /// 下一个token，不消耗
+    /// 恢复模式下跳过无法识别的字符，输入提前结束时返回补上的结束符，其范围为输入末尾的空范围
+    fn peek(&mut self) -> Result<Token<'s>> {
+        if !self.recovering {
+            return self.tokens.peek_token();
+        }
+        // 连续的无法识别的字符只报告一次
+        let mut reported = false;
+        loop {
+            if self.tokens.at_end()? {
+                if let Some(close) = self.open.last() {
+                    self.synthetic = true;
+                    let end = self.tokens.lexer().index();
+                    return Ok(Token { kind: *close, span: Span { start: end, end } });
+                }
+            }
+            self.synthetic = false;
+            match self.tokens.peek_token() {
+                Ok(token) => return Ok(token),
+                Err(msg) if self.tokens.at_end()? && self.open.is_empty() => return Err(msg),
+                Err(msg) => {
+                    if !reported {
+                        self.diagnostics.push(msg);
+                        reported = true;
+                    }
+                    self.tokens.skip_char();
+                }
+            }
+        }
+    }
+
+    /// 消耗下一个token，补上的结束符没有消耗输入
+    /// 分析树只在非恢复模式下构造，消耗的token都是叶子
+    fn lex(&mut self) -> Result<Token<'s>> {
+        if !self.recovering {
+            let token = self.tokens.next_token()?;
+            if let Some(tree) = &mut self.tree {
+                tree.leaf(token.kind);
+            }
+            return Ok(token);
+        }
+        let token = self.peek()?;
+        if !self.synthetic {
+            self.tokens.next_token()?;
+        }
+        Ok(token)
+    }
+
+    // 消耗了 ] 或 } 之后调用
+    fn close(&mut self) {
+        let close = self.open.pop();
+        if self.synthetic {
+            let symbol = if let Some(TOKEN::RBRACKET) = close { "]" } else { "}" };
+            self.diagnostics.push(format!("expect {} before end of input", symbol));
+        }
+    }
+
+    /// 非恢复模式下直接返回错误
+    /// 恢复模式下记录错误，并跳过token直到遇到`sync`中的符号，该符号留给调用者消耗
+    fn recover(&mut self, msg: String, sync: &[TOKEN]) -> Result<()> {
+        if !self.recovering {
+            return Err(msg);
+        }
+        self.diagnostics.push(msg);
+        let mut depth = 0;
+        loop {
+            let token = self.peek()?;
+            if self.synthetic {
+                return Ok(());
+            }
+            match token.kind {
+                TOKEN::RBRACKET | TOKEN::RBRACE if depth > 0 => depth -= 1,
+                kind if depth == 0 && sync.iter().any(|t| same_token(t, &kind)) => return Ok(()),
+                TOKEN::LBRACKET | TOKEN::LBRACE => depth += 1,
+                _ => {}
+            }
+            self.lex()?;
+        }
+    }
+
     /// Value
     /// - SELECT(Value -> [ Array') = [
     /// - SELECT(Value -> { Object') = {
@@ -63,107 +297,269 @@ impl<'s> DefiniteParser<'s> {
     /// - SELECT(Value -> number) = number
     /// - SELECT(Value -> bool) = bool
     /// - SELECT(Value -> null) = null
-    fn parse_value(&mut self) -> Result<Value<'s>> {
-        match self.lexer.lex()? {
-            TOKEN::LBRACE => self.parse_object1(),
-            TOKEN::LBRACKET => self.parse_array1(),
-            TOKEN::STRING(s) => Ok(Value::String(s)),
-            TOKEN::NUMBER(n) => Ok(Value::Number(n)),
-            TOKEN::BOOL(b) => Ok(Value::Bool(b)),
-            TOKEN::NULL => Ok(Value::Null),
-            _ => Err(format!(
+    fn value(&mut self) -> Result<Value<'s>> {
+        let token = self.peek()?;
+        if let TOKEN::LBRACE | TOKEN::LBRACKET | TOKEN::STRING(_) | TOKEN::NUMBER(_) | TOKEN::BOOL(_) | TOKEN::NULL = token.kind {
+            self.lex()?;
+        } else {
+            let msg = format!(
                 "expect [ | {{ | string | number | bool | null at position: {}",
-                self.lexer.index(),
-            ))
+                token.span.end,
+            );
+            self.recover(msg, &FOLLOW_VALUE)?;
+            return Ok(Value::Null);
         }
+        let value = match token.kind {
+            TOKEN::LBRACE => {
+                self.open.push(TOKEN::RBRACE);
+                self.parse_object1()?
+            }
+            TOKEN::LBRACKET => {
+                self.open.push(TOKEN::RBRACKET);
+                self.parse_array1()?
+            }
+            TOKEN::STRING(s) => Value::String(s),
+            TOKEN::NUMBER(n) => Value::Number(n),
+            TOKEN::BOOL(b) => Value::Bool(b),
+            // TOKEN::NULL
+            _ => Value::Null,
+        };
+        if let Some(spans) = &mut self.spans {
+            let span = Span { start: token.span.start, end: self.tokens.last_span().end };
+            spans.values.insert(pointer(&self.path), span);
+        }
+        Ok(value)
     }
     /// Array'
     /// - SELECT(Array' -> ]) = ]
     /// - SELECT(Array' -> Value ValueList]) = string number bool null [ {
-    fn parse_array1(&mut self) -> Result<Value<'s>> {
-        match self.lexer.lex()? {
-            TOKEN::RBRACKET => Ok(Value::Array(vec![])),
-            _ => {
-                self.lexer.push_back();
-                let value = self.parse_value()?;
-                let mut list = self.parse_value_list()?;
-                list.push(value);
-                return if let TOKEN::RBRACKET = self.lexer.lex()? {
-                    Ok(Value::Array(list))
-                } else {
-                    Err(format!("expect ] at position: {}", self.lexer.index()))
-                };
-            }
+    fn array1(&mut self) -> Result<Value<'s>> {
+        if let TOKEN::RBRACKET = self.peek()?.kind {
+            self.lex()?;
+            self.close();
+            return Ok(Value::Array(vec![]));
+        }
+        self.enter(|| 0.to_string());
+        let value = self.parse_value()?;
+        self.leave();
+        let mut list = self.parse_value_list(1)?;
+        list.push(value);
+        // ValueList从后向前收集元素
+        list.reverse();
+        let token = self.peek()?;
+        if !matches!(token.kind, TOKEN::RBRACKET) {
+            let msg = format!("expect ] at position: {}", token.span.end);
+            self.recover(msg, &[TOKEN::RBRACKET])?;
         }
+        self.lex()?;
+        self.close();
+        Ok(Value::Array(list))
     }
     /// Object'
     /// - SELECT(Object' -> }) = }
     /// - SELECT(Object' -> Pair PairList}) = string
-    fn parse_object1(&mut self) -> Result<Value<'s>> {
-        match self.lexer.lex()? {
-            TOKEN::RBRACE => Ok(Value::Object(HashMap::new())),
-            TOKEN::STRING(_) => {
-                self.lexer.push_back();
-                let pair = self.parse_pair()?;
-                let mut list = self.parse_pair_list()?;
-                list.push(pair);
-                return if let TOKEN::RBRACE = self.lexer.lex()? {
-                    Ok(Value::Object(HashMap::from_iter(list)))
-                } else {
-                    Err(format!("expect }} at position: {}", self.lexer.index()))
-                };
-            }
-            _ => Err(format!("expect }} | string at position: {}", self.lexer.index()))
+    fn object1(&mut self) -> Result<Value<'s>> {
+        let mut list = vec![];
+        let token = self.peek()?;
+        match token.kind {
+            TOKEN::RBRACE => {
+                self.lex()?;
+                self.close();
+                return Ok(Value::Object(HashMap::new()));
+            }
+            TOKEN::STRING(_) | TOKEN::IDENT(_) => {
+                if let Some(pair) = self.parse_pair()? {
+                    list.push(pair);
+                }
+            }
+            _ => {
+                let msg = format!("expect }} | string at position: {}", token.span.end);
+                self.recover(msg, &FOLLOW_PAIR)?;
+            }
         }
+        list.append(&mut self.parse_pair_list()?);
+        let token = self.peek()?;
+        if !matches!(token.kind, TOKEN::RBRACE) {
+            let msg = format!("expect }} at position: {}", token.span.end);
+            self.recover(msg, &[TOKEN::RBRACE])?;
+        }
+        self.lex()?;
+        self.close();
+        Ok(Value::from_members(list))
     }
     /// Pair
     /// - SELECT(Pair -> string : Value) = string
-    fn parse_pair(&mut self) -> Result<(&'s str, Value<'s>)> {
-        if let TOKEN::STRING(s) = self.lexer.lex()? {
-            if let TOKEN::COLON = self.lexer.lex()? {
-                let value = self.parse_value()?;
-                return Ok((s, value));
+    ///
+    /// 恢复模式下键出错时返回None
+    fn pair(&mut self) -> Result<Option<(&'s str, Value<'s>)>> {
+        let token = self.peek()?;
+        if let TOKEN::STRING(s) | TOKEN::IDENT(s) = token.kind {
+            self.lex()?;
+            if let Some(spans) = self.spans.as_mut() {
+                self.path.push(unescape(s)?.into_owned());
+                spans.keys.insert(pointer(&self.path), token.span);
             }
-            return  Err(format!("expect : at position: {}", self.lexer.index()))
+            let token = self.peek()?;
+            let value = if let TOKEN::COLON = token.kind {
+                self.lex()?;
+                self.parse_value()?
+            } else {
+                let msg = format!("expect : at position: {}", token.span.end);
+                self.recover(msg, &FOLLOW_PAIR)?;
+                Value::Null
+            };
+            self.leave();
+            return Ok(Some((s, value)));
         }
-        return  Err(format!("expect string at position: {}", self.lexer.index()))
+        let msg = format!("expect string at position: {}", token.span.end);
+        self.recover(msg, &FOLLOW_PAIR)?;
+        Ok(None)
     }
     /// PairList
     /// - SELECT(PairList -> , Pair PairList) = ,
     /// - SELECT(PairList -> ε) = \emptyset and FOLLOW(PairList) = }
-    fn parse_pair_list(&mut self) -> Result<Vec<(&'s str, Value<'s>)>> {
-        match self.lexer.lex()? {
+    fn pair_list(&mut self) -> Result<Vec<(&'s str, Value<'s>)>> {
+        let token = self.peek()?;
+        match token.kind {
             TOKEN::COMMA => {
-                let value = self.parse_pair()?;
-                let mut list = self.parse_pair_list()?;
-                list.push(value);
+                self.lex()?;
+                if self.trailing_comma(TOKEN::RBRACE)? {
+                    return Ok(vec![]);
+                }
+                // 按源文本的顺序收集，重复的键取最后一个
+                let mut list: Vec<_> = self.parse_pair()?.into_iter().collect();
+                list.append(&mut self.parse_pair_list()?);
                 Ok(list)
             }
-            TOKEN::RBRACE => {
-                self.lexer.push_back();
-                Ok(vec![])
-            }
+            TOKEN::RBRACE => Ok(vec![]),
             _ => {
-                Err(format!("expect , | }} at position: {}", self.lexer.index()))
+                let msg = format!("expect , | }} at position: {}", token.span.end);
+                self.recover(msg, &FOLLOW_PAIR)?;
+                self.parse_pair_list()
             }
         }
     }
     /// ValueList
     /// - SELECT(ValueList -> , Value ValueList) = ,
     /// - SELECT(ValueList -> ε) = \emptyset and FOLLOW(ValueList) = ]
-    fn parse_value_list(&mut self) -> Result<Vec<Value<'s>>> {
-        match self.lexer.lex()? {
+    fn value_list(&mut self, index: usize) -> Result<Vec<Value<'s>>> {
+        let token = self.peek()?;
+        match token.kind {
             TOKEN::COMMA => {
+                self.lex()?;
+                if self.trailing_comma(TOKEN::RBRACKET)? {
+                    return Ok(vec![]);
+                }
+                self.enter(|| index.to_string());
                 let value = self.parse_value()?;
-                let mut list = self.parse_value_list()?;
+                self.leave();
+                let mut list = self.parse_value_list(index + 1)?;
                 list.push(value);
                 Ok(list)
             }
-            TOKEN::RBRACKET => {
-                self.lexer.push_back();
-                Ok(vec![])
+            TOKEN::RBRACKET => Ok(vec![]),
+            _ => {
+                let msg = format!("expect , | ] at position: {}", token.span.end);
+                self.recover(msg, &SYNC_VALUE_LIST)?;
+                self.parse_value_list(index)
             }
-            _ => Err(format!("expect , | ] at position: {}", self.lexer.index()))
         }
     }
-}
\ No newline at end of file
+
+    /// 宽松模式下，`,`之后紧跟`close`时视为末尾多余的`,`，`close`留给调用者消耗
+    fn trailing_comma(&mut self, close: TOKEN) -> Result<bool> {
+        if !self.tokens.lexer().is_lenient() {
+            return Ok(false);
+        }
+        Ok(same_token(&self.peek()?.kind, &close))
+    }
+}
+
+impl<'s> Parser<'s> for DefiniteParser<'s> {
+    fn parse(&mut self) -> Result<Value<'s>> {
+        let value = DefiniteParser::parse(self)?;
+        if !self.tokens.at_end()? {
+            let token = self.tokens.peek_token()?;
+            return Err(format!("expect end of input at position: {}", token.span.end));
+        }
+        Ok(value)
+    }
+
+    fn error_position(&self) -> usize {
+        self.tokens.lexer().error_position()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn spans() {
+        let src = "{\"a\": [1, {\"b~/c\": true}],\n \"d\": \"x\"}";
+        let (_, spans) = DefiniteParser::new(Lexer::new(src)).parse_with_spans().unwrap();
+        let text = |span: Option<Span>| span.map(|s| &src[s.start..s.end]);
+        assert_eq!(text(spans.value("")), Some(src));
+        assert_eq!(text(spans.value("/a")), Some("[1, {\"b~/c\": true}]"));
+        assert_eq!(text(spans.value("/a/0")), Some("1"));
+        assert_eq!(text(spans.value("/a/1/b~0~1c")), Some("true"));
+        assert_eq!(text(spans.key("/a/1/b~0~1c")), Some("\"b~/c\""));
+        assert_eq!(text(spans.key("/d")), Some("\"d\""));
+        assert_eq!(spans.value("/d").unwrap().line_col(src), (2, 7));
+        assert_eq!(spans.key("/a/0"), None);
+        assert_eq!(spans.value("/x"), None);
+    }
+
+    #[test]
+    fn spans_use_unescaped_keys() {
+        let src = r#"{"a\/b": 1}"#;
+        let (_, spans) = DefiniteParser::new(Lexer::new(src)).parse_with_spans().unwrap();
+        assert_eq!(spans.value("/a~1b"), Some(Span { start: 9, end: 10 }));
+    }
+
+    #[test]
+    fn pointers() {
+        assert_eq!(pointer::<&str>(&[]), "");
+        assert_eq!(pointer(&["a/b", "~", "0"]), "/a~1b/~0/0");
+    }
+
+    fn recover(src: &str) -> (String, Vec<String>) {
+        let (value, diagnostics) = DefiniteParser::new(Lexer::new(src)).parse_recovering();
+        (value.to_canonical_string().unwrap(), diagnostics)
+    }
+
+    #[test]
+    fn recovering_skips_to_follow_set() {
+        assert_eq!(recover("[1, 2 3, 4]"), ("[1,2,4]".to_string(), vec!["expect , | ] at position: 7".to_string()]));
+        let (value, diagnostics) = recover(r#"{"a": 1, "b" 2, "c": 3}"#);
+        assert_eq!(value, r#"{"a":1,"b":null,"c":3}"#);
+        assert_eq!(diagnostics.len(), 1);
+    }
+
+    #[test]
+    fn recovering_skips_unknown_chars() {
+        let (value, diagnostics) = recover("[1, @, 2]");
+        assert_eq!(value, "[1,null,2]");
+        assert_eq!(diagnostics[0], "unexpected token at position 4");
+    }
+
+    #[test]
+    fn recovering_closes_open_containers() {
+        let (value, diagnostics) = recover(r#"[1, {"a": [2, 3"#);
+        assert_eq!(value, r#"[1,{"a":[2,3]}]"#);
+        assert_eq!(diagnostics, ["expect ] before end of input", "expect } before end of input", "expect ] before end of input"]);
+    }
+
+    #[test]
+    fn recovering_trailing_input() {
+        assert_eq!(recover("[1] 2"), ("[1]".to_string(), vec!["expect end of input at position: 5".to_string()]));
+        assert_eq!(recover("[1] @"), ("[1]".to_string(), vec!["expect end of input at position: 5".to_string()]));
+        assert_eq!(recover("[1  "), ("[1]".to_string(), vec!["expect ] before end of input".to_string()]));
+    }
+
+    #[test]
+    fn recovering_without_errors() {
+        let src = r#"{"a": [1, 2, {"b": null}]}"#;
+        assert_eq!(recover(src), (DefiniteParser::new(Lexer::new(src)).parse().unwrap().to_canonical_string().unwrap(), vec![]));
+    }
+}
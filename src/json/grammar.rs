@@ -0,0 +1,431 @@
+use std::collections::BTreeSet;
+use std::fmt::{Display, Formatter};
+use super::Result;
+
+// 上下文无关文法，计算FIRST/FOLLOW/SELECT集并生成LL1预测分析表
+//
+// 文法的文本格式与README中的相同，每行一个非终结符:
+//   TOKEN: [ { } ] , : string number bool null
+//   Value -> [ Array' | { Object' | string | number | bool | null
+//   ValueList -> , Value ValueList | ε
+// - 符号之间以空白分隔，`|`分隔候选式，`ε`表示空
+// - `TOKEN:`一行声明终结符，此时其余符号都必须是非终结符；没有这一行时，不出现在`->`左边的符号都是终结符
+// - 以`|`开头的行是上一个非终结符的候选式，空行和`//`开头的行被忽略
+// - 第一个产生式的左部为起始符号，`#`为输入的结束符
+//
+// 符号编号: 非终结符按在左部出现的顺序排在最前面，然后是终结符，最后是`#`
+
+pub type Symbol = usize;
+
+/// 产生式，右部为空表示ε
+#[derive(Debug, Clone)]
+pub struct Production {
+    pub head: Symbol,
+    pub body: Vec<Symbol>,
+}
+
+pub struct Grammar {
+    names: Vec<String>,
+    // 非终结符的个数，编号小于它的是非终结符
+    nonterminals: usize,
+    productions: Vec<Production>,
+    nullable: Vec<bool>,
+    first: Vec<BTreeSet<Symbol>>,
+    follow: Vec<BTreeSet<Symbol>>,
+}
+
+/// LL1冲突: 同一个非终结符的多个产生式的SELECT集包含同一个终结符
+#[derive(Debug, Clone)]
+pub struct Conflict {
+    pub nonterminal: String,
+    pub terminal: String,
+    pub productions: Vec<String>,
+}
+
+impl Display for Conflict {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "LL(1) conflict on ({}, {}): {}", self.nonterminal, self.terminal, self.productions.join("  /  "))
+    }
+}
+
+/// 预测分析表，`get(A, a)`为栈顶为A、输入为a时应用的产生式编号
+pub struct PredictTable {
+    nonterminals: usize,
+    width: usize,
+    cells: Vec<Option<usize>>,
+}
+
+impl PredictTable {
+    pub fn get(&self, nonterminal: Symbol, terminal: Symbol) -> Option<usize> {
+        self.cells[nonterminal * self.width + terminal - self.nonterminals]
+    }
+
+    /// 栈顶为nonterminal时可以接受的终结符
+    pub fn expected(&self, nonterminal: Symbol) -> Vec<Symbol> {
+        (0..self.width)
+            .filter(|i| self.cells[nonterminal * self.width + i].is_some())
+            .map(|i| i + self.nonterminals)
+            .collect()
+    }
+
+    /// 输出为README中的markdown表格，-1表示错误
+    pub fn markdown(&self, grammar: &Grammar) -> String {
+        let terminals: Vec<Symbol> = grammar.terminals().collect();
+        let mut out = String::from("| noterminal |");
+        for t in &terminals {
+            out.push_str(&format!(" {} |", grammar.name(*t)));
+        }
+        out.push_str("\n|------------|");
+        out.push_str(&"----|".repeat(terminals.len()));
+        for a in grammar.nonterminals() {
+            out.push_str(&format!("\n| {:<10} |", grammar.name(a)));
+            for t in &terminals {
+                match self.get(a, *t) {
+                    Some(index) => out.push_str(&format!(" {} |", index)),
+                    None => out.push_str(" -1 |"),
+                }
+            }
+        }
+        out
+    }
+}
+
+impl Grammar {
+    pub fn parse(text: &str) -> Result<Grammar> {
+        let mut declared: Option<Vec<&str>> = None;
+        // (左部, 各候选式)
+        let mut rules: Vec<(&str, Vec<Vec<&str>>)> = vec![];
+        for (line_no, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with("//") {
+                continue;
+            }
+            if let Some(tokens) = line.strip_prefix("TOKEN:") {
+                declared = Some(tokens.split_whitespace().collect());
+                continue;
+            }
+            let (head, body) = if let Some(body) = line.strip_prefix('|') {
+                match rules.last() {
+                    Some((head, _)) => (*head, body),
+                    None => return Err(format!("line {}: expect ->", line_no + 1)),
+                }
+            } else {
+                match line.split_once("->") {
+                    Some((head, body)) if head.split_whitespace().count() == 1 => (head.trim(), body),
+                    _ => return Err(format!("line {}: expect `Head -> body`", line_no + 1)),
+                }
+            };
+            if head == "#" || head == "ε" {
+                return Err(format!("line {}: {} is reserved", line_no + 1, head));
+            }
+            let mut alternatives = vec![];
+            for alternative in body.split('|') {
+                let symbols: Vec<&str> = alternative.split_whitespace().collect();
+                if symbols.is_empty() {
+                    return Err(format!("line {}: empty alternative, use ε", line_no + 1));
+                }
+                if symbols == ["ε"] {
+                    alternatives.push(vec![]);
+                } else if symbols.iter().any(|s| *s == "ε" || *s == "#") {
+                    return Err(format!("line {}: ε and # can not appear in a sequence", line_no + 1));
+                } else {
+                    alternatives.push(symbols);
+                }
+            }
+            match rules.iter_mut().find(|(h, _)| *h == head) {
+                Some((_, existing)) => existing.extend(alternatives),
+                None => rules.push((head, alternatives)),
+            }
+        }
+        if rules.is_empty() {
+            return Err("empty grammar".to_string());
+        }
+
+        let mut names: Vec<String> = rules.iter().map(|(head, _)| head.to_string()).collect();
+        let nonterminals = names.len();
+        match &declared {
+            Some(tokens) => {
+                for token in tokens {
+                    if names.iter().any(|n| n == token) {
+                        return Err(format!("{} is declared as a token but has productions", token));
+                    }
+                    names.push(token.to_string());
+                }
+            }
+            None => {
+                for (_, alternatives) in &rules {
+                    for symbol in alternatives.iter().flatten() {
+                        if !names.iter().any(|n| n == symbol) {
+                            names.push(symbol.to_string());
+                        }
+                    }
+                }
+            }
+        }
+        names.push("#".to_string());
+
+        let mut productions = vec![];
+        for (head, alternatives) in &rules {
+            let head = names.iter().position(|n| n == head).unwrap();
+            for alternative in alternatives {
+                let mut body = vec![];
+                for symbol in alternative {
+                    match names.iter().position(|n| n == symbol) {
+                        Some(s) => body.push(s),
+                        None => return Err(format!("undefined symbol {} in {}", symbol, names[head])),
+                    }
+                }
+                productions.push(Production { head, body });
+            }
+        }
+
+        let mut grammar = Grammar {
+            nullable: vec![false; names.len()],
+            first: vec![BTreeSet::new(); names.len()],
+            follow: vec![BTreeSet::new(); names.len()],
+            names,
+            nonterminals,
+            productions,
+        };
+        grammar.compute_first();
+        grammar.compute_follow();
+        Ok(grammar)
+    }
+
+    // 不动点迭代，终结符的FIRST集为自身
+    fn compute_first(&mut self) {
+        for t in self.nonterminals..self.names.len() {
+            self.first[t].insert(t);
+        }
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for p in 0..self.productions.len() {
+                let Production { head, body } = &self.productions[p];
+                let (first, nullable) = self.first_of(body);
+                let head = *head;
+                let before = self.first[head].len();
+                self.first[head].extend(first);
+                if self.first[head].len() != before || (nullable && !self.nullable[head]) {
+                    self.nullable[head] |= nullable;
+                    changed = true;
+                }
+            }
+        }
+    }
+
+    // FOLLOW(起始符号)包含#；A -> αBβ时FOLLOW(B)包含FIRST(β)，β能推出ε时还包含FOLLOW(A)
+    fn compute_follow(&mut self) {
+        let (start, eof) = (self.start(), self.eof());
+        self.follow[start].insert(eof);
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for p in 0..self.productions.len() {
+                let head = self.productions[p].head;
+                for i in 0..self.productions[p].body.len() {
+                    let symbol = self.productions[p].body[i];
+                    if self.is_terminal(symbol) {
+                        continue;
+                    }
+                    let (mut follow, nullable) = self.first_of(&self.productions[p].body[i + 1..]);
+                    if nullable {
+                        follow.extend(self.follow[head].iter().copied());
+                    }
+                    let before = self.follow[symbol].len();
+                    self.follow[symbol].extend(follow);
+                    changed |= self.follow[symbol].len() != before;
+                }
+            }
+        }
+    }
+
+    pub fn name(&self, symbol: Symbol) -> &str {
+        &self.names[symbol]
+    }
+
+    pub fn symbol(&self, name: &str) -> Option<Symbol> {
+        self.names.iter().position(|n| n == name)
+    }
+
+    pub fn is_terminal(&self, symbol: Symbol) -> bool {
+        symbol >= self.nonterminals
+    }
+
+    pub fn start(&self) -> Symbol {
+        0
+    }
+
+    /// 结束符`#`
+    pub fn eof(&self) -> Symbol {
+        self.names.len() - 1
+    }
+
+    pub fn nonterminals(&self) -> std::ops::Range<Symbol> {
+        0..self.nonterminals
+    }
+
+    /// 所有终结符，包括`#`
+    pub fn terminals(&self) -> std::ops::Range<Symbol> {
+        self.nonterminals..self.names.len()
+    }
+
+    pub fn productions(&self) -> &[Production] {
+        &self.productions
+    }
+
+    /// 如`Array' -> Value ValueList ]`
+    pub fn production_string(&self, index: usize) -> String {
+        let Production { head, body } = &self.productions[index];
+        let body: Vec<&str> = body.iter().map(|s| self.name(*s)).collect();
+        let body = if body.is_empty() { "ε".to_string() } else { body.join(" ") };
+        format!("{} -> {}", self.name(*head), body)
+    }
+
+    /// FIRST(symbol)，不含ε，是否能推出ε由nullable给出
+    pub fn first(&self, symbol: Symbol) -> &BTreeSet<Symbol> {
+        &self.first[symbol]
+    }
+
+    pub fn nullable(&self, symbol: Symbol) -> bool {
+        self.nullable[symbol]
+    }
+
+    pub fn follow(&self, symbol: Symbol) -> &BTreeSet<Symbol> {
+        &self.follow[symbol]
+    }
+
+    /// 符号串的FIRST集，以及它是否能推出ε
+    pub fn first_of(&self, symbols: &[Symbol]) -> (BTreeSet<Symbol>, bool) {
+        let mut first = BTreeSet::new();
+        for symbol in symbols {
+            first.extend(self.first[*symbol].iter().copied());
+            if !self.nullable[*symbol] {
+                return (first, false);
+            }
+        }
+        (first, true)
+    }
+
+    /// SELECT(A -> α) = FIRST(α)，α能推出ε时再并上FOLLOW(A)
+    pub fn select(&self, index: usize) -> BTreeSet<Symbol> {
+        let Production { head, body } = &self.productions[index];
+        let (mut select, nullable) = self.first_of(body);
+        if nullable {
+            select.extend(self.follow[*head].iter().copied());
+        }
+        select
+    }
+
+    /// 构造预测分析表，不是LL1文法时返回所有冲突
+    pub fn predict_table(&self) -> std::result::Result<PredictTable, Vec<Conflict>> {
+        let width = self.names.len() - self.nonterminals;
+        let mut cells: Vec<Vec<usize>> = vec![vec![]; self.nonterminals * width];
+        for index in 0..self.productions.len() {
+            let head = self.productions[index].head;
+            for terminal in self.select(index) {
+                cells[head * width + terminal - self.nonterminals].push(index);
+            }
+        }
+        let mut conflicts = vec![];
+        for (i, cell) in cells.iter().enumerate() {
+            if cell.len() > 1 {
+                conflicts.push(Conflict {
+                    nonterminal: self.names[i / width].clone(),
+                    terminal: self.names[self.nonterminals + i % width].clone(),
+                    productions: cell.iter().map(|p| self.production_string(*p)).collect(),
+                });
+            }
+        }
+        if !conflicts.is_empty() {
+            return Err(conflicts);
+        }
+        Ok(PredictTable {
+            nonterminals: self.nonterminals,
+            width,
+            cells: cells.into_iter().map(|cell| cell.first().copied()).collect(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXPR: &str = "
+        // 消除左递归后的算术表达式
+        E -> T E'
+        E' -> + T E' | ε
+        T -> F T'
+        T' -> * F T' | ε
+        F -> ( E ) | id
+    ";
+
+    fn error(text: &str) -> String {
+        Grammar::parse(text).err().unwrap()
+    }
+
+    fn names(grammar: &Grammar, set: &BTreeSet<Symbol>) -> Vec<String> {
+        set.iter().map(|s| grammar.name(*s).to_string()).collect()
+    }
+
+    #[test]
+    fn first_and_follow() {
+        let g = Grammar::parse(EXPR).unwrap();
+        let s = |name| g.symbol(name).unwrap();
+        assert_eq!(names(&g, g.first(s("E"))), ["(", "id"]);
+        assert_eq!(names(&g, g.first(s("E'"))), ["+"]);
+        assert!(g.nullable(s("E'")) && g.nullable(s("T'")) && !g.nullable(s("T")));
+        assert_eq!(names(&g, g.follow(s("E"))), [")", "#"]);
+        assert_eq!(names(&g, g.follow(s("T"))), ["+", ")", "#"]);
+        assert_eq!(names(&g, g.follow(s("F"))), ["+", "*", ")", "#"]);
+        // T' -> ε 的SELECT集为FOLLOW(T')
+        assert_eq!(names(&g, &g.select(5)), ["+", ")", "#"]);
+        assert_eq!(g.production_string(5), "T' -> ε");
+    }
+
+    #[test]
+    fn predict_table() {
+        let g = Grammar::parse(EXPR).unwrap();
+        let table = g.predict_table().unwrap();
+        let s = |name| g.symbol(name).unwrap();
+        assert_eq!(table.get(s("E"), s("id")), Some(0));
+        assert_eq!(table.get(s("E'"), s(")")), Some(2));
+        assert_eq!(table.get(s("E'"), g.eof()), Some(2));
+        assert_eq!(table.get(s("F"), s("+")), None);
+        assert_eq!(table.expected(s("F")), vec![s("("), s("id")]);
+    }
+
+    #[test]
+    fn json_grammar_matches_readme() {
+        let readme = include_str!("../../README.md");
+        let start = readme.find("TOKEN:").unwrap();
+        let g = Grammar::parse(&readme[start..start + readme[start..].find("```").unwrap()]).unwrap();
+        let table = g.predict_table().unwrap();
+        let s = |name| g.symbol(name).unwrap();
+        assert_eq!(table.get(s("Value"), s("[")), Some(0));
+        assert_eq!(table.get(s("Array'"), s("]")), Some(6));
+        assert_eq!(table.get(s("Array'"), s("null")), Some(7));
+        assert_eq!(table.get(s("ValueList"), s("]")), Some(9));
+        assert_eq!(table.get(s("PairList"), s("}")), Some(13));
+        // 能推出ε的ValueList和PairList的FOLLOW集中没有#，#列全空
+        assert!(g.nonterminals().all(|a| table.get(a, g.eof()).is_none()));
+    }
+
+    #[test]
+    fn conflicts() {
+        let g = Grammar::parse("E -> E + id | id").unwrap();
+        let conflicts = g.predict_table().err().unwrap();
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0].to_string(), "LL(1) conflict on (E, id): E -> E + id  /  E -> id");
+    }
+
+    #[test]
+    fn invalid_grammar() {
+        assert_eq!(error("| a"), "line 1: expect ->");
+        assert_eq!(error("A B -> c"), "line 1: expect `Head -> body`");
+        assert_eq!(error("A -> a |"), "line 1: empty alternative, use ε");
+        assert_eq!(error("A -> a ε"), "line 1: ε and # can not appear in a sequence");
+        assert_eq!(error("# -> a"), "line 1: # is reserved");
+    }
+}
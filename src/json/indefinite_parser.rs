@@ -1,5 +1,7 @@
-use std::collections::HashMap;
-use super::{Lexer, Result, TOKEN, Value};
+use super::{Lexer, Mark, Result, TOKEN, Value};
+use super::parse_tree::{ParseTree, TreeBuilder};
+use super::parser::Parser;
+use super::trace::{input, Step, StepKind, Tracer};
 
 // Value -> Array | Object | String | Number | Bool | Null
 // Array -> [Value ValueList]
@@ -11,14 +13,87 @@ use super::{Lexer, Result, TOKEN, Value};
 // PairList -> , Pair PairList
 // PairList -> ε
 // Pair -> String : Value
+//
+// 依次尝试候选式，失败时用Lexer的mark/rewind回到尝试前的位置，回溯的次数记录在parser中
+// 只向前看一个token就能决定的候选式用Lexer::peek选择，不算作回溯
 pub struct IndefiniteParser<'s> {
     lexer: Lexer<'s>,
+    backtracks: usize,
+    trace: Option<Vec<Backtrack>>,
+    tracer: Option<Tracer>,
+    // with_tracer时记录调用栈中的非终结符
+    rules: Vec<&'static str>,
+    // 仅在parse_tree时构造
+    tree: Option<TreeBuilder<'s>>,
+}
+
+/// 一次回溯
+#[derive(Debug, Clone)]
+pub struct Backtrack {
+    // 回溯发生时所在的非终结符
+    pub rule: &'static str,
+    // 从from回到to
+    pub from: usize,
+    pub to: usize,
+    // 撤销的token数
+    pub tokens: usize,
 }
 
 impl<'s> IndefiniteParser<'s> {
     pub fn new(lexer: Lexer<'s>) -> IndefiniteParser<'s> {
         IndefiniteParser {
             lexer,
+            backtracks: 0,
+            trace: None,
+            tracer: None,
+            rules: vec![],
+            tree: None,
+        }
+    }
+
+    /// 记录每次进入的非终结符和当时的调用栈，包括回溯前失败的尝试
+    pub fn with_tracer(self, tracer: Tracer) -> IndefiniteParser<'s> {
+        IndefiniteParser { tracer: Some(tracer), ..self }
+    }
+
+    pub fn take_tracer(&mut self) -> Option<Tracer> {
+        self.tracer.take()
+    }
+
+    /// 记录每一次回溯
+    pub fn traced(self) -> IndefiniteParser<'s> {
+        IndefiniteParser { trace: Some(vec![]), ..self }
+    }
+
+    /// 回溯的次数
+    pub fn backtracks(&self) -> usize {
+        self.backtracks
+    }
+
+    /// 回溯后重新读取的token总数，同一个token每重新读取一次计一次
+    pub fn relexed(&self) -> usize {
+        self.lexer.relexed()
+    }
+
+    /// traced时记录的回溯，按发生的顺序
+    pub fn trace(&self) -> &[Backtrack] {
+        self.trace.as_deref().unwrap_or(&[])
+    }
+
+    // lexer的位置，以及分析树当前节点下的子树数
+    fn mark(&self) -> (Mark, usize) {
+        (self.lexer.mark(), self.tree.as_ref().map_or(0, |tree| tree.len()))
+    }
+
+    fn rewind(&mut self, rule: &'static str, (mark, len): (Mark, usize)) {
+        if let Some(tree) = &mut self.tree {
+            tree.truncate(len);
+        }
+        let from = self.lexer.index();
+        let tokens = self.lexer.rewind(mark);
+        self.backtracks += 1;
+        if let Some(trace) = &mut self.trace {
+            trace.push(Backtrack { rule, from, to: self.lexer.index(), tokens });
         }
     }
 
@@ -26,37 +101,105 @@ impl<'s> IndefiniteParser<'s> {
         self.parse_value()
     }
 
-    // Value -> Array | Object | String | Number | Bool | Null
-    fn parse_value(&mut self) -> Result<Value<'s>> {
+    /// 解析的同时构造原始文法的分析树，回溯时撤销失败的尝试
+    pub fn parse_tree(&mut self) -> Result<(Value<'s>, ParseTree<'s>)> {
+        self.tree = Some(TreeBuilder::new());
+        let value = self.parse_value();
+        let tree = self.tree.take().unwrap();
+        Ok((value?, tree.finish().unwrap()))
+    }
+
+    fn lex(&mut self) -> Result<TOKEN<'s>> {
         let token = self.lexer.lex()?;
-        return match token {
-            TOKEN::LBRACE => {
-                self.lexer.push_back();
-                self.parse_object()
-            }
-            TOKEN::LBRACKET => {
-                self.lexer.push_back();
-                self.parse_array()
-            }
+        if let Some(tree) = &mut self.tree {
+            tree.leaf(token);
+        }
+        Ok(token)
+    }
+
+    // 进入非终结符rule，返回(包括失败)时出栈
+    fn rule<T>(&mut self, rule: &'static str, parse: fn(&mut Self) -> Result<T>) -> Result<T> {
+        if self.tracer.is_none() && self.tree.is_none() {
+            return parse(self);
+        }
+        if let Some(tracer) = &mut self.tracer {
+            self.rules.push(rule);
+            tracer.push(Step {
+                stack: self.rules.join(" "),
+                input: input(self.lexer.rest(self.lexer.index())),
+                kind: StepKind::Enter(rule),
+            });
+        }
+        if let Some(tree) = &mut self.tree {
+            tree.open(rule);
+        }
+        let result = parse(self);
+        if self.tracer.is_some() {
+            self.rules.pop();
+        }
+        if let Some(tree) = &mut self.tree {
+            tree.close(result.is_ok());
+        }
+        result
+    }
+
+    fn parse_value(&mut self) -> Result<Value<'s>> {
+        self.rule("Value", Self::value)
+    }
+
+    fn parse_array(&mut self) -> Result<Value<'s>> {
+        self.rule("Array", Self::array)
+    }
+
+    fn parse_value_list(&mut self) -> Result<Vec<Value<'s>>> {
+        self.rule("ValueList", Self::value_list)
+    }
+
+    fn parse_object(&mut self) -> Result<Value<'s>> {
+        self.rule("Object", Self::object)
+    }
+
+    fn parse_pair_list(&mut self) -> Result<Vec<(&'s str, Value<'s>)>> {
+        self.rule("PairList", Self::pair_list)
+    }
+
+    fn parse_pair(&mut self) -> Result<(&'s str, Value<'s>)> {
+        self.rule("Pair", Self::pair)
+    }
+
+    // Value -> Array | Object | String | Number | Bool | Null
+    fn value(&mut self) -> Result<Value<'s>> {
+        match self.lexer.peek()? {
+            TOKEN::LBRACE => return self.parse_object(),
+            TOKEN::LBRACKET => return self.parse_array(),
+            _ => {}
+        }
+        match self.lex()? {
             TOKEN::STRING(s) => Ok(Value::String(s)),
             TOKEN::NUMBER(n) => Ok(Value::Number(n)),
             TOKEN::BOOL(b) => Ok(Value::Bool(b)),
             TOKEN::NULL => Ok(Value::Null),
             _ => Err(format!("expect [ | {{ | string | number | bool | null at position: {}", self.lexer.index())),
-        };
+        }
     }
     // Array -> [Value ValueList]
     // Array -> []
-    fn parse_array(&mut self) -> Result<Value<'s>> {
-        if let TOKEN::LBRACKET = self.lexer.lex()? {
+    fn array(&mut self) -> Result<Value<'s>> {
+        if let TOKEN::LBRACKET = self.lex()? {
             let mut list = vec![];
-            if let Ok(value) = self.parse_value() {
-                list = self.parse_value_list()?;
-                list.push(value);
-            } else {
-                self.lexer.push_back();
+            let mark = self.mark();
+            match self.parse_value() {
+                Ok(value) => {
+                    list = self.parse_value_list()?;
+                    list.push(value);
+                    // ValueList从后向前收集元素
+                    list.reverse();
+                }
+                // 超过嵌套深度不是候选式不匹配，不回溯
+                Err(msg) if self.lexer.depth_exceeded() => return Err(msg),
+                Err(_) => self.rewind("Array", mark),
             }
-            return if let TOKEN::RBRACKET = self.lexer.lex()? {
+            return if let TOKEN::RBRACKET = self.lex()? {
                 Ok(Value::Array(list))
             } else {
                 Err(format!("expect ] at position: {}", self.lexer.index()))
@@ -67,64 +210,126 @@ impl<'s> IndefiniteParser<'s> {
 
     // ValueList -> , Value ValueList
     // ValueList -> ε
-    fn parse_value_list(&mut self) -> Result<Vec<Value<'s>>> {
-        return if let TOKEN::COMMA = self.lexer.lex()? {
+    fn value_list(&mut self) -> Result<Vec<Value<'s>>> {
+        if let TOKEN::COMMA = self.lexer.peek()? {
+            self.lex()?;
+            if self.trailing_comma(TOKEN::RBRACKET)? {
+                return Ok(vec![]);
+            }
             let value = self.parse_value()?;
             let mut list = self.parse_value_list()?;
             list.push(value);
             Ok(list)
         } else {
-            self.lexer.push_back();
             Ok(vec![])
-        };
+        }
     }
 
     // Object -> { Pair PairList }
     // Object -> {}
-    fn parse_object(&mut self) -> Result<Value<'s>> {
-        if let TOKEN::LBRACE = self.lexer.lex()? {
+    fn object(&mut self) -> Result<Value<'s>> {
+        if let TOKEN::LBRACE = self.lex()? {
             let mut list = vec![];
-            if let Ok(pair) = self.parse_pair() {
-                list = self.parse_pair_list()?;
-                list.push(pair);
-            } else {
-                self.lexer.push_back();
+            let mark = self.mark();
+            match self.parse_pair() {
+                Ok(pair) => {
+                    list = self.parse_pair_list()?;
+                    list.push(pair);
+                    // 与数组相同按源文本的顺序插入，重复的键取最后一个
+                    list.reverse();
+                }
+                Err(msg) if self.lexer.depth_exceeded() => return Err(msg),
+                Err(_) => self.rewind("Object", mark),
             }
-            return if let TOKEN::RBRACE = self.lexer.lex()? {
-                Ok(Value::Object(HashMap::from_iter(list)))
+            return if let TOKEN::RBRACE = self.lex()? {
+                Ok(Value::from_members(list))
             } else {
                 Err(format!("expect }} at position: {}", self.lexer.index()))
             };
         }
-        return Err(format!("expect {{ at position: {}", self.lexer.index()));
+        Err(format!("expect {{ at position: {}", self.lexer.index()))
     }
 
     // PairList -> , Pair PairList
     // PairList -> ε
-    fn parse_pair_list(&mut self) -> Result<Vec<(&'s str, Value<'s>)>> {
-        return if let TOKEN::COMMA = self.lexer.lex()? {
+    fn pair_list(&mut self) -> Result<Vec<(&'s str, Value<'s>)>> {
+        if let TOKEN::COMMA = self.lexer.peek()? {
+            self.lex()?;
+            if self.trailing_comma(TOKEN::RBRACE)? {
+                return Ok(vec![]);
+            }
             let pair = self.parse_pair()?;
             let mut list = self.parse_pair_list()?;
             list.push(pair);
             Ok(list)
         } else {
-            self.lexer.push_back();
             Ok(vec![])
-        };
+        }
     }
 
     // Pair -> String : Value
-    fn parse_pair(&mut self) -> Result<(&'s str, Value<'s>)> {
-        if let TOKEN::STRING(s) = self.lexer.lex()? {
-            if let TOKEN::COLON = self.lexer.lex()? {
+    fn pair(&mut self) -> Result<(&'s str, Value<'s>)> {
+        if let TOKEN::STRING(s) | TOKEN::IDENT(s) = self.lex()? {
+            if let TOKEN::COLON = self.lex()? {
                 let value = self.parse_value()?;
-                return Ok((s, value));
+                Ok((s, value))
             } else {
                 Err(format!("expect : at position: {}", self.lexer.index()))
             }
         } else {
-            self.lexer.push_back();
-            Err(format!("expect string at position: {}", self.lexer.index()))
+            Err(format!("expect string at position: {}", self.lexer.token_start()))
+        }
+    }
+
+    // 宽松模式下，`,`之后紧跟`close`时视为末尾多余的`,`
+    fn trailing_comma(&mut self, close: TOKEN) -> Result<bool> {
+        if !self.lexer.is_lenient() {
+            return Ok(false);
         }
+        let token = self.lexer.peek()?;
+        Ok(std::mem::discriminant(&token) == std::mem::discriminant(&close))
     }
-}
\ No newline at end of file
+}
+
+impl<'s> Parser<'s> for IndefiniteParser<'s> {
+    fn parse(&mut self) -> Result<Value<'s>> {
+        let value = IndefiniteParser::parse(self)?;
+        if !self.lexer.at_end()? {
+            self.lexer.lex()?;
+            return Err(format!("expect end of input at position: {}", self.lexer.index()));
+        }
+        Ok(value)
+    }
+
+    fn error_position(&self) -> usize {
+        self.lexer.error_position()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(src: &str) -> IndefiniteParser<'_> {
+        let mut parser = IndefiniteParser::new(Lexer::new(src)).traced();
+        Parser::parse(&mut parser).unwrap();
+        parser
+    }
+
+    #[test]
+    fn lookahead_is_not_backtracking() {
+        let parser = parse(r#"{"a":[1,2,{"b":null}],"c":true}"#);
+        assert_eq!(parser.backtracks(), 0);
+        assert_eq!(parser.relexed(), 0);
+    }
+
+    #[test]
+    fn empty_containers_backtrack_once() {
+        // 每个空容器先尝试第一个候选式，读到 ] 或 } 失败后回溯，再重新读取这一个token
+        let parser = parse(r#"{"a":[1,[]],"b":{},"c":[[]]}"#);
+        assert_eq!(parser.backtracks(), 3);
+        assert_eq!(parser.relexed(), 3);
+        let rules: Vec<_> = parser.trace().iter().map(|b| (b.rule, b.from, b.to, b.tokens)).collect();
+        assert_eq!(rules, vec![("Array", 10, 9, 1), ("Object", 18, 17, 1), ("Array", 26, 25, 1)]);
+    }
+}
@@ -0,0 +1,379 @@
+use super::{Lexer, Result, TOKEN, Tape, Value, unescape};
+
+// 按需访问，只解析取到的值
+// Lazy记录一个值在源文本中的起点(一个定位好的Lexer)，get/index/pointer沿路径前进时
+// 跳过不需要的子树而不构造Value，取到目标后再由parse构造Value
+//
+// 跳过子树时的校验有两种:
+// - Brackets(默认): 按块查找结构字符和引号做括号匹配，检查括号配对、字符串闭合，不检查`,` `:`的位置和标量的拼写
+// - Tokens: 逐个token检查文法，与完整解析的错误相同，只是不构造Value
+// 宽松模式下注释和单引号字符串中可能出现括号，总是使用Tokens
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Validation {
+    Brackets,
+    Tokens,
+}
+
+#[derive(Clone)]
+pub struct Lazy<'s> {
+    lexer: Lexer<'s>,
+    source: &'s str,
+    validation: Validation,
+}
+
+impl<'s> Lazy<'s> {
+    pub fn new(json_str: &'s str) -> Lazy<'s> {
+        Lazy::with_lexer(Lexer::new(json_str), json_str)
+    }
+
+    pub fn lenient(json_str: &'s str) -> Lazy<'s> {
+        Lazy::with_lexer(Lexer::lenient(json_str), json_str)
+    }
+
+    fn with_lexer(lexer: Lexer<'s>, source: &'s str) -> Lazy<'s> {
+        let validation = if lexer.is_lenient() { Validation::Tokens } else { Validation::Brackets };
+        Lazy { lexer, source, validation }
+    }
+
+    pub fn validation(self, validation: Validation) -> Lazy<'s> {
+        let validation = if self.lexer.is_lenient() { Validation::Tokens } else { validation };
+        Lazy { validation, ..self }
+    }
+
+    fn at(&self, lexer: Lexer<'s>) -> Lazy<'s> {
+        Lazy { lexer, ..self.clone() }
+    }
+
+    /// 值的第一个字节
+    fn first_byte(&self) -> Result<u8> {
+        let mut lexer = self.lexer.clone();
+        if lexer.at_end()? {
+            return Err(format!("unexpected end of file at position {}", lexer.index() + 1));
+        }
+        Ok(self.source.as_bytes()[lexer.index()])
+    }
+
+    pub fn type_name(&self) -> Result<&'static str> {
+        Ok(match self.first_byte()? {
+            b'{' => "object",
+            b'[' => "array",
+            b'"' | b'\'' => "string",
+            b't' | b'f' => "bool",
+            b'n' => "null",
+            _ => "number",
+        })
+    }
+
+    /// 构造这个值，经由Tape构造，数组和对象的元素个数不受栈深度的限制
+    pub fn parse(&self) -> Result<Value<'s>> {
+        Ok(Tape::parse_value(&mut self.lexer.clone())?.root().to_value())
+    }
+
+    /// 这个值在源文本中的原文
+    pub fn raw(&self) -> Result<&'s str> {
+        let mut lexer = self.lexer.clone();
+        lexer.at_end()?;
+        let start = lexer.index();
+        self.skip(&mut lexer)?;
+        Ok(lexer.slice(start, lexer.index()))
+    }
+
+    /// 跳过整个值并确认之后没有多余的内容
+    pub fn validate(&self) -> Result<()> {
+        let mut lexer = self.lexer.clone();
+        self.skip(&mut lexer)?;
+        if !lexer.at_end()? {
+            return Err(format!("unexpected token at position {}", lexer.index()));
+        }
+        Ok(())
+    }
+
+    /// 对象中键为key的成员，键按还原转义后的内容比较
+    /// 与完整解析相同，键重复时取最后一个，因此总要读到对象的末尾
+    pub fn get(&self, key: &str) -> Result<Option<Lazy<'s>>> {
+        let mut lexer = self.lexer.clone();
+        match lexer.lex()? {
+            TOKEN::LBRACE => {}
+            _ => return Err(format!("expect object at position {}", lexer.token_start())),
+        }
+        let mut found = None;
+        let mut first = true;
+        loop {
+            let token = lexer.lex()?;
+            let token = match token {
+                TOKEN::RBRACE => return Ok(found),
+                TOKEN::COMMA if !first => lexer.lex()?,
+                _ if first => token,
+                _ => return Err(format!("expect , or }} at position {}", lexer.token_start())),
+            };
+            let name = match token {
+                TOKEN::STRING(s) | TOKEN::IDENT(s) => s,
+                TOKEN::RBRACE if lexer.is_lenient() => return Ok(found),
+                _ => return Err(format!("expect string at position {}", lexer.token_start())),
+            };
+            match lexer.lex()? {
+                TOKEN::COLON => {}
+                _ => return Err(format!("expect : at position {}", lexer.token_start())),
+            }
+            if unescape(name)? == key {
+                found = Some(self.at(lexer.clone()));
+            }
+            self.skip(&mut lexer)?;
+            first = false;
+        }
+    }
+
+    /// 数组的第index个元素
+    pub fn index(&self, index: usize) -> Result<Option<Lazy<'s>>> {
+        let mut lexer = self.lexer.clone();
+        match lexer.lex()? {
+            TOKEN::LBRACKET => {}
+            _ => return Err(format!("expect array at position {}", lexer.token_start())),
+        }
+        let mut i = 0;
+        loop {
+            let token = lexer.lex()?;
+            match token {
+                TOKEN::RBRACKET => return Ok(None),
+                TOKEN::COMMA if i > 0 => {
+                    if let TOKEN::RBRACKET = lexer.lex()? {
+                        if lexer.is_lenient() {
+                            return Ok(None);
+                        }
+                        return Err(format!("unexpected ] at position {}", lexer.token_start()));
+                    }
+                    lexer.push_back();
+                }
+                _ if i == 0 => lexer.push_back(),
+                _ => return Err(format!("expect , or ] at position {}", lexer.token_start())),
+            }
+            if i == index {
+                return Ok(Some(self.at(lexer)));
+            }
+            self.skip(&mut lexer)?;
+            i += 1;
+        }
+    }
+
+    /// 按JSON Pointer(RFC 6901)取值
+    pub fn pointer(&self, pointer: &str) -> Result<Option<Lazy<'s>>> {
+        if pointer.is_empty() {
+            return Ok(Some(self.clone()));
+        }
+        let tokens = match pointer.strip_prefix('/') {
+            Some(tokens) => tokens,
+            None => return Ok(None),
+        };
+        let mut value = self.clone();
+        for token in tokens.split('/') {
+            let token = token.replace("~1", "/").replace("~0", "~");
+            let next = match value.type_name()? {
+                "object" => value.get(&token)?,
+                "array" => match token.parse::<usize>() {
+                    Ok(index) => value.index(index)?,
+                    Err(_) => None,
+                },
+                _ => None,
+            };
+            value = match next {
+                Some(next) => next,
+                None => return Ok(None),
+            };
+        }
+        Ok(Some(value))
+    }
+
+    fn skip(&self, lexer: &mut Lexer<'s>) -> Result<()> {
+        match self.validation {
+            Validation::Brackets => skip_brackets(lexer, self.source.as_bytes()),
+            Validation::Tokens => skip_tokens(lexer),
+        }
+    }
+}
+
+// 括号匹配跳过一个值，标量仍由Lexer读取
+fn skip_brackets(lexer: &mut Lexer, bytes: &[u8]) -> Result<()> {
+    lexer.at_end()?;
+    let start = lexer.index();
+    match bytes.get(start) {
+        Some(b'{' | b'[') => {}
+        _ => return lexer.lex().map(|_| ()),
+    }
+    let scanner = lexer.scanner();
+    let mut open = vec![bytes[start]];
+    let mut i = start + 1;
+    while !open.is_empty() {
+        i = scanner.find_structural_or_quote(bytes, i);
+        match bytes.get(i) {
+            None => return Err(format!("unexpected end of file at position {}", i + 1)),
+            Some(b'"') => {
+                let quote = i;
+                i += 1;
+                loop {
+                    i = scanner.find_quote_or_escape(bytes, i, b'"');
+                    match bytes.get(i) {
+                        None => return Err(format!("unterminated string at position {}", quote)),
+                        Some(b'"') => break,
+                        _ => i += 2,
+                    }
+                }
+            }
+            Some(b @ (b'{' | b'[')) => open.push(*b),
+            Some(b @ (b'}' | b']')) => {
+                let expected = if open.pop() == Some(b'{') { b'}' } else { b']' };
+                if *b != expected {
+                    return Err(format!("expect {} at position {}", expected as char, i));
+                }
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    lexer.skip_to(i);
+    Ok(())
+}
+
+// 逐个token检查文法跳过一个值，与Tape::parse_lexer的结构相同
+fn skip_tokens(lexer: &mut Lexer) -> Result<()> {
+    let mut open: Vec<bool> = vec![];
+    loop {
+        let mut closed = false;
+        match lexer.lex()? {
+            token @ (TOKEN::LBRACE | TOKEN::LBRACKET) => {
+                let object = matches!(token, TOKEN::LBRACE);
+                open.push(object);
+                if is_close(object, &lexer.lex()?) {
+                    closed = true;
+                } else {
+                    lexer.push_back();
+                    if object {
+                        member_key(lexer)?;
+                    }
+                    continue;
+                }
+            }
+            TOKEN::STRING(_) | TOKEN::NUMBER(_) | TOKEN::BOOL(_) | TOKEN::NULL => {}
+            _ => return Err(format!("unexpected token at position {}", lexer.token_start())),
+        }
+        loop {
+            let object = match open.last() {
+                Some(object) => *object,
+                None => return Ok(()),
+            };
+            if closed {
+                closed = false;
+                open.pop();
+                continue;
+            }
+            match lexer.lex()? {
+                TOKEN::COMMA => {
+                    if is_close(object, &lexer.lex()?) && lexer.is_lenient() {
+                        closed = true;
+                        continue;
+                    }
+                    lexer.push_back();
+                    if object {
+                        member_key(lexer)?;
+                    }
+                    break;
+                }
+                token if is_close(object, &token) => {
+                    open.pop();
+                }
+                _ => {
+                    let close = if object { "}" } else { "]" };
+                    return Err(format!("expect , or {} at position {}", close, lexer.token_start()));
+                }
+            }
+        }
+    }
+}
+
+fn is_close(object: bool, token: &TOKEN) -> bool {
+    if object {
+        matches!(token, TOKEN::RBRACE)
+    } else {
+        matches!(token, TOKEN::RBRACKET)
+    }
+}
+
+fn member_key(lexer: &mut Lexer) -> Result<()> {
+    match lexer.lex()? {
+        TOKEN::STRING(_) | TOKEN::IDENT(_) => {}
+        _ => return Err(format!("expect string at position {}", lexer.token_start())),
+    }
+    match lexer.lex()? {
+        TOKEN::COLON => Ok(()),
+        _ => Err(format!("expect : at position {}", lexer.token_start())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::json::{from_str, from_str_with, Options, ParserKind};
+
+    const SRC: &str = r#"{"a": 1, "\u0062": [true, {"c": "x"}], "a": 2, "d~/e": null}"#;
+
+    fn get(lazy: &Lazy, pointer: &str) -> Option<String> {
+        lazy.pointer(pointer).unwrap().map(|v| v.raw().unwrap().to_string())
+    }
+
+    #[test]
+    fn same_as_full_parse() {
+        let value = from_str(SRC).unwrap();
+        for validation in [Validation::Brackets, Validation::Tokens] {
+            let lazy = Lazy::new(SRC).validation(validation);
+            for pointer in ["", "/a", "/b", "/b/0", "/b/1/c", "/d~0~1e"] {
+                let expect = value.pointer(pointer).map(|v| v.to_canonical_string().unwrap());
+                let found = lazy.pointer(pointer).unwrap().map(|v| v.parse().unwrap().to_canonical_string().unwrap());
+                assert_eq!(found, expect, "{}", pointer);
+            }
+        }
+    }
+
+    #[test]
+    fn duplicate_keys_take_last() {
+        let lazy = Lazy::new(SRC);
+        assert_eq!(get(&lazy, "/a"), Some("2".to_string()));
+        for kind in ParserKind::ALL {
+            let value = from_str_with(kind, &Options::default(), SRC).unwrap();
+            assert_eq!(value.get("a"), Some(&Value::Number(2.0)), "{}", kind);
+        }
+    }
+
+    #[test]
+    fn keys_compare_unescaped() {
+        let lazy = Lazy::new(SRC);
+        assert_eq!(get(&lazy, "/b/0"), Some("true".to_string()));
+        // 转义的原文不是键
+        assert_eq!(get(&lazy, "/\\u0062"), None);
+        assert_eq!(get(&lazy, "/x"), None);
+        assert_eq!(get(&lazy, "/b/2"), None);
+        assert_eq!(get(&lazy, "/a/0"), None);
+    }
+
+    #[test]
+    fn errors() {
+        assert!(Lazy::new("[1, 2").index(5).is_err());
+        assert!(Lazy::new(r#"{"a" 1}"#).get("a").is_err());
+        assert!(Lazy::new("[1]").get("a").is_err());
+        assert!(Lazy::new("{} x").validate().is_err());
+        assert!(Lazy::new(r#"{"a": [1, 2], "b": 3}"#).validate().is_ok());
+        assert_eq!(Lazy::lenient("{a: 1, /* } */ b: 2,}").get("b").unwrap().unwrap().raw().unwrap(), "2");
+    }
+
+    #[test]
+    fn parse_large_array() {
+        let array = format!("[{}]", vec!["1"; 200_000].join(","));
+        let src = format!(r#"{{"a": {}, "b": 2}}"#, array);
+        // 根和后面还有内容的子值
+        for value in [Lazy::new(&array).parse(), Lazy::new(&src).get("a").unwrap().unwrap().parse()] {
+            match value.unwrap() {
+                Value::Array(items) => assert_eq!(items.len(), 200_000),
+                value => panic!("expect array, found {}", value.type_name()),
+            }
+        }
+    }
+}
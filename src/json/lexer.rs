@@ -1,6 +1,8 @@
+use std::borrow::Cow;
 use super::Result;
+use super::scan::Scanner;
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 #[repr(u8)]
 pub enum TOKEN<'s> {
     LBRACE = 0,
@@ -13,52 +15,182 @@ pub enum TOKEN<'s> {
     NUMBER(f64),
     BOOL(bool),
     NULL,
+    IDENT(&'s str),
+    // 输入的结束符`#`，只由lex_with_eof产生
+    EOF,
 }
 
+#[derive(Clone)]
 pub struct Lexer<'s> {
     json_str: &'s [u8],
     index: usize,
     current_token_size: usize,
+    lenient: bool,
+    scanner: Scanner,
+    // 当前位置之前的token数，rewind时回退
+    tokens: usize,
+    // 曾经读到的最远位置之前的token数，低于它时读取的token是重新读取的
+    furthest: usize,
+    // 回溯后重新读取的token数，每读取一次计一次
+    relexed: usize,
+    // 当前未闭合的 [ { 的个数，超过max_depth时报错
+    depth: usize,
+    max_depth: usize,
+    // 是否曾经超过max_depth，回溯时不恢复
+    exceeded: bool,
+    // 上一次读取出错的位置，成功读取时清除
+    error: Option<usize>,
 }
 
+/// Lexer的位置，由mark保存，rewind回到该位置
+#[derive(Debug, Clone, Copy)]
+pub struct Mark {
+    index: usize,
+    current_token_size: usize,
+    tokens: usize,
+    depth: usize,
+}
 
+// 宽松模式(JSON5)在RFC 8259的基础上额外接受:
+// - `//`行注释和`/* */`块注释
+// - 数组和对象末尾多余的`,`(由parser处理)
+// - 单引号字符串
+// - 对象中不带引号的标识符键，词法上为`TOKEN::IDENT`
+// - 十六进制数字，`+`开头、`.`开头或`.`结尾的数字，`Infinity`和`NaN`
 impl<'s> Lexer<'s> {
     pub fn new(json_str: &'s str) -> Lexer<'s> {
         Lexer {
             json_str: json_str.as_bytes(),
             index: 0,
             current_token_size: 0,
+            lenient: false,
+            scanner: Scanner::detect(),
+            tokens: 0,
+            furthest: 0,
+            relexed: 0,
+            depth: 0,
+            max_depth: usize::MAX,
+            exceeded: false,
+            error: None,
+        }
+    }
+
+    /// 从字节构造，先批量校验UTF-8
+    pub fn from_slice(json_str: &'s [u8]) -> Result<Lexer<'s>> {
+        Scanner::detect().validate_utf8(json_str)
+            .map_err(|index| format!("invalid UTF-8 at position {}", index))?;
+        Ok(Lexer {
+            json_str,
+            ..Lexer::new("")
+        })
+    }
+
+    /// 指定扫描空白和字符串时使用的实现，默认为当前CPU上最快的实现
+    pub fn with_scanner(self, scanner: Scanner) -> Lexer<'s> {
+        Lexer { scanner, ..self }
+    }
+
+    /// 限制 [ { 的嵌套深度，超过时lex返回错误，默认不限制
+    pub fn with_max_depth(self, max_depth: usize) -> Lexer<'s> {
+        Lexer { max_depth, ..self }
+    }
+
+    /// 是否因超过嵌套深度而报过错，回溯的parser据此区分候选式不匹配和超出限制
+    pub fn depth_exceeded(&self) -> bool {
+        self.exceeded
+    }
+
+    pub fn lenient(json_str: &'s str) -> Lexer<'s> {
+        Lexer {
+            lenient: true,
+            ..Lexer::new(json_str)
         }
     }
 
-    fn eof_msg(&self) -> String {
+    pub fn is_lenient(&self) -> bool {
+        self.lenient
+    }
+
+    // 记录出错的位置
+    fn fail(&mut self, msg: &str, position: usize) -> String {
+        self.error = Some(position);
+        format!("{} at position {}", msg, position)
+    }
+
+    fn eof_msg(&mut self) -> String {
+        self.error = Some(self.index);
         format!("unexpected end of file at position {}", self.index + 1)
     }
 
-    pub fn lex(&mut self) -> Result<TOKEN<'s>> {
-        if self.index >= self.json_str.len() {
-            return Err(self.eof_msg());
+    fn peek_byte(&self, offset: usize) -> Option<u8> {
+        self.json_str.get(self.index + offset).copied()
+    }
+
+    fn starts_with(&self, word: &[u8]) -> bool {
+        self.json_str[self.index..].starts_with(word)
+    }
+
+    // word之后不能紧跟标识符字符
+    fn starts_with_word(&self, word: &[u8]) -> bool {
+        self.starts_with(word) && !self.peek_byte(word.len()).is_some_and(is_ident_continue)
+    }
+
+    fn skip_blank(&mut self) -> Result<()> {
+        self.error = None;
+        loop {
+            self.index = self.scanner.skip_whitespace(self.json_str, self.index);
+            match self.peek_byte(0) {
+                Some(b'/') if self.lenient && self.peek_byte(1) == Some(b'/') => {
+                    while !matches!(self.peek_byte(0), None | Some(b'\n')) {
+                        self.index += 1;
+                    }
+                }
+                Some(b'/') if self.lenient && self.peek_byte(1) == Some(b'*') => {
+                    let start = self.index;
+                    self.index += 2;
+                    while !self.starts_with(b"*/") {
+                        if self.index >= self.json_str.len() {
+                            return Err(self.fail("unterminated comment", start));
+                        }
+                        self.index += 1;
+                    }
+                    self.index += 2;
+                }
+                _ => return Ok(()),
+            }
         }
-        let blank = [b' ', b'\n', b'\t', b'\r'];
-        while blank.contains(self.json_str.get(self.index).ok_or(self.eof_msg())?) {
-            self.index += 1;
+    }
+
+    /// 与lex相同，但输入结束时返回`TOKEN::EOF`而不是错误
+    pub fn lex_with_eof(&mut self) -> Result<TOKEN<'s>> {
+        if self.at_end()? {
+            self.current_token_size = 0;
+            return Ok(TOKEN::EOF);
         }
+        self.lex()
+    }
+
+    pub fn lex(&mut self) -> Result<TOKEN<'s>> {
+        self.skip_blank()?;
         self.current_token_size = self.index;
-        let ret = match self.json_str.get(self.index).ok_or(self.eof_msg())? {
+        let Some(&byte) = self.json_str.get(self.index) else {
+            return Err(self.eof_msg());
+        };
+        let ret = match byte {
             b'{' => {
-                self.index += 1;
+                self.open()?;
                 TOKEN::LBRACE
             }
             b'}' => {
-                self.index += 1;
+                self.close();
                 TOKEN::RBRACE
             }
             b'[' => {
-                self.index += 1;
+                self.open()?;
                 TOKEN::LBRACKET
             }
             b']' => {
-                self.index += 1;
+                self.close();
                 TOKEN::RBRACKET
             }
             b',' => {
@@ -69,47 +201,441 @@ impl<'s> Lexer<'s> {
                 self.index += 1;
                 TOKEN::COLON
             }
-            b'"' => {
-                self.index += 1;
-                let start = self.index;
-                while !(self.json_str[self.index] == b'"' && (self.index == 0 || self.json_str[self.index - 1] != b'\\')) {
-                    self.index += 1;
+            b'"' => self.lex_string(b'"')?,
+            b'\'' if self.lenient => self.lex_string(b'\'')?,
+            b'-' => self.lex_number()?,
+            b'+' | b'.' if self.lenient => self.lex_number()?,
+            value if value.is_ascii_digit() => self.lex_number()?,
+            value if is_ident_start(value) => self.lex_ident()?,
+            _ => return Err(self.fail("unexpected token", self.index))
+        };
+        self.current_token_size = self.index - self.current_token_size;
+        if self.tokens < self.furthest {
+            self.relexed += 1;
+        }
+        self.tokens += 1;
+        self.furthest = self.furthest.max(self.tokens);
+        Ok(ret)
+    }
+
+    fn open(&mut self) -> Result<()> {
+        if self.depth == self.max_depth {
+            self.exceeded = true;
+            return Err(self.fail(&format!("exceed max depth {}", self.max_depth), self.index));
+        }
+        self.depth += 1;
+        self.index += 1;
+        Ok(())
+    }
+
+    // 多余的 ] } 由parser报告
+    fn close(&mut self) {
+        self.depth = self.depth.saturating_sub(1);
+        self.index += 1;
+    }
+
+    fn lex_string(&mut self, quote: u8) -> Result<TOKEN<'s>> {
+        let open = self.index;
+        self.index += 1;
+        let start = self.index;
+        loop {
+            let segment = self.index;
+            self.index = self.scanner.find_quote_or_escape(self.json_str, self.index, quote);
+            // 严格模式下字符串中不能出现未转义的控制字符
+            if !self.lenient {
+                if let Some(i) = self.json_str[segment..self.index].iter().position(|&b| b < 0x20) {
+                    return Err(self.fail("control character in string", segment + i));
+                }
+            }
+            match self.peek_byte(0) {
+                None => return Err(self.fail("unterminated string", open)),
+                Some(b) if b == quote => break,
+                _ => {
+                    let Some(escape) = self.peek_byte(1) else {
+                        return Err(self.fail("unterminated string", open));
+                    };
+                    let valid: &[u8] = if self.lenient { b"\"\\/bfnrtu'0vx\n" } else { b"\"\\/bfnrtu" };
+                    if !valid.contains(&escape) {
+                        return Err(self.fail("invalid escape", self.index));
+                    }
+                    self.index += 2;
                 }
-                let end = self.index;
-                self.index += 1;
-                TOKEN::STRING(std::str::from_utf8(&self.json_str[start..end]).unwrap())
             }
-            value if value.is_ascii_digit() => {
-                let start = self.index;
-                while self.json_str[self.index].is_ascii_digit() || self.json_str[self.index] == b'.' {
+        }
+        let end = self.index;
+        self.index += 1;
+        // SAFETY: 输入是合法的UTF-8，start和end都紧邻ASCII引号，是字符边界
+        Ok(TOKEN::STRING(unsafe { std::str::from_utf8_unchecked(&self.json_str[start..end]) }))
+    }
+
+    fn lex_number(&mut self) -> Result<TOKEN<'s>> {
+        let start = self.index;
+        let mut sign = 1.0;
+        if let Some(b @ (b'-' | b'+')) = self.peek_byte(0) {
+            sign = if b == b'-' { -1.0 } else { 1.0 };
+            self.index += 1;
+        }
+        if self.lenient {
+            if self.starts_with_word(b"Infinity") {
+                self.index += 8;
+                return Ok(TOKEN::NUMBER(sign * f64::INFINITY));
+            }
+            if self.starts_with_word(b"NaN") {
+                self.index += 3;
+                return Ok(TOKEN::NUMBER(f64::NAN));
+            }
+            if self.starts_with(b"0x") || self.starts_with(b"0X") {
+                self.index += 2;
+                let digits = self.index;
+                let mut n = 0.0;
+                while let Some(d) = self.peek_byte(0).and_then(|b| (b as char).to_digit(16)) {
+                    n = n * 16.0 + d as f64;
                     self.index += 1;
                 }
-                let end = self.index;
-                TOKEN::NUMBER(std::str::from_utf8(&self.json_str[start..end]).unwrap().parse().unwrap())
+                if self.index == digits {
+                    return Err(self.fail("invalid number", start));
+                }
+                return Ok(TOKEN::NUMBER(sign * n));
             }
-            _ if self.json_str[self.index..self.index + 4].as_ref() == b"true" => {
-                self.index += 4;
-                TOKEN::BOOL(true)
+        }
+        let int_digits = self.skip_digits();
+        // 宽松模式允许省略整数部分(`.5`)，但两种模式都不允许前导零
+        if (!self.lenient && int_digits == 0) || (int_digits > 1 && self.json_str[self.index - int_digits] == b'0') {
+            return Err(self.fail("invalid number", start));
+        }
+        let mut frac_digits = 0;
+        if self.peek_byte(0) == Some(b'.') {
+            self.index += 1;
+            frac_digits = self.skip_digits();
+            if !self.lenient && frac_digits == 0 {
+                return Err(self.fail("invalid number", start));
             }
-            _ if self.json_str[self.index..self.index + 5].as_ref() == b"false".as_ref() => {
-                self.index += 5;
-                TOKEN::BOOL(false)
+        }
+        if int_digits == 0 && frac_digits == 0 {
+            return Err(self.fail("invalid number", start));
+        }
+        if let Some(b'e' | b'E') = self.peek_byte(0) {
+            self.index += 1;
+            if let Some(b'-' | b'+') = self.peek_byte(0) {
+                self.index += 1;
             }
-            _ if self.json_str[self.index..self.index + 4].as_ref() == b"null".as_ref() => {
-                self.index += 4;
-                TOKEN::NULL
+            if self.skip_digits() == 0 {
+                return Err(self.fail("invalid number", start));
             }
-            _ => return Err(format!("unexpected token at position {}", self.index))
-        };
-        self.current_token_size = self.index - self.current_token_size;
-        return Ok(ret);
+        }
+        match std::str::from_utf8(&self.json_str[start..self.index]).unwrap().parse() {
+            Ok(n) => Ok(TOKEN::NUMBER(n)),
+            Err(_) => Err(self.fail("invalid number", start)),
+        }
+    }
+
+    fn skip_digits(&mut self) -> usize {
+        let start = self.index;
+        while self.peek_byte(0).is_some_and(|b| b.is_ascii_digit()) {
+            self.index += 1;
+        }
+        self.index - start
+    }
+
+    // 先读完整个标识符再判断关键字，`nullable`是标识符而不是null加上able
+    fn lex_ident(&mut self) -> Result<TOKEN<'s>> {
+        let start = self.index;
+        while self.peek_byte(0).is_some_and(is_ident_continue) {
+            self.index += 1;
+        }
+        Ok(match &self.json_str[start..self.index] {
+            b"true" => TOKEN::BOOL(true),
+            b"false" => TOKEN::BOOL(false),
+            b"null" => TOKEN::NULL,
+            b"Infinity" if self.lenient => TOKEN::NUMBER(f64::INFINITY),
+            b"NaN" if self.lenient => TOKEN::NUMBER(f64::NAN),
+            ident if self.lenient => TOKEN::IDENT(std::str::from_utf8(ident).unwrap()),
+            _ => {
+                self.index = start;
+                return Err(self.fail("unexpected token", start));
+            }
+        })
     }
 
+    /// 退回上一个token，只能退回一个；需要退回多个token时使用mark/rewind
     pub fn push_back(&mut self) {
         self.index -= self.current_token_size;
+        // 撤销 [ { ] } 对嵌套深度的改变
+        if self.current_token_size == 1 {
+            match self.json_str[self.index] {
+                b'{' | b'[' => self.depth -= 1,
+                b'}' | b']' => self.depth += 1,
+                _ => {}
+            }
+        }
+    }
+
+    pub fn mark(&self) -> Mark {
+        Mark {
+            index: self.index,
+            current_token_size: self.current_token_size,
+            tokens: self.tokens,
+            depth: self.depth,
+        }
+    }
+
+    /// 回到mark的位置，返回撤销的token数
+    pub fn rewind(&mut self, mark: Mark) -> usize {
+        let undone = self.tokens - mark.tokens;
+        self.index = mark.index;
+        self.current_token_size = mark.current_token_size;
+        self.tokens = mark.tokens;
+        self.depth = mark.depth;
+        undone
+    }
+
+    /// 读取下一个token但不前进，不算作回溯
+    pub fn peek(&mut self) -> Result<TOKEN<'s>> {
+        let mark = self.mark();
+        let (furthest, relexed) = (self.furthest, self.relexed);
+        let token = self.lex();
+        self.rewind(mark);
+        self.furthest = furthest;
+        self.relexed = relexed;
+        token
+    }
+
+    /// 回溯后重新读取的token数
+    pub fn relexed(&self) -> usize {
+        self.relexed
     }
 
     pub fn index(&self) -> usize {
         self.index
     }
-}
\ No newline at end of file
+
+    /// 跳过一个字符，用于错误恢复
+    pub fn skip_char(&mut self) {
+        self.index += 1;
+        while self.index < self.json_str.len() && self.json_str[self.index] & 0xC0 == 0x80 {
+            self.index += 1;
+        }
+    }
+
+    /// 上一个token的起始位置
+    pub fn token_start(&self) -> usize {
+        self.index - self.current_token_size
+    }
+
+    /// 出错的位置: 读取token出错时为出错的字符，否则为最近读取的token的起点，parser报告的语法错误都在这个token上
+    pub fn error_position(&self) -> usize {
+        self.error.unwrap_or_else(|| self.token_start())
+    }
+
+    /// 取出源文本中`[start, end)`的部分，边界需落在token或空白的边界上
+    pub fn slice(&self, start: usize, end: usize) -> &'s str {
+        std::str::from_utf8(&self.json_str[start..end]).unwrap()
+    }
+
+    /// 源文本中从start开始的剩余部分
+    pub fn rest(&self, start: usize) -> &'s str {
+        self.slice(start, self.json_str.len())
+    }
+
+    /// 直接移动到index，用于跳过已经在别处扫描过的部分
+    pub fn skip_to(&mut self, index: usize) {
+        self.index = index;
+        self.current_token_size = 0;
+    }
+
+    pub fn scanner(&self) -> Scanner {
+        self.scanner
+    }
+
+    /// 跳过空白和注释后是否已到达输入末尾
+    pub fn at_end(&mut self) -> Result<bool> {
+        self.skip_blank()?;
+        Ok(self.index >= self.json_str.len())
+    }
+}
+
+fn is_ident_start(b: u8) -> bool {
+    b.is_ascii_alphabetic() || b == b'_' || b == b'$'
+}
+
+fn is_ident_continue(b: u8) -> bool {
+    is_ident_start(b) || b.is_ascii_digit()
+}
+
+/// 将`TOKEN::STRING`中保留的原始转义序列还原为字符串内容，不含转义时不分配内存
+pub fn unescape(raw: &str) -> Result<Cow<'_, str>> {
+    if !raw.contains('\\') {
+        return Ok(Cow::Borrowed(raw));
+    }
+    let mut out = String::with_capacity(raw.len());
+    let mut chars = raw.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('"') => out.push('"'),
+            Some('\\') => out.push('\\'),
+            Some('/') => out.push('/'),
+            Some('b') => out.push('\u{8}'),
+            Some('f') => out.push('\u{c}'),
+            Some('n') => out.push('\n'),
+            Some('r') => out.push('\r'),
+            Some('t') => out.push('\t'),
+            Some('\'') => out.push('\''),
+            Some('0') => out.push('\0'),
+            Some('v') => out.push('\u{b}'),
+            Some('\n') => {}
+            Some('x') => {
+                let code = read_hex(&mut chars, 2)?;
+                out.push(char::from_u32(code).unwrap());
+            }
+            Some('u') => {
+                let high = read_hex(&mut chars, 4)?;
+                let code = if (0xD800..0xDC00).contains(&high) {
+                    if chars.next() != Some('\\') || chars.next() != Some('u') {
+                        return Err(format!("unpaired surrogate \\u{:04x} in string", high));
+                    }
+                    let low = read_hex(&mut chars, 4)?;
+                    if !(0xDC00..0xE000).contains(&low) {
+                        return Err(format!("invalid low surrogate \\u{:04x} in string", low));
+                    }
+                    0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00)
+                } else {
+                    high
+                };
+                out.push(char::from_u32(code).ok_or_else(|| format!("invalid code point \\u{:04x} in string", code))?);
+            }
+            Some(c) => return Err(format!("invalid escape \\{} in string", c)),
+            None => return Err("unterminated escape in string".to_string()),
+        }
+    }
+    Ok(Cow::Owned(out))
+}
+
+fn read_hex(chars: &mut std::str::Chars, len: usize) -> Result<u32> {
+    let mut code = 0;
+    for _ in 0..len {
+        let digit = chars.next()
+            .and_then(|c| c.to_digit(16))
+            .ok_or_else(|| format!("expect {} hex digits in escape", len))?;
+        code = code * 16 + digit;
+    }
+    Ok(code)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tokens(mut lexer: Lexer) -> Result<Vec<TOKEN>> {
+        let mut tokens = vec![];
+        while !lexer.at_end()? {
+            tokens.push(lexer.lex()?);
+        }
+        Ok(tokens)
+    }
+
+    #[test]
+    fn keywords() {
+        assert_eq!(tokens(Lexer::new("[true,false,null]")).unwrap(), vec![
+            TOKEN::LBRACKET, TOKEN::BOOL(true), TOKEN::COMMA, TOKEN::BOOL(false), TOKEN::COMMA, TOKEN::NULL, TOKEN::RBRACKET,
+        ]);
+    }
+
+    #[test]
+    fn identifiers_starting_with_keywords() {
+        for key in ["nullable", "trueish", "falsey", "Infinityx", "NaNa"] {
+            let src = format!("{{{}:1}}", key);
+            assert_eq!(tokens(Lexer::lenient(&src)).unwrap(), vec![
+                TOKEN::LBRACE, TOKEN::IDENT(key), TOKEN::COLON, TOKEN::NUMBER(1.0), TOKEN::RBRACE,
+            ]);
+        }
+    }
+
+    #[test]
+    fn keyword_needs_boundary() {
+        assert_eq!(tokens(Lexer::new("truex")).unwrap_err(), "unexpected token at position 0");
+        assert_eq!(tokens(Lexer::new("[nul]")).unwrap_err(), "unexpected token at position 1");
+        assert!(tokens(Lexer::lenient("-Infinityx")).is_err());
+        let n = tokens(Lexer::lenient("[Infinity,-Infinity,NaN]")).unwrap();
+        assert_eq!(n[1], TOKEN::NUMBER(f64::INFINITY));
+        assert_eq!(n[3], TOKEN::NUMBER(f64::NEG_INFINITY));
+        assert!(matches!(n[5], TOKEN::NUMBER(x) if x.is_nan()));
+    }
+
+    #[test]
+    fn lenient_only() {
+        assert!(tokens(Lexer::new("{a:1}")).is_err());
+        assert!(tokens(Lexer::new("NaN")).is_err());
+    }
+
+    #[test]
+    fn control_characters() {
+        assert_eq!(tokens(Lexer::new("[\"a\tb\"]")).unwrap_err(), "control character in string at position 3");
+        assert!(tokens(Lexer::new("\"a\nb\"")).is_err());
+        assert!(tokens(Lexer::new("\"\u{1f}\"")).is_err());
+        assert_eq!(tokens(Lexer::new(r#""a\tb""#)).unwrap(), vec![TOKEN::STRING(r"a\tb")]);
+        assert_eq!(tokens(Lexer::lenient("'a\tb'")).unwrap(), vec![TOKEN::STRING("a\tb")]);
+    }
+
+    #[test]
+    fn leading_zeros() {
+        for src in ["01", "-01", "00.5"] {
+            assert!(tokens(Lexer::new(src)).is_err());
+            assert!(tokens(Lexer::lenient(src)).is_err());
+        }
+        assert_eq!(tokens(Lexer::lenient("[0, -0.5, .5, 0x1F, 10]")).unwrap().len(), 11);
+    }
+
+    #[test]
+    fn relexed_counts_each_read_once() {
+        let mut lexer = Lexer::new("[1,2,3]");
+        let outer = lexer.mark();
+        lexer.lex().unwrap();
+        lexer.lex().unwrap();
+        let inner = lexer.mark();
+        lexer.lex().unwrap();
+        lexer.lex().unwrap();
+        assert_eq!(lexer.rewind(inner), 2);
+        lexer.lex().unwrap();
+        lexer.lex().unwrap();
+        assert_eq!(lexer.relexed(), 2);
+        // 嵌套的rewind不重复计算已经撤销过的token
+        assert_eq!(lexer.rewind(outer), 4);
+        assert_eq!(lexer.peek().unwrap(), TOKEN::LBRACKET);
+        assert_eq!(lexer.relexed(), 2);
+        while !lexer.at_end().unwrap() {
+            lexer.lex().unwrap();
+        }
+        assert_eq!(lexer.relexed(), 6);
+    }
+
+    #[test]
+    fn eof_token() {
+        let mut lexer = Lexer::new(" 1 ");
+        assert_eq!(lexer.lex_with_eof().unwrap(), TOKEN::NUMBER(1.0));
+        assert_eq!(lexer.lex().unwrap_err(), "unexpected end of file at position 4");
+        assert_eq!(lexer.lex_with_eof().unwrap(), TOKEN::EOF);
+        let mut lexer = Lexer::lenient(" 1 /**/");
+        assert_eq!(lexer.lex_with_eof().unwrap(), TOKEN::NUMBER(1.0));
+        assert_eq!(lexer.lex_with_eof().unwrap(), TOKEN::EOF);
+        assert_eq!(lexer.lex_with_eof().unwrap(), TOKEN::EOF);
+    }
+
+    #[test]
+    fn error_position() {
+        let mut lexer = Lexer::new(r#"[1, "a\q"]"#);
+        lexer.lex().unwrap();
+        lexer.lex().unwrap();
+        assert_eq!(lexer.error_position(), 1);
+        lexer.lex().unwrap();
+        assert!(lexer.lex().is_err());
+        assert_eq!(lexer.error_position(), 6);
+        let mut lexer = Lexer::lenient("1 /* x");
+        lexer.lex().unwrap();
+        assert!(lexer.at_end().is_err());
+        assert_eq!(lexer.error_position(), 2);
+    }
+}
@@ -0,0 +1,480 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt::{Display, Formatter};
+use super::grammar::{Grammar, Symbol};
+
+// LR自动机，由Grammar构造LR(0)项目集规范族，并生成LR(0)/SLR/LALR(1)分析表
+// - 增广文法: 加入产生式 S' -> S，编号为grammar.productions().len()，S'不是文法中的符号
+// - 项目集由核心项目经CLOSURE得到，GOTO(I, X)为I中圆点在X之前的项目将圆点右移后的闭包
+// - LALR(1)的向前看符号在LR(0)自动机上传播得到: 初始时S' -> ·S的向前看符号为#，
+//   每个状态内按LR(1)闭包计算各项目的向前看符号，再沿GOTO传给后继状态的核心项目，直到不再变化
+// - 分析表中LR(0)对所有终结符归约，SLR按FOLLOW集归约，LALR(1)按向前看符号归约
+
+/// 项目: 产生式编号和圆点的位置
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Item {
+    pub production: usize,
+    pub dot: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Method {
+    Lr0,
+    Slr,
+    Lalr,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Action {
+    Shift(usize),
+    Reduce(usize),
+    Accept,
+}
+
+impl Display for Action {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Action::Shift(state) => write!(f, "s{}", state),
+            Action::Reduce(production) => write!(f, "r{}", production),
+            Action::Accept => write!(f, "acc"),
+        }
+    }
+}
+
+pub struct State {
+    // 前kernel个为核心项目，其余为闭包中加入的项目
+    pub items: Vec<Item>,
+    pub kernel: usize,
+    pub goto: BTreeMap<Symbol, usize>,
+    // LALR(1)向前看符号，与items一一对应
+    pub lookaheads: Vec<BTreeSet<Symbol>>,
+}
+
+pub struct Automaton<'g> {
+    grammar: &'g Grammar,
+    start: Symbol,
+    states: Vec<State>,
+}
+
+/// 分析表中的冲突，以及产生冲突的项目
+#[derive(Debug, Clone)]
+pub struct LrConflict {
+    pub state: usize,
+    pub terminal: String,
+    // "shift/reduce"或"reduce/reduce"
+    pub kind: &'static str,
+    pub items: Vec<String>,
+}
+
+impl Display for LrConflict {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} conflict in state {} on {}: {}", self.kind, self.state, self.terminal, self.items.join("  /  "))
+    }
+}
+
+/// ACTION表和GOTO表
+pub struct LrTable {
+    nonterminals: usize,
+    width: usize,
+    action: Vec<Option<Action>>,
+    goto: Vec<Option<usize>>,
+    // 进入各个状态的符号，状态0没有
+    symbols: Vec<Option<Symbol>>,
+}
+
+impl LrTable {
+    pub fn states(&self) -> usize {
+        self.goto.len() / self.nonterminals
+    }
+
+    pub fn action(&self, state: usize, terminal: Symbol) -> Option<Action> {
+        self.action[state * self.width + terminal - self.nonterminals]
+    }
+
+    pub fn goto(&self, state: usize, nonterminal: Symbol) -> Option<usize> {
+        self.goto[state * self.nonterminals + nonterminal]
+    }
+
+    /// 进入state的符号，由LR(0)自动机的构造，进入同一个状态的边上的符号都相同
+    pub fn symbol(&self, state: usize) -> Option<Symbol> {
+        self.symbols[state]
+    }
+
+    /// 状态state下可以接受的终结符
+    pub fn expected(&self, state: usize) -> Vec<Symbol> {
+        (0..self.width)
+            .filter(|i| self.action[state * self.width + i].is_some())
+            .map(|i| i + self.nonterminals)
+            .collect()
+    }
+
+    /// 输出为markdown表格，ACTION在前，GOTO在后
+    pub fn markdown(&self, grammar: &Grammar) -> String {
+        let mut out = String::from("| state |");
+        for symbol in grammar.terminals().chain(grammar.nonterminals()) {
+            out.push_str(&format!(" {} |", grammar.name(symbol)));
+        }
+        out.push_str("\n|-------|");
+        out.push_str(&"----|".repeat(self.width + self.nonterminals));
+        for state in 0..self.states() {
+            out.push_str(&format!("\n| {:<5} |", state));
+            for terminal in grammar.terminals() {
+                match self.action(state, terminal) {
+                    Some(action) => out.push_str(&format!(" {} |", action)),
+                    None => out.push_str("    |"),
+                }
+            }
+            for nonterminal in grammar.nonterminals() {
+                match self.goto(state, nonterminal) {
+                    Some(next) => out.push_str(&format!(" {} |", next)),
+                    None => out.push_str("    |"),
+                }
+            }
+        }
+        out
+    }
+}
+
+impl<'g> Automaton<'g> {
+    pub fn new(grammar: &'g Grammar) -> Automaton<'g> {
+        let mut automaton = Automaton { grammar, start: grammar.start(), states: vec![] };
+        automaton.build();
+        automaton.propagate_lookaheads();
+        automaton
+    }
+
+    pub fn states(&self) -> &[State] {
+        &self.states
+    }
+
+    fn augmented(&self) -> usize {
+        self.grammar.productions().len()
+    }
+
+    fn head(&self, production: usize) -> Option<Symbol> {
+        self.grammar.productions().get(production).map(|p| p.head)
+    }
+
+    fn body(&self, production: usize) -> &[Symbol] {
+        match self.grammar.productions().get(production) {
+            Some(p) => &p.body,
+            None => std::slice::from_ref(&self.start),
+        }
+    }
+
+    // 圆点之后的符号
+    fn next_symbol(&self, item: Item) -> Option<Symbol> {
+        self.body(item.production).get(item.dot).copied()
+    }
+
+    fn closure(&self, kernel: Vec<Item>) -> Vec<Item> {
+        let mut items = kernel;
+        let mut i = 0;
+        while i < items.len() {
+            if let Some(symbol) = self.next_symbol(items[i]) {
+                if !self.grammar.is_terminal(symbol) {
+                    for (production, p) in self.grammar.productions().iter().enumerate() {
+                        let item = Item { production, dot: 0 };
+                        if p.head == symbol && !items.contains(&item) {
+                            items.push(item);
+                        }
+                    }
+                }
+            }
+            i += 1;
+        }
+        items
+    }
+
+    // LR(0)项目集规范族
+    fn build(&mut self) {
+        let kernel = vec![Item { production: self.augmented(), dot: 0 }];
+        let mut index: HashMap<Vec<Item>, usize> = HashMap::new();
+        index.insert(kernel.clone(), 0);
+        self.states.push(self.state(kernel));
+        let mut i = 0;
+        while i < self.states.len() {
+            // 按圆点之后的符号第一次出现的顺序求GOTO
+            let mut symbols: Vec<Symbol> = vec![];
+            for item in &self.states[i].items {
+                if let Some(symbol) = self.next_symbol(*item) {
+                    if !symbols.contains(&symbol) {
+                        symbols.push(symbol);
+                    }
+                }
+            }
+            for symbol in symbols {
+                let mut kernel: Vec<Item> = self.states[i].items.iter()
+                    .filter(|item| self.next_symbol(**item) == Some(symbol))
+                    .map(|item| Item { production: item.production, dot: item.dot + 1 })
+                    .collect();
+                kernel.sort();
+                let next = match index.get(&kernel) {
+                    Some(next) => *next,
+                    None => {
+                        let next = self.states.len();
+                        index.insert(kernel.clone(), next);
+                        self.states.push(self.state(kernel));
+                        next
+                    }
+                };
+                self.states[i].goto.insert(symbol, next);
+            }
+            i += 1;
+        }
+    }
+
+    fn state(&self, kernel: Vec<Item>) -> State {
+        let len = kernel.len();
+        let items = self.closure(kernel);
+        State { lookaheads: vec![BTreeSet::new(); items.len()], items, kernel: len, goto: BTreeMap::new() }
+    }
+
+    fn propagate_lookaheads(&mut self) {
+        self.states[0].lookaheads[0].insert(self.grammar.eof());
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for i in 0..self.states.len() {
+                self.close_lookaheads(i);
+                let state = &self.states[i];
+                let mut passed: Vec<(usize, Item, BTreeSet<Symbol>)> = vec![];
+                for (item, lookahead) in state.items.iter().zip(&state.lookaheads) {
+                    if let Some(symbol) = self.next_symbol(*item) {
+                        let next = Item { production: item.production, dot: item.dot + 1 };
+                        passed.push((state.goto[&symbol], next, lookahead.clone()));
+                    }
+                }
+                for (target, item, lookahead) in passed {
+                    let target = &mut self.states[target];
+                    let k = target.items[..target.kernel].iter().position(|i| *i == item).unwrap();
+                    let before = target.lookaheads[k].len();
+                    target.lookaheads[k].extend(lookahead);
+                    changed |= target.lookaheads[k].len() != before;
+                }
+            }
+        }
+    }
+
+    // 由核心项目的向前看符号求闭包中各项目的向前看符号:
+    // [A -> α·Bβ, a]加入[B -> ·γ, FIRST(βa)]
+    fn close_lookaheads(&mut self, i: usize) {
+        let state = &self.states[i];
+        let mut lookaheads = state.lookaheads.clone();
+        for set in &mut lookaheads[state.kernel..] {
+            set.clear();
+        }
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for (j, item) in state.items.iter().enumerate() {
+                let symbol = match self.next_symbol(*item) {
+                    Some(symbol) if !self.grammar.is_terminal(symbol) => symbol,
+                    _ => continue,
+                };
+                let (mut first, nullable) = self.grammar.first_of(&self.body(item.production)[item.dot + 1..]);
+                if nullable {
+                    first.extend(lookaheads[j].iter().copied());
+                }
+                for (k, other) in state.items.iter().enumerate() {
+                    if other.dot == 0 && self.head(other.production) == Some(symbol) {
+                        let before = lookaheads[k].len();
+                        lookaheads[k].extend(first.iter().copied());
+                        changed |= lookaheads[k].len() != before;
+                    }
+                }
+            }
+        }
+        self.states[i].lookaheads = lookaheads;
+    }
+
+    /// 如`Array -> [ · Elements ]`
+    pub fn item_string(&self, item: Item) -> String {
+        let head = match self.head(item.production) {
+            Some(head) => self.grammar.name(head).to_string(),
+            None => format!("{}'", self.grammar.name(self.start)),
+        };
+        let mut out = format!("{} ->", head);
+        for (i, symbol) in self.body(item.production).iter().enumerate() {
+            if i == item.dot {
+                out.push_str(" ·");
+            }
+            out.push(' ');
+            out.push_str(self.grammar.name(*symbol));
+        }
+        if item.dot == self.body(item.production).len() {
+            out.push_str(" ·");
+        }
+        out
+    }
+
+    fn lookahead_string(&self, lookahead: &BTreeSet<Symbol>) -> String {
+        lookahead.iter().map(|s| self.grammar.name(*s)).collect::<Vec<_>>().join(" ")
+    }
+
+    /// 构造ACTION/GOTO表，有冲突时返回所有冲突
+    pub fn table(&self, method: Method) -> std::result::Result<LrTable, Vec<LrConflict>> {
+        let nonterminals = self.grammar.nonterminals().len();
+        let width = self.grammar.terminals().len();
+        let mut table = LrTable {
+            nonterminals,
+            width,
+            action: vec![None; self.states.len() * width],
+            goto: vec![None; self.states.len() * nonterminals],
+            symbols: vec![None; self.states.len()],
+        };
+        let mut conflicts = vec![];
+        for (i, state) in self.states.iter().enumerate() {
+            // 每个终结符上的候选动作，以及产生该动作的项目
+            let mut candidates: BTreeMap<Symbol, Vec<(Action, Item)>> = BTreeMap::new();
+            for (item, lookahead) in state.items.iter().zip(&state.lookaheads) {
+                match self.next_symbol(*item) {
+                    Some(symbol) if self.grammar.is_terminal(symbol) => {
+                        candidates.entry(symbol).or_default().push((Action::Shift(state.goto[&symbol]), *item));
+                    }
+                    Some(_) => {}
+                    None if item.production == self.augmented() => {
+                        candidates.entry(self.grammar.eof()).or_default().push((Action::Accept, *item));
+                    }
+                    None => {
+                        let head = self.head(item.production).unwrap();
+                        let terminals: Vec<Symbol> = match method {
+                            Method::Lr0 => self.grammar.terminals().collect(),
+                            Method::Slr => self.grammar.follow(head).iter().copied().collect(),
+                            Method::Lalr => lookahead.iter().copied().collect(),
+                        };
+                        for terminal in terminals {
+                            candidates.entry(terminal).or_default().push((Action::Reduce(item.production), *item));
+                        }
+                    }
+                }
+            }
+            for (terminal, actions) in candidates {
+                let reduces = actions.iter().filter(|(a, _)| matches!(a, Action::Reduce(_))).count();
+                let shifts = actions.len() - reduces;
+                if reduces > 1 || (reduces == 1 && shifts > 0) {
+                    conflicts.push(LrConflict {
+                        state: i,
+                        terminal: self.grammar.name(terminal).to_string(),
+                        kind: if shifts > 0 { "shift/reduce" } else { "reduce/reduce" },
+                        items: actions.iter().map(|(_, item)| self.item_string(*item)).collect(),
+                    });
+                }
+                table.action[i * width + terminal - nonterminals] = Some(actions[0].0);
+            }
+            for (symbol, next) in &state.goto {
+                table.symbols[*next] = Some(*symbol);
+                if !self.grammar.is_terminal(*symbol) {
+                    table.goto[i * nonterminals + symbol] = Some(*next);
+                }
+            }
+        }
+        if !conflicts.is_empty() {
+            return Err(conflicts);
+        }
+        Ok(table)
+    }
+
+    /// 输出为Graphviz DOT，每个状态列出其项目，LALR时附上向前看符号，核心项目与闭包项目之间以横线分隔
+    pub fn dot(&self, method: Method) -> String {
+        let mut out = String::from("digraph LR {\n    rankdir=LR;\n    node [shape=box, fontname=\"monospace\"];\n");
+        for (i, state) in self.states.iter().enumerate() {
+            let mut label = format!("I{}\\l", i);
+            for (j, (item, lookahead)) in state.items.iter().zip(&state.lookaheads).enumerate() {
+                if j == state.kernel && j < state.items.len() {
+                    label.push_str("--------\\l");
+                }
+                label.push_str(&escape(&self.item_string(*item)));
+                if method == Method::Lalr {
+                    label.push_str(&format!(", {}", escape(&self.lookahead_string(lookahead))));
+                }
+                label.push_str("\\l");
+            }
+            out.push_str(&format!("    I{} [label=\"{}\"];\n", i, label));
+        }
+        for (i, state) in self.states.iter().enumerate() {
+            for (symbol, next) in &state.goto {
+                out.push_str(&format!("    I{} -> I{} [label=\"{}\"];\n", i, next, escape(self.grammar.name(*symbol))));
+            }
+        }
+        out.push_str("}\n");
+        out
+    }
+}
+
+fn escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXPR: &str = "
+        E -> E + T | T
+        T -> T * F | F
+        F -> ( E ) | id
+    ";
+
+    // 赋值语句文法是LALR(1)的，但SLR在 = 上有移进/归约冲突
+    const ASSIGN: &str = "
+        S -> L = R | R
+        L -> * R | id
+        R -> L
+    ";
+
+    fn conflicts(text: &str, method: Method) -> Vec<String> {
+        let grammar = Grammar::parse(text).unwrap();
+        match Automaton::new(&grammar).table(method) {
+            Ok(_) => vec![],
+            Err(conflicts) => conflicts.iter().map(|c| c.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn canonical_collection() {
+        let grammar = Grammar::parse(EXPR).unwrap();
+        let automaton = Automaton::new(&grammar);
+        assert_eq!(automaton.states().len(), 12);
+        let start = &automaton.states()[0];
+        assert_eq!(start.kernel, 1);
+        assert_eq!(automaton.item_string(start.items[0]), "E' -> · E");
+        assert_eq!(automaton.item_string(Item { production: 0, dot: 3 }), "E -> E + T ·");
+    }
+
+    #[test]
+    fn methods() {
+        assert!(!conflicts(EXPR, Method::Lr0).is_empty());
+        assert!(conflicts(EXPR, Method::Slr).is_empty());
+        assert!(conflicts(EXPR, Method::Lalr).is_empty());
+        let slr = conflicts(ASSIGN, Method::Slr);
+        assert_eq!(slr.len(), 1);
+        assert!(slr[0].starts_with("shift/reduce conflict in state"), "{}", slr[0]);
+        assert!(slr[0].contains("on =: S -> L · = R  /  R -> L ·"), "{}", slr[0]);
+        assert!(conflicts(ASSIGN, Method::Lalr).is_empty());
+    }
+
+    #[test]
+    fn reduce_reduce() {
+        let found = conflicts("S -> A | B\nA -> id\nB -> id", Method::Lalr);
+        assert_eq!(found.len(), 1);
+        assert!(found[0].starts_with("reduce/reduce conflict"), "{}", found[0]);
+        assert!(found[0].ends_with("on #: A -> id ·  /  B -> id ·"), "{}", found[0]);
+    }
+
+    #[test]
+    fn table_and_dot() {
+        let grammar = Grammar::parse(EXPR).unwrap();
+        let automaton = Automaton::new(&grammar);
+        let table = automaton.table(Method::Lalr).unwrap();
+        let id = grammar.symbol("id").unwrap();
+        let Some(Action::Shift(next)) = table.action(0, id) else { panic!() };
+        assert_eq!(table.symbol(next), Some(id));
+        // F -> id · 在 + 上归约
+        assert_eq!(table.action(next, grammar.symbol("+").unwrap()), Some(Action::Reduce(5)));
+        assert!(table.goto(0, grammar.symbol("E").unwrap()).is_some());
+        let dot = automaton.dot(Method::Lalr);
+        assert!(dot.starts_with("digraph LR {"));
+        assert!(dot.contains("I0 -> I"));
+        assert!(dot.contains("E' -> · E, #"), "{}", dot);
+    }
+}
@@ -1,32 +1,359 @@
-use super::{Lexer, Result, Value};
+use std::collections::HashMap;
+use std::sync::OnceLock;
+use super::{Lexer, Result, TOKEN, Value};
+use super::grammar::{Grammar, Symbol};
+use super::lr::{Action, Automaton, LrTable, Method};
+use super::parser::Parser;
+use super::trace::{input, Step, StepKind, Tracer};
 
-struct LRParser<'s> {
+// LR分析不需要消除左递归和提取左公因子，直接使用左递归的文法，元素列表在分析栈上只占一项
+// 分析表为LALR(1)，由lr模块构造，项目集见`Automaton::new(LRParser::grammar()).dot(Method::Lalr)`
+// 产生式编号，reduce依赖这些编号:
+// Value(0-5)
+// - Value -> Array | Object | string | number | bool | null
+// Array(6-8)，产生式8只在宽松模式下可用
+// - Array -> [ ] | [ Elements ] | [ Elements , ]
+// Elements(9-10)
+// - Elements -> Elements , Value | Value
+// Object(11-13)，产生式13只在宽松模式下可用
+// - Object -> { } | { Members } | { Members , }
+// Members(14-15)
+// - Members -> Members , Pair | Pair
+// Pair(16)
+// - Pair -> string : Value
+const GRAMMAR: &str = "
+TOKEN: [ { } ] , : string number bool null
+Value -> Array | Object | string | number | bool | null
+Array -> [ ] | [ Elements ] | [ Elements , ]
+Elements -> Elements , Value | Value
+Object -> { } | { Members } | { Members , }
+Members -> Members , Pair | Pair
+Pair -> string : Value
+";
+
+struct Tables {
+    grammar: Grammar,
+    table: LrTable,
+    // 各个TOKEN对应的终结符，下标由token_index给出
+    terminals: [Symbol; 11],
+}
+
+static TABLES: OnceLock<Tables> = OnceLock::new();
+
+fn tables() -> &'static Tables {
+    TABLES.get_or_init(|| {
+        let grammar = Grammar::parse(GRAMMAR).unwrap();
+        let table = match Automaton::new(&grammar).table(Method::Lalr) {
+            Ok(table) => table,
+            Err(conflicts) => panic!("{}", conflicts[0]),
+        };
+        let terminals = ["{", "}", "[", "]", ",", ":", "string", "number", "bool", "null", "#"]
+            .map(|name| grammar.symbol(name).unwrap());
+        Tables { grammar, table, terminals }
+    })
+}
+
+fn token_index(token: &TOKEN) -> usize {
+    match token {
+        TOKEN::LBRACE => 0,
+        TOKEN::RBRACE => 1,
+        TOKEN::LBRACKET => 2,
+        TOKEN::RBRACKET => 3,
+        TOKEN::COMMA => 4,
+        TOKEN::COLON => 5,
+        TOKEN::STRING(_) | TOKEN::IDENT(_) => 6,
+        TOKEN::NUMBER(_) => 7,
+        TOKEN::BOOL(_) => 8,
+        TOKEN::NULL => 9,
+        TOKEN::EOF => 10,
+    }
+}
+
+// 分析栈中与状态对应的语义值
+enum Node<'s> {
+    Token(TOKEN<'s>),
+    Value(Value<'s>),
+    Elements(Vec<Value<'s>>),
+    Members(Vec<(&'s str, Value<'s>)>),
+    Pair(&'s str, Value<'s>),
+}
+
+pub struct LRParser<'s> {
     lexer: Lexer<'s>,
+    tables: &'static Tables,
+    states: Vec<usize>,
+    nodes: Vec<Node<'s>>,
+    tracer: Option<Tracer>,
 }
 
-// CLOSURE(S') = {
-//      S'->·S  S->·A  S->·B  S->·c  S->·d  S->·e  S->·f
-//      A->·[SC]  A->·[]
-//      B->·{DE}  B->·{}
-// }
-
-// CLOSURE(S) = {
-//      S->·A  S->·B  S->·c  S->·d  S->·e  S->·f
-//      A->·[SC]  A->·[]
-//      B->·{DE}  B->·{}
-// }
-//
-// CLOSURE(A) = {
-//     A->·[SC]  A->·[]
-// }
-// CLOSURE(B) = {
-//     B->·{DE}  B->·{}
-// }
-impl LRParser {
+impl<'s> LRParser<'s> {
     pub fn new(lexer: Lexer) -> LRParser {
         LRParser {
-            lexer
+            lexer,
+            tables: tables(),
+            states: vec![0],
+            nodes: vec![],
+            tracer: None,
+        }
+    }
+
+    /// 记录每一次移进和归约，以及当时的状态栈和剩余输入
+    pub fn with_tracer(self, tracer: Tracer) -> LRParser<'s> {
+        LRParser { tracer: Some(tracer), ..self }
+    }
+
+    pub fn take_tracer(&mut self) -> Option<Tracer> {
+        self.tracer.take()
+    }
+
+    /// 生成分析表所用的文法
+    pub fn grammar() -> &'static Grammar {
+        &tables().grammar
+    }
+
+    pub fn parse(&mut self) -> Result<Value<'s>> {
+        let t = self.tables;
+        let mut token = self.lexer.lex_with_eof()?;
+        loop {
+            let state = *self.states.last().unwrap();
+            let symbol = t.terminals[token_index(&token)];
+            match t.table.action(state, symbol) {
+                Some(Action::Shift(next)) => {
+                    self.trailing_comma(&token)?;
+                    self.trace(|| StepKind::Shift(next));
+                    self.states.push(next);
+                    self.nodes.push(Node::Token(token));
+                    token = self.lexer.lex_with_eof()?;
+                }
+                Some(Action::Reduce(index)) => {
+                    self.trace(|| StepKind::Reduce(index, t.grammar.production_string(index)));
+                    let production = &t.grammar.productions()[index];
+                    self.states.truncate(self.states.len() - production.body.len());
+                    self.reduce(index)?;
+                    let state = *self.states.last().unwrap();
+                    self.states.push(t.table.goto(state, production.head).unwrap());
+                }
+                Some(Action::Accept) => {
+                    self.trace(|| StepKind::Accept);
+                    break;
+                }
+                None => {
+                    let valid_symbols: Vec<&str> = self.expected()
+                        .into_iter()
+                        .map(|s| self.name(s))
+                        .collect();
+                    return Err(self.error(&valid_symbols.join(" | "), symbol));
+                }
+            }
+        }
+        match self.nodes.pop() {
+            Some(Node::Value(value)) => Ok(value),
+            _ => unreachable!(),
         }
     }
-    // pub fn parse(&self) -> Result<Value> {}
-}
\ No newline at end of file
+
+    // 栈为状态和进入该状态的符号交替排列，如`0 [ 8 number 5`
+    fn trace(&mut self, kind: impl FnOnce() -> StepKind) {
+        if self.tracer.is_none() {
+            return;
+        }
+        let t = self.tables;
+        let mut stack = vec![];
+        for state in &self.states {
+            if let Some(symbol) = t.table.symbol(*state) {
+                stack.push(t.grammar.name(symbol).to_string());
+            }
+            stack.push(state.to_string());
+        }
+        let step = Step {
+            stack: stack.join(" "),
+            input: input(self.lexer.rest(self.lexer.token_start())),
+            kind: kind(),
+        };
+        self.tracer.as_mut().unwrap().push(step);
+    }
+
+    fn name(&self, symbol: Symbol) -> &'static str {
+        if symbol == self.tables.grammar.eof() {
+            "end of input"
+        } else {
+            self.tables.grammar.name(symbol)
+        }
+    }
+
+    // 出错的状态可能已经按向前看符号做过归约，其ACTION行中的终结符不一定能被接受
+    // 对每个终结符在栈的副本上模拟归约，最终能移进或接受的才是期望的终结符
+    // 非宽松模式下`,`之后的`]`/`}`会被trailing_comma拒绝，不算在内
+    fn expected(&self) -> Vec<Symbol> {
+        let t = self.tables;
+        let after_comma = !self.lexer.is_lenient() && matches!(self.nodes.last(), Some(Node::Token(TOKEN::COMMA)));
+        t.grammar.terminals().filter(|terminal| {
+            if after_comma && [t.terminals[1], t.terminals[3]].contains(terminal) {
+                return false;
+            }
+            let mut states = self.states.clone();
+            loop {
+                match t.table.action(*states.last().unwrap(), *terminal) {
+                    Some(Action::Shift(_)) | Some(Action::Accept) => return true,
+                    Some(Action::Reduce(index)) => {
+                        let production = &t.grammar.productions()[index];
+                        states.truncate(states.len() - production.body.len());
+                        states.push(t.table.goto(*states.last().unwrap(), production.head).unwrap());
+                    }
+                    None => return false,
+                }
+            }
+        }).collect()
+    }
+
+    fn error(&self, expected: &str, symbol: Symbol) -> String {
+        if symbol == self.tables.grammar.eof() {
+            format!("expect {} before end of input", expected)
+        } else {
+            format!("expect {} at position: {}, found {}", expected, self.lexer.index(), self.name(symbol))
+        }
+    }
+
+    // 文法中包含末尾多余`,`的产生式8/13，非宽松模式下在移进`]`/`}`时拒绝
+    fn trailing_comma(&self, token: &TOKEN) -> Result<()> {
+        if self.lexer.is_lenient() || !matches!(self.nodes.last(), Some(Node::Token(TOKEN::COMMA))) {
+            return Ok(());
+        }
+        let (expected, found) = match token {
+            TOKEN::RBRACKET => ("[ | { | string | number | bool | null", "]"),
+            TOKEN::RBRACE => ("string", "}"),
+            _ => return Ok(()),
+        };
+        Err(format!("expect {} at position: {}, found {}", expected, self.lexer.index(), found))
+    }
+
+    fn pop(&mut self) -> Node<'s> {
+        self.nodes.pop().unwrap()
+    }
+
+    fn pop_value(&mut self) -> Value<'s> {
+        match self.pop() {
+            Node::Value(value) => value,
+            _ => panic!("unexpected node"),
+        }
+    }
+
+    // 弹出产生式右部对应的语义值，压入左部的语义值
+    fn reduce(&mut self, index: usize) -> Result<()> {
+        let node = match index {
+            // Value -> Array | Object，语义值不变
+            0 | 1 => return Ok(()),
+            2..=5 => match self.pop() {
+                Node::Token(TOKEN::STRING(s)) => Node::Value(Value::String(s)),
+                Node::Token(TOKEN::IDENT(s)) => {
+                    return Err(format!("unexpected identifier {} at position: {}", s, self.lexer.index()));
+                }
+                Node::Token(TOKEN::NUMBER(n)) => Node::Value(Value::Number(n)),
+                Node::Token(TOKEN::BOOL(b)) => Node::Value(Value::Bool(b)),
+                Node::Token(TOKEN::NULL) => Node::Value(Value::Null),
+                _ => panic!("unexpected node"),
+            },
+            6 | 11 => {
+                self.nodes.truncate(self.nodes.len() - 2);
+                Node::Value(if index == 6 { Value::Array(vec![]) } else { Value::Object(HashMap::new()) })
+            }
+            7 | 8 | 12 | 13 => {
+                // 弹出]或}，以及可能的,
+                self.pop();
+                if index == 8 || index == 13 {
+                    self.pop();
+                }
+                let node = match self.pop() {
+                    Node::Elements(list) => Node::Value(Value::Array(list)),
+                    Node::Members(members) => Node::Value(Value::from_members(members)),
+                    _ => panic!("unexpected node"),
+                };
+                self.pop();
+                node
+            }
+            9 => {
+                let value = self.pop_value();
+                self.pop();
+                match self.nodes.last_mut() {
+                    Some(Node::Elements(list)) => list.push(value),
+                    _ => panic!("unexpected node"),
+                }
+                return Ok(());
+            }
+            10 => Node::Elements(vec![self.pop_value()]),
+            14 => {
+                let pair = match self.pop() {
+                    Node::Pair(key, value) => (key, value),
+                    _ => panic!("unexpected node"),
+                };
+                self.pop();
+                match self.nodes.last_mut() {
+                    Some(Node::Members(members)) => members.push(pair),
+                    _ => panic!("unexpected node"),
+                }
+                return Ok(());
+            }
+            15 => match self.pop() {
+                Node::Pair(key, value) => Node::Members(vec![(key, value)]),
+                _ => panic!("unexpected node"),
+            },
+            16 => {
+                let value = self.pop_value();
+                self.pop();
+                match self.pop() {
+                    Node::Token(TOKEN::STRING(key) | TOKEN::IDENT(key)) => Node::Pair(key, value),
+                    _ => panic!("unexpected node"),
+                }
+            }
+            _ => panic!("unexpected production"),
+        };
+        self.nodes.push(node);
+        Ok(())
+    }
+}
+
+// 分析到#才接受，值之后的多余内容已经报错
+impl<'s> Parser<'s> for LRParser<'s> {
+    fn parse(&mut self) -> Result<Value<'s>> {
+        LRParser::parse(self)
+    }
+
+    fn error_position(&self) -> usize {
+        self.lexer.error_position()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(lexer: Lexer) -> Result<Value> {
+        LRParser::new(lexer).parse()
+    }
+
+    #[test]
+    fn values() {
+        let value = parse(Lexer::new(r#"{"a": [1, [], {}], "b": "x", "a": null}"#)).unwrap();
+        assert_eq!(value, Value::Object(HashMap::from([
+            ("a", Value::Null),
+            ("b", Value::String("x")),
+        ])));
+        let value = parse(Lexer::new("[1, [2, [3]], true]")).unwrap();
+        assert_eq!(value.to_canonical_string().unwrap(), "[1,[2,[3]],true]");
+    }
+
+    #[test]
+    fn trailing_comma_only_when_lenient() {
+        assert_eq!(parse(Lexer::lenient("[1, 2,]")).unwrap().to_canonical_string().unwrap(), "[1,2]");
+        assert_eq!(parse(Lexer::lenient("{a: 1,}")).unwrap().to_canonical_string().unwrap(), r#"{"a":1}"#);
+        assert!(parse(Lexer::new("[1, 2,]")).is_err());
+        assert!(parse(Lexer::new(r#"{"a": 1,}"#)).is_err());
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(parse(Lexer::new("[1 2]")).unwrap_err(), "expect ] | , at position: 4, found number");
+        assert_eq!(parse(Lexer::new("[1,")).unwrap_err(), "expect [ | { | string | number | bool | null before end of input");
+        assert_eq!(parse(Lexer::new("1 2")).unwrap_err(), "expect end of input at position: 3, found number");
+    }
+}
@@ -1,20 +1,52 @@
+use std::borrow::Cow;
+use std::cmp::Ordering;
 use std::collections::HashMap;
-use std::fmt::{Display, format, Formatter};
+use std::fmt::{Display, Formatter};
+use std::hash::{Hash, Hasher};
 
 pub mod indefinite_parser;
 pub mod definite_parser;
 pub mod table_driven_parser;
+pub mod grammar;
+pub mod lr;
+pub mod lr_parser;
+pub mod trace;
+pub mod parse_tree;
+pub mod parser;
+pub mod writer;
 pub mod lexer;
-mod lr_parser;
+pub mod token_stream;
+pub mod scan;
+pub mod cst;
+pub mod span;
+pub mod schema;
+pub mod convert;
+pub mod tape;
+pub mod lazy;
+#[cfg(feature = "serde")]
+pub mod serde;
 
-pub use lexer::{Lexer, TOKEN};
-pub use indefinite_parser::IndefiniteParser;
+pub use lexer::{Lexer, Mark, TOKEN, unescape};
+pub use token_stream::{Token, TokenStream};
+pub use indefinite_parser::{Backtrack, IndefiniteParser};
 pub use definite_parser::DefiniteParser;
 pub use table_driven_parser::TableDrivenParser;
+pub use grammar::Grammar;
+pub use lr::Automaton;
+pub use lr_parser::LRParser;
+pub use trace::Tracer;
+pub use parse_tree::ParseTree;
+pub use parser::{from_str, from_str_with, Options, ParseError, Parser, ParserKind};
+pub use writer::{Writable, Writer};
+pub use cst::{CstParser, Document};
+pub use span::{Span, SpanTable};
+pub use convert::{ConvertError, FromJson, ToJson};
+pub use tape::{Tape, ValueRef};
+pub use lazy::Lazy;
 
 type Result<T> = std::result::Result<T, String>;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum Value<'a> {
     String(&'a str),
     Number(f64),
@@ -32,8 +64,8 @@ impl Value<'_> {
             Value::Bool(_) => self.fmt_bool(f),
             Value::Null => self.fmt_null(f),
 
-            Value::Array(arr) => self.fmt_array(f, width),
-            Value::Object(obj) => self.fmt_object(f, width),
+            Value::Array(_) => self.fmt_array(f, width),
+            Value::Object(_) => self.fmt_object(f, width),
         }
     }
 
@@ -67,16 +99,17 @@ impl Value<'_> {
 
     fn fmt_array(&self, f: &mut Formatter<'_>, width: usize) -> std::fmt::Result {
         match self {
+            Value::Array(arr) if arr.is_empty() => write!(f, "[]"),
             Value::Array(arr) => {
-                write!(f, "[\n")?;
-                for i in 0..arr.len() {
+                writeln!(f, "[")?;
+                for (i, value) in arr.iter().enumerate() {
                     Self::tab(f, width + 1)?;
-                    arr[i].fmt_value(f, width + 1)?;
-                    if (i < arr.len() - 1) {
-                        write!(f, ",\n")?;
+                    value.fmt_value(f, width + 1)?;
+                    if i < arr.len() - 1 {
+                        writeln!(f, ",")?;
                     }
                 }
-                write!(f, "\n")?;
+                writeln!(f)?;
                 Self::tab(f, width)?;
                 write!(f, "]")
             }
@@ -86,18 +119,19 @@ impl Value<'_> {
 
     fn fmt_object(&self, f: &mut Formatter<'_>, width: usize) -> std::fmt::Result {
         match self {
+            Value::Object(obj) if obj.is_empty() => write!(f, "{{}}"),
             Value::Object(obj) => {
-                write!(f, "{{\n")?;
+                writeln!(f, "{{")?;
                 let mut iter = obj.iter().peekable();
                 while let Some((key, value)) = iter.next() {
                     Self::tab(f, width + 1)?;
                     write!(f, "\"{}\": ", key)?;
                     value.fmt_value(f, width + 1)?;
                     if iter.peek().is_some() {
-                        write!(f, ",\n")?;
+                        writeln!(f, ",")?;
                     }
                 }
-                write!(f, "\n")?;
+                writeln!(f)?;
                 Self::tab(f, width)?;
                 write!(f, "}}")
             }
@@ -112,22 +146,61 @@ impl Value<'_> {
         Ok(())
     }
 
-    fn index(&self, index: usize) -> Option<&Value> {
+    pub fn type_name(&self) -> &'static str {
         match self {
-            Value::Array(arr) => {
-                arr.get(index)
-            }
-            _ => panic!("{}", format!("expect array, found {:?}", self))
+            Value::String(_) => "string",
+            Value::Number(_) => "number",
+            Value::Bool(_) => "bool",
+            Value::Null => "null",
+            Value::Object(_) => "object",
+            Value::Array(_) => "array",
         }
     }
-    fn get(&self, index: &str) -> Option<&Value> {
-        match self {
-            Value::Object(arr) => {
-                arr.get(index)
-            }
-            _ => panic!("{}", format!("expect object, found {:?}", self))
+}
+
+impl<'a> Value<'a> {
+    /// 由按源文本顺序排列的成员构造对象，键按还原转义后的内容去重，重复时取最后一个
+    /// 各个parser都由此构造对象，与Lazy、ValueRef的get取同一个成员
+    pub fn from_members(members: Vec<(&'a str, Value<'a>)>) -> Value<'a> {
+        if !members.iter().any(|(k, _)| k.contains('\\')) {
+            return Value::Object(HashMap::from_iter(members));
+        }
+        let mut unique: HashMap<Cow<'a, str>, (&'a str, Value<'a>)> = HashMap::new();
+        for (k, v) in members {
+            unique.insert(content(k), (k, v));
+        }
+        Value::Object(unique.into_values().collect())
+    }
+
+    /// 按键取对象的成员，键按还原转义后的内容比较，不是对象时返回None
+    pub fn get(&self, key: &str) -> Option<&Value<'a>> {
+        let obj = match self {
+            Value::Object(obj) => obj,
+            _ => return None,
+        };
+        // 源文本中不含转义的键可以直接查找
+        match obj.get_key_value(key) {
+            Some((k, v)) if !k.contains('\\') => Some(v),
+            _ => obj.iter().find(|(k, _)| content(k) == key).map(|(_, v)| v),
         }
     }
+
+    /// 按JSON Pointer(RFC 6901)取值，对象的键按还原转义后的内容比较
+    pub fn pointer(&self, pointer: &str) -> Option<&Value<'a>> {
+        if pointer.is_empty() {
+            return Some(self);
+        }
+        let mut value = self;
+        for token in pointer.strip_prefix('/')?.split('/') {
+            let token = token.replace("~1", "/").replace("~0", "~");
+            value = match value {
+                Value::Object(_) => value.get(&token)?,
+                Value::Array(arr) => arr.get(token.parse::<usize>().ok()?)?,
+                _ => return None,
+            };
+        }
+        Some(value)
+    }
 }
 
 const TAB: &str = "    ";
@@ -138,6 +211,117 @@ impl Display for Value<'_> {
     }
 }
 
+// 规范化输出(RFC 8785 JCS)
+// - 对象的键按UTF-16码元排序
+// - 数字使用ES6 Number.prototype.toString的最短表示，不允许NaN和Infinity
+// - 字符串仅转义 " \ 和控制字符
+// - 不输出任何空白
+impl Value<'_> {
+    pub fn to_canonical_string(&self) -> Result<String> {
+        let mut out = String::new();
+        self.write_canonical(&mut out)?;
+        Ok(out)
+    }
+
+    fn write_canonical(&self, out: &mut String) -> Result<()> {
+        match self {
+            Value::String(s) => write_canonical_string(&unescape(s)?, out),
+            Value::Number(n) => out.push_str(&canonical_number(*n)?),
+            Value::Bool(b) => out.push_str(if *b { "true" } else { "false" }),
+            Value::Null => out.push_str("null"),
+            Value::Array(arr) => {
+                out.push('[');
+                for (i, value) in arr.iter().enumerate() {
+                    if i > 0 {
+                        out.push(',');
+                    }
+                    value.write_canonical(out)?;
+                }
+                out.push(']');
+            }
+            Value::Object(obj) => {
+                let mut pairs = Vec::with_capacity(obj.len());
+                for (key, value) in obj {
+                    pairs.push((unescape(key)?, value));
+                }
+                pairs.sort_by(|a, b| a.0.encode_utf16().cmp(b.0.encode_utf16()));
+                out.push('{');
+                for (i, (key, value)) in pairs.iter().enumerate() {
+                    if i > 0 {
+                        out.push(',');
+                    }
+                    write_canonical_string(key, out);
+                    out.push(':');
+                    value.write_canonical(out)?;
+                }
+                out.push('}');
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 转义并加上引号，输出为JSON字符串(同规范化输出)
+pub fn write_canonical_string(s: &str, out: &mut String) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\u{8}' => out.push_str("\\b"),
+            '\u{c}' => out.push_str("\\f"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+// ES6 Number.prototype.toString, 7.1.12.1
+fn canonical_number(n: f64) -> Result<String> {
+    if !n.is_finite() {
+        return Err(format!("{} is not allowed in canonical JSON", n));
+    }
+    if n == 0.0 {
+        return Ok("0".to_string());
+    }
+    // {:e} 给出最短的可往返十进制表示，形如 -1.2345e-7
+    let sci = format!("{:e}", n.abs());
+    let (mantissa, exp) = sci.split_once('e').unwrap();
+    let digits: String = mantissa.chars().filter(|c| *c != '.').collect();
+    let k = digits.len() as i32;
+    let n_exp = exp.parse::<i32>().unwrap() + 1;
+    let mut out = String::new();
+    if n < 0.0 {
+        out.push('-');
+    }
+    if k <= n_exp && n_exp <= 21 {
+        out.push_str(&digits);
+        out.push_str(&"0".repeat((n_exp - k) as usize));
+    } else if 0 < n_exp && n_exp <= 21 {
+        out.push_str(&digits[..n_exp as usize]);
+        out.push('.');
+        out.push_str(&digits[n_exp as usize..]);
+    } else if -6 < n_exp && n_exp <= 0 {
+        out.push_str("0.");
+        out.push_str(&"0".repeat(-n_exp as usize));
+        out.push_str(&digits);
+    } else {
+        out.push_str(&digits[..1]);
+        if k > 1 {
+            out.push('.');
+            out.push_str(&digits[1..]);
+        }
+        out.push('e');
+        out.push(if n_exp > 0 { '+' } else { '-' });
+        out.push_str(&(n_exp - 1).abs().to_string());
+    }
+    Ok(out)
+}
+
 impl<'a> std::ops::Index<usize> for Value<'a> {
     type Output = Value<'a>;
 
@@ -207,4 +391,221 @@ impl<'a> AsRef<Vec<Value<'a>>> for Value<'a> {
             _ => panic!("{}", format!("expect array, found {:?}", self)),
         }
     }
-}
\ No newline at end of file
+}
+
+// Value的比较与哈希
+// - String和Object的键按还原转义后的内容比较，"a"与"\u0061"相等
+// - Object比较时忽略键的顺序
+// - Number中-0.0与0.0相等，所有NaN彼此相等且大于其他任何数字
+// - 不同类型之间按 Null < Bool < Number < String < Array < Object 排序
+impl Value<'_> {
+    fn rank(&self) -> u8 {
+        match self {
+            Value::Null => 0,
+            Value::Bool(_) => 1,
+            Value::Number(_) => 2,
+            Value::String(_) => 3,
+            Value::Array(_) => 4,
+            Value::Object(_) => 5,
+        }
+    }
+
+    // 按还原转义后的键排序，还原后相同的键再按值排序，保证顺序确定
+    fn sorted_pairs<'v>(obj: &'v HashMap<&str, Value>) -> Vec<(Cow<'v, str>, &'v Value<'v>)> {
+        let mut pairs: Vec<(Cow<str>, &Value)> = obj.iter().map(|(k, v)| (content(k), v)).collect();
+        pairs.sort();
+        pairs
+    }
+}
+
+// 字符串的内容，转义不合法时按原文处理
+fn content(raw: &str) -> Cow<'_, str> {
+    unescape(raw).unwrap_or(Cow::Borrowed(raw))
+}
+
+fn cmp_number(a: f64, b: f64) -> Ordering {
+    match (a.is_nan(), b.is_nan()) {
+        (true, true) => Ordering::Equal,
+        (true, false) => Ordering::Greater,
+        (false, true) => Ordering::Less,
+        // -0.0 == 0.0 under partial_cmp
+        (false, false) => a.partial_cmp(&b).unwrap(),
+    }
+}
+
+fn canonical_bits(n: f64) -> u64 {
+    if n.is_nan() {
+        f64::NAN.to_bits()
+    } else if n == 0.0 {
+        0.0f64.to_bits()
+    } else {
+        n.to_bits()
+    }
+}
+
+impl PartialEq for Value<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Value::Null, Value::Null) => true,
+            (Value::Bool(a), Value::Bool(b)) => a == b,
+            (Value::Number(a), Value::Number(b)) => cmp_number(*a, *b) == Ordering::Equal,
+            (Value::String(a), Value::String(b)) => a == b || content(a) == content(b),
+            (Value::Array(a), Value::Array(b)) => a == b,
+            (Value::Object(a), Value::Object(b)) => a.len() == b.len() && Self::sorted_pairs(a) == Self::sorted_pairs(b),
+            _ => false,
+        }
+    }
+}
+
+impl Eq for Value<'_> {}
+
+impl PartialOrd for Value<'_> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Value<'_> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (Value::Null, Value::Null) => Ordering::Equal,
+            (Value::Bool(a), Value::Bool(b)) => a.cmp(b),
+            (Value::Number(a), Value::Number(b)) => cmp_number(*a, *b),
+            (Value::String(a), Value::String(b)) => content(a).cmp(&content(b)),
+            (Value::Array(a), Value::Array(b)) => a.cmp(b),
+            (Value::Object(a), Value::Object(b)) => Self::sorted_pairs(a).cmp(&Self::sorted_pairs(b)),
+            _ => self.rank().cmp(&other.rank()),
+        }
+    }
+}
+
+impl Hash for Value<'_> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.rank().hash(state);
+        match self {
+            Value::Null => {}
+            Value::Bool(b) => b.hash(state),
+            Value::Number(n) => canonical_bits(*n).hash(state),
+            Value::String(s) => content(s).hash(state),
+            Value::Array(arr) => arr.hash(state),
+            Value::Object(obj) => Self::sorted_pairs(obj).hash(state),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::hash_map::DefaultHasher;
+    use std::collections::HashSet;
+    use super::*;
+
+    fn hash(value: &Value) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        value.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    #[test]
+    fn escaped_strings_are_equal() {
+        let a = from_str(r#""a/b""#).unwrap();
+        let b = from_str(r#""a\/b""#).unwrap();
+        assert_eq!(a, b);
+        assert_eq!(hash(&a), hash(&b));
+        assert_eq!(a.cmp(&b), Ordering::Equal);
+        assert_ne!(a, from_str(r#""a\\/b""#).unwrap());
+    }
+
+    #[test]
+    fn escaped_keys_are_equal() {
+        let a = from_str(r#"{"\u0061b": 1, "c": [true, null]}"#).unwrap();
+        let b = from_str(r#"{"c": [true, null], "a\u0062": 1}"#).unwrap();
+        assert_eq!(a, b);
+        assert_eq!(hash(&a), hash(&b));
+        let set: HashSet<Value> = [a, b].into_iter().collect();
+        assert_eq!(set.len(), 1);
+    }
+
+    #[test]
+    fn strings_order_by_content() {
+        // 原文"\u007a"按字节小于"b"，内容"z"大于"b"
+        let b = from_str(r#""b""#).unwrap();
+        let z = from_str(r#""\u007a""#).unwrap();
+        assert!(b < z);
+        let x = from_str(r#"{"\u007a": 1}"#).unwrap();
+        let y = from_str(r#"{"b": 1}"#).unwrap();
+        assert!(y < x);
+    }
+
+    #[test]
+    fn numbers() {
+        assert_eq!(Value::Number(0.0), Value::Number(-0.0));
+        assert_eq!(hash(&Value::Number(0.0)), hash(&Value::Number(-0.0)));
+        assert_eq!(Value::Number(f64::NAN), Value::Number(f64::NAN));
+        assert!(Value::Number(f64::INFINITY) < Value::Number(f64::NAN));
+        assert!(Value::Null < Value::Bool(false));
+        assert!(Value::Number(1e300) < Value::String("a"));
+    }
+
+    #[test]
+    fn get_compares_unescaped_keys() {
+        let value = from_str(r#"{"\u0061": 1, "b": 2}"#).unwrap();
+        assert_eq!(value.get("a"), Some(&Value::Number(1.0)));
+        // 不按转义的原文匹配
+        assert_eq!(value.get("\\u0061"), None);
+        assert_eq!(value.get("b"), Some(&Value::Number(2.0)));
+        assert_eq!(value.pointer("/a"), Some(&Value::Number(1.0)));
+        assert_eq!(Value::Null.get("a"), None);
+    }
+
+    #[test]
+    fn duplicate_keys_take_last() {
+        for kind in ParserKind::ALL {
+            let parse = |src| from_str_with(kind, &Options::default(), src).unwrap();
+            for (src, expect) in [
+                (r#"{"b": 1, "\u0062": 2}"#, 2.0),
+                (r#"{"\u0062": 1, "b": 2, "c": 3}"#, 2.0),
+                (r#"{"b": 1, "\u0062": 2, "b": 3}"#, 3.0),
+            ] {
+                let value = parse(src);
+                assert_eq!(value.get("b"), Some(&Value::Number(expect)), "{} {}", kind, src);
+                assert_eq!(value.get("b"), Tape::parse(src).unwrap().root().get("b").map(|v| v.to_value()).as_ref());
+                assert_eq!(value.get("b"), Lazy::new(src).get("b").unwrap().map(|v| v.parse().unwrap()).as_ref());
+            }
+            assert_eq!(parse(r#"{"b": 1, "\u0062": 2}"#), parse(r#"{"b": 2}"#));
+        }
+    }
+
+    #[test]
+    fn canonical_sorts_keys_by_utf16() {
+        // U+E000按UTF-16排在U+1F600(代理对D83D DE00)之后，按码点则在之前
+        let value = from_str(r#"{"b": 1, "a": [true, null], "\uE000": 2, "\uD83D\uDE00": 3}"#).unwrap();
+        assert_eq!(value.to_canonical_string().unwrap(), "{\"a\":[true,null],\"b\":1,\"\u{1F600}\":3,\"\u{E000}\":2}");
+    }
+
+    #[test]
+    fn canonical_numbers() {
+        let cases = [
+            (0.0, "0"), (-0.0, "0"), (1.0, "1"), (-1.5, "-1.5"), (1e21, "1e+21"), (1e20, "100000000000000000000"),
+            (1e-7, "1e-7"), (0.000001, "0.000001"), (123.456, "123.456"), (5e-324, "5e-324"),
+            (1.7976931348623157e308, "1.7976931348623157e+308"),
+        ];
+        for (n, text) in cases {
+            assert_eq!(Value::Number(n).to_canonical_string().unwrap(), text);
+        }
+        assert!(Value::Number(f64::NAN).to_canonical_string().is_err());
+        assert!(Value::Number(f64::INFINITY).to_canonical_string().is_err());
+    }
+
+    #[test]
+    fn canonical_strings() {
+        let value = from_str(r#""\u0041\/\u0001\n\"""#).unwrap();
+        assert_eq!(value.to_canonical_string().unwrap(), r#""A/\u0001\n\"""#);
+    }
+
+    #[test]
+    fn canonical_is_independent_of_layout() {
+        let a = from_str("{ \"x\" : [1.0, 2e0], \"y\": {} }").unwrap();
+        let b = from_str(r#"{"y":{},"x":[1,2]}"#).unwrap();
+        assert_eq!(a.to_canonical_string().unwrap(), b.to_canonical_string().unwrap());
+    }
+}
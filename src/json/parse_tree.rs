@@ -0,0 +1,208 @@
+use super::TOKEN;
+
+// 具体语法树(分析树)，内部节点为文法的非终结符，叶子为token，ε产生式的节点没有子树
+// 由递归下降的parser构造，便于对比两种文法:
+// - DefiniteParser::parse_tree: LL1文法，Value / Array' / ValueList / Object' / PairList / Pair
+// - IndefiniteParser::parse_tree: 原始文法，Value / Array / ValueList / Object / PairList / Pair
+//
+// 与cst模块不同，这里不保留空白和注释，只反映推导的结构
+
+#[derive(Debug, Clone)]
+pub enum ParseTree<'s> {
+    Node(&'static str, Vec<ParseTree<'s>>),
+    Leaf(TOKEN<'s>),
+}
+
+impl ParseTree<'_> {
+    /// 非终结符的名称，或token在源文本中的写法
+    pub fn label(&self) -> String {
+        match self {
+            ParseTree::Node(rule, _) => rule.to_string(),
+            ParseTree::Leaf(token) => match token {
+                TOKEN::LBRACE => "{".to_string(),
+                TOKEN::RBRACE => "}".to_string(),
+                TOKEN::LBRACKET => "[".to_string(),
+                TOKEN::RBRACKET => "]".to_string(),
+                TOKEN::COMMA => ",".to_string(),
+                TOKEN::COLON => ":".to_string(),
+                TOKEN::STRING(s) => format!("\"{}\"", s),
+                TOKEN::IDENT(s) => s.to_string(),
+                TOKEN::NUMBER(n) => n.to_string(),
+                TOKEN::BOOL(b) => b.to_string(),
+                TOKEN::NULL => "null".to_string(),
+                TOKEN::EOF => "#".to_string(),
+            },
+        }
+    }
+
+    pub fn children(&self) -> &[ParseTree<'_>] {
+        match self {
+            ParseTree::Node(_, children) => children,
+            ParseTree::Leaf(_) => &[],
+        }
+    }
+
+    /// 每行一个节点，子节点缩进两个空格
+    pub fn text(&self) -> String {
+        let mut out = String::new();
+        self.write_text(&mut out, 0);
+        out
+    }
+
+    fn write_text(&self, out: &mut String, depth: usize) {
+        out.push_str(&"  ".repeat(depth));
+        out.push_str(&self.label());
+        out.push('\n');
+        match self {
+            ParseTree::Node(_, children) if children.is_empty() => {
+                out.push_str(&"  ".repeat(depth + 1));
+                out.push_str("ε\n");
+            }
+            _ => {
+                for child in self.children() {
+                    child.write_text(out, depth + 1);
+                }
+            }
+        }
+    }
+
+    /// 输出为Graphviz DOT，非终结符为椭圆，token为方框，子节点按从左到右的顺序排列
+    pub fn dot(&self) -> String {
+        let mut out = String::from("digraph ParseTree {\n    ordering=out;\n    node [fontname=\"monospace\"];\n");
+        let mut next = 0;
+        self.write_dot(&mut out, &mut next);
+        out.push_str("}\n");
+        out
+    }
+
+    // 返回本节点的编号
+    fn write_dot(&self, out: &mut String, next: &mut usize) -> usize {
+        let id = *next;
+        *next += 1;
+        let label = self.label().replace('\\', "\\\\").replace('"', "\\\"");
+        match self {
+            ParseTree::Node(_, children) => {
+                out.push_str(&format!("    n{} [label=\"{}\"];\n", id, label));
+                if children.is_empty() {
+                    out.push_str(&format!("    n{} [label=\"ε\", shape=plaintext];\n    n{} -> n{};\n", *next, id, *next));
+                    *next += 1;
+                }
+                for child in children {
+                    let child = child.write_dot(out, next);
+                    out.push_str(&format!("    n{} -> n{};\n", id, child));
+                }
+            }
+            ParseTree::Leaf(_) => out.push_str(&format!("    n{} [label=\"{}\", shape=box];\n", id, label)),
+        }
+        id
+    }
+}
+
+/// 在递归下降的过程中构造ParseTree: 进入非终结符时open，返回时close，消耗token时leaf
+/// 回溯时由len/truncate撤销当前节点下已经加入的子树
+pub struct TreeBuilder<'s> {
+    // 尚未完成的节点，栈底为存放根节点的哨兵
+    stack: Vec<(&'static str, Vec<ParseTree<'s>>)>,
+}
+
+impl Default for TreeBuilder<'_> {
+    fn default() -> Self {
+        TreeBuilder::new()
+    }
+}
+
+impl<'s> TreeBuilder<'s> {
+    pub fn new() -> TreeBuilder<'s> {
+        TreeBuilder { stack: vec![("", vec![])] }
+    }
+
+    pub fn open(&mut self, rule: &'static str) {
+        self.stack.push((rule, vec![]));
+    }
+
+    /// ok为false时丢弃这个节点，用于失败的尝试
+    pub fn close(&mut self, ok: bool) {
+        let (rule, children) = self.stack.pop().unwrap();
+        if ok {
+            self.stack.last_mut().unwrap().1.push(ParseTree::Node(rule, children));
+        }
+    }
+
+    pub fn leaf(&mut self, token: TOKEN<'s>) {
+        self.stack.last_mut().unwrap().1.push(ParseTree::Leaf(token));
+    }
+
+    /// 当前节点已有的子树数
+    pub fn len(&self) -> usize {
+        self.stack.last().unwrap().1.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn truncate(&mut self, len: usize) {
+        self.stack.last_mut().unwrap().1.truncate(len);
+    }
+
+    /// 分析完成后取出根节点
+    pub fn finish(mut self) -> Option<ParseTree<'s>> {
+        self.stack.pop().and_then(|(_, mut children)| children.pop())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::{DefiniteParser, IndefiniteParser, Lexer};
+
+    fn definite(src: &str) -> ParseTree<'_> {
+        DefiniteParser::new(Lexer::new(src)).parse_tree().unwrap().1
+    }
+
+    fn indefinite(src: &str) -> ParseTree<'_> {
+        IndefiniteParser::new(Lexer::new(src)).parse_tree().unwrap().1
+    }
+
+    #[test]
+    fn ll1_grammar() {
+        assert_eq!(definite("[1]").text(), "Value\n  [\n  Array'\n    Value\n      1\n    ValueList\n      ε\n    ]\n");
+    }
+
+    #[test]
+    fn original_grammar() {
+        assert_eq!(indefinite("[1]").text(), "Value\n  Array\n    [\n    Value\n      1\n    ValueList\n      ε\n    ]\n");
+        // [] 中先尝试的Value失败，回溯后不留在树中
+        assert_eq!(indefinite(r#"{"a":[]}"#).text(), concat!(
+            "Value\n",
+            "  Object\n",
+            "    {\n",
+            "    Pair\n",
+            "      \"a\"\n",
+            "      :\n",
+            "      Value\n",
+            "        Array\n",
+            "          [\n",
+            "          ]\n",
+            "    PairList\n",
+            "      ε\n",
+            "    }\n",
+        ));
+    }
+
+    #[test]
+    fn dot() {
+        assert_eq!(definite("[]").dot(), concat!(
+            "digraph ParseTree {\n    ordering=out;\n    node [fontname=\"monospace\"];\n",
+            "    n0 [label=\"Value\"];\n",
+            "    n1 [label=\"[\", shape=box];\n",
+            "    n0 -> n1;\n",
+            "    n2 [label=\"Array'\"];\n",
+            "    n3 [label=\"]\", shape=box];\n",
+            "    n2 -> n3;\n",
+            "    n0 -> n2;\n",
+            "}\n",
+        ));
+        assert!(definite("[1]").dot().contains("[label=\"ε\", shape=plaintext]"));
+    }
+}
@@ -0,0 +1,221 @@
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+use super::{DefiniteParser, IndefiniteParser, Lexer, LRParser, Result, TableDrivenParser, Value};
+
+// 各个parser的统一接口，运行时按ParserKind选择实现，所有parser使用相同的Options
+// - 嵌套深度由Lexer限制，对所有parser一致
+// - Parser::parse解析整个输入，值之后只能有空白(宽松模式下还有注释)，
+//   而各parser自身的parse在解析完一个值后即返回
+// - 出错后由error_position取得出错的字节位置，from_str_with将它与错误消息一起放在ParseError中
+
+pub trait Parser<'s> {
+    /// 解析整个输入，值之后有多余的内容时报错
+    fn parse(&mut self) -> Result<Value<'s>>;
+
+    /// parse出错时出错的位置，见`Lexer::error_position`
+    fn error_position(&self) -> usize;
+}
+
+/// 解析错误，position为出错的字节位置，输入不完整时为输入的末尾
+/// 与输入内容无关的错误(如超过max_size)没有位置
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub message: String,
+    pub position: Option<usize>,
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl Error for ParseError {}
+
+impl From<ParseError> for String {
+    fn from(e: ParseError) -> String {
+        e.message
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum ParserKind {
+    Indefinite,
+    Definite,
+    // 默认使用TableDrivenParser，它不递归，深层嵌套或很长的数组也不会栈溢出
+    #[default]
+    Table,
+    Lr,
+}
+
+impl ParserKind {
+    pub const ALL: [ParserKind; 4] = [ParserKind::Indefinite, ParserKind::Definite, ParserKind::Table, ParserKind::Lr];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ParserKind::Indefinite => "indefinite",
+            ParserKind::Definite => "definite",
+            ParserKind::Table => "table",
+            ParserKind::Lr => "lr",
+        }
+    }
+
+    pub fn parser<'s>(self, lexer: Lexer<'s>) -> Box<dyn Parser<'s> + 's> {
+        match self {
+            ParserKind::Indefinite => Box::new(IndefiniteParser::new(lexer)),
+            ParserKind::Definite => Box::new(DefiniteParser::new(lexer)),
+            ParserKind::Table => Box::new(TableDrivenParser::new(lexer)),
+            ParserKind::Lr => Box::new(LRParser::new(lexer)),
+        }
+    }
+}
+
+impl Display for ParserKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for ParserKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<ParserKind> {
+        ParserKind::ALL.into_iter()
+            .find(|kind| kind.name() == s)
+            .ok_or_else(|| format!("unknown parser {}, expect indefinite | definite | table | lr", s))
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Options {
+    // 宽松模式(JSON5)，见Lexer
+    pub lenient: bool,
+    // [ { 的最大嵌套深度
+    pub max_depth: usize,
+    // 输入的最大字节数
+    pub max_size: usize,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            lenient: false,
+            max_depth: 128,
+            max_size: usize::MAX,
+        }
+    }
+}
+
+impl Options {
+    /// 按选项构造Lexer，输入超过max_size时报错
+    pub fn lexer<'s>(&self, s: &'s str) -> Result<Lexer<'s>> {
+        if s.len() > self.max_size {
+            return Err(format!("input of {} bytes exceeds max size {}", s.len(), self.max_size));
+        }
+        let lexer = if self.lenient { Lexer::lenient(s) } else { Lexer::new(s) };
+        Ok(lexer.with_max_depth(self.max_depth))
+    }
+}
+
+/// 用默认的parser和选项解析
+pub fn from_str(s: &str) -> std::result::Result<Value<'_>, ParseError> {
+    from_str_with(ParserKind::default(), &Options::default(), s)
+}
+
+pub fn from_str_with<'s>(kind: ParserKind, options: &Options, s: &'s str) -> std::result::Result<Value<'s>, ParseError> {
+    let lexer = options.lexer(s).map_err(|message| ParseError { message, position: None })?;
+    let mut parser = kind.parser(lexer);
+    parser.parse().map_err(|message| ParseError { message, position: Some(parser.error_position()) })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lenient() -> Options {
+        Options { lenient: true, ..Options::default() }
+    }
+
+    #[test]
+    fn all_kinds_agree() {
+        let inputs = [
+            r#"{"a": [1, -2.5e3, {}, []], "b": {"c": null, "d": [true, false]}, "a": "last"}"#,
+            "[[[[]]], {}]",
+            r#""x""#,
+            " 0 ",
+        ];
+        for src in inputs {
+            let expected = from_str(src).unwrap();
+            for kind in ParserKind::ALL {
+                assert_eq!(from_str_with(kind, &Options::default(), src).unwrap(), expected, "{} {}", kind, src);
+            }
+        }
+        let src = "// JSON5\n{a: [1, 2,], 'b': +.5,}";
+        let expected = from_str_with(ParserKind::default(), &lenient(), src).unwrap();
+        for kind in ParserKind::ALL {
+            assert_eq!(from_str_with(kind, &lenient(), src).unwrap(), expected, "{}", kind);
+        }
+    }
+
+    #[test]
+    fn all_kinds_reject() {
+        for src in ["[1] 2", "[1,]", "{\"a\" 1}", "[", "", "{a: 1}"] {
+            for kind in ParserKind::ALL {
+                assert!(from_str_with(kind, &Options::default(), src).is_err(), "{} {:?}", kind, src);
+            }
+        }
+    }
+
+    #[test]
+    fn limits() {
+        let options = Options { max_depth: 3, max_size: 16, ..Options::default() };
+        for kind in ParserKind::ALL {
+            assert!(from_str_with(kind, &options, "[[[1]]]").is_ok(), "{}", kind);
+            assert!(from_str_with(kind, &options, "[[[[1]]]]").is_err(), "{}", kind);
+            assert_eq!(from_str_with(kind, &options, &" ".repeat(17)).unwrap_err(), ParseError {
+                message: "input of 17 bytes exceeds max size 16".to_string(),
+                position: None,
+            });
+        }
+    }
+
+    #[test]
+    fn kind_names() {
+        for kind in ParserKind::ALL {
+            assert_eq!(kind.to_string().parse::<ParserKind>().unwrap(), kind);
+        }
+        assert_eq!(ParserKind::default(), ParserKind::Table);
+        assert_eq!("ll".parse::<ParserKind>().unwrap_err(), "unknown parser ll, expect indefinite | definite | table | lr");
+    }
+
+    #[test]
+    fn error_positions() {
+        // (输入, 出错的位置)，语法错误在出错的token的起点，词法错误在出错的字符
+        let cases = [
+            ("[1 2]", 3),
+            ("[1,]", 3),
+            ("{\"a\":1,}", 7),
+            ("[1] 2", 4),
+            ("[1, x]", 4),
+            ("[\"ab\\q\"]", 4),
+            ("[\"ab", 1),
+            ("[1,", 3),
+            ("[1,\n  ", 6),
+            ("", 0),
+        ];
+        for (src, position) in cases {
+            for kind in ParserKind::ALL {
+                assert_eq!(from_str_with(kind, &Options::default(), src).unwrap_err().position, Some(position), "{} {:?}", kind, src);
+            }
+        }
+        // 回溯的parser在Pair失败后改为尝试{}，在"a"上报错
+        let error = from_str_with(ParserKind::Indefinite, &Options::default(), "{\"a\" 1}").unwrap_err();
+        assert_eq!((error.message.as_str(), error.position), ("expect } at position: 4", Some(1)));
+        assert_eq!(from_str_with(ParserKind::Table, &Options::default(), "{\"a\" 1}").unwrap_err().position, Some(5));
+        let options = Options { max_depth: 2, ..Options::default() };
+        for kind in ParserKind::ALL {
+            assert_eq!(from_str_with(kind, &options, "[[[]]]").unwrap_err().position, Some(2), "{}", kind);
+        }
+    }
+}
@@ -0,0 +1,325 @@
+// 按16/32字节的块扫描输入，供Lexer跳过空白、查找字符串的结束位置，以及批量校验UTF-8
+// - Avx2: x86_64上运行时检测到AVX2时使用，每次处理32字节；CPU不支持时退回Portable，结果相同
+// - Sse2: x86_64必定支持SSE2，每次处理16字节
+// - Portable: 其他平台，按16字节的块逐字节分类，由编译器自动向量化
+// - Scalar: 原先的逐字节实现，用于对比
+// 块内第i个字节对应掩码的第i位，块末尾不足一块的部分逐字节处理
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Scanner {
+    Scalar,
+    Portable,
+    Sse2,
+    Avx2,
+}
+
+/// 一个块中各类字节的位置
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Masks {
+    pub quote: u32,
+    pub backslash: u32,
+    // { } [ ] : ,
+    pub structural: u32,
+    // 空格 \t \n \r
+    pub whitespace: u32,
+    pub non_ascii: u32,
+}
+
+fn is_whitespace(b: u8) -> bool {
+    matches!(b, b' ' | b'\t' | b'\n' | b'\r')
+}
+
+fn is_structural(b: u8) -> bool {
+    matches!(b, b'{' | b'}' | b'[' | b']' | b':' | b',')
+}
+
+impl Scanner {
+    /// 当前CPU上最快的实现
+    pub fn detect() -> Scanner {
+        #[cfg(target_arch = "x86_64")]
+        {
+            if is_x86_feature_detected!("avx2") {
+                return Scanner::Avx2;
+            }
+            Scanner::Sse2
+        }
+        #[cfg(not(target_arch = "x86_64"))]
+        Scanner::Portable
+    }
+
+    /// 每块的字节数
+    pub fn width(self) -> usize {
+        match self {
+            Scanner::Scalar => 1,
+            Scanner::Portable | Scanner::Sse2 => 16,
+            Scanner::Avx2 => 32,
+        }
+    }
+
+    /// 对`block`开头的一块字节分类，`block`至少要有`width()`个字节
+    pub fn classify(self, block: &[u8], quote: u8) -> Masks {
+        assert!(block.len() >= self.width());
+        match self {
+            #[cfg(target_arch = "x86_64")]
+            // SAFETY: 长度已检查，AVX2在调用前检测
+            Scanner::Avx2 if has_avx2() => unsafe { x86::classify_avx2(block.as_ptr(), quote) },
+            #[cfg(target_arch = "x86_64")]
+            // SAFETY: 长度已检查，x86_64必定支持SSE2
+            Scanner::Sse2 => unsafe { x86::classify_sse2(block.as_ptr(), quote) },
+            _ => classify_portable(&block[..self.width()], quote),
+        }
+    }
+
+    // 从i开始第一个满足条件的字节的位置，没有时返回bytes.len()
+    // select从块的分类结果中选出满足条件的位，scalar对单个字节判断
+    fn find(self, bytes: &[u8], i: usize, quote: u8, select: impl Fn(Masks) -> u32, scalar: impl Fn(u8) -> bool) -> usize {
+        let mut i = match self {
+            Scanner::Scalar => i,
+            #[cfg(target_arch = "x86_64")]
+            // SAFETY: 同classify
+            Scanner::Avx2 if has_avx2() => unsafe { x86::find_avx2(bytes, i, quote, &select) },
+            #[cfg(target_arch = "x86_64")]
+            Scanner::Sse2 => unsafe { x86::find_sse2(bytes, i, quote, &select) },
+            _ => find_portable(bytes, i, quote, &select),
+        };
+        while i < bytes.len() && !scalar(bytes[i]) {
+            i += 1;
+        }
+        i
+    }
+
+    /// 跳过从i开始的空白，返回第一个非空白字节的位置
+    pub fn skip_whitespace(self, bytes: &[u8], i: usize) -> usize {
+        // token之间通常没有或只有一个空白，不值得按块处理
+        if i >= bytes.len() || !is_whitespace(bytes[i]) {
+            return i;
+        }
+        if i + 1 < bytes.len() && !is_whitespace(bytes[i + 1]) {
+            return i + 1;
+        }
+        self.find(bytes, i, b'"', |m| !m.whitespace, |b| !is_whitespace(b))
+    }
+
+    /// 从i开始查找字符串中的引号或`\`
+    pub fn find_quote_or_escape(self, bytes: &[u8], i: usize, quote: u8) -> usize {
+        self.find(bytes, i, quote, |m| m.quote | m.backslash, |b| b == quote || b == b'\\')
+    }
+
+    /// 从i开始查找结构字符或引号，用于跳过整个子树
+    pub fn find_structural_or_quote(self, bytes: &[u8], i: usize) -> usize {
+        self.find(bytes, i, b'"', |m| m.structural | m.quote, |b| is_structural(b) || b == b'"')
+    }
+
+    /// 校验UTF-8，失败时返回第一个非法字节的位置
+    /// 按块跳过纯ASCII的部分，只对含有非ASCII字节的片段调用标准库的校验；
+    /// 片段至少4KB，延伸到之后第一个纯ASCII的块之前，块的开头必然是字符边界
+    pub fn validate_utf8(self, bytes: &[u8]) -> Result<(), usize> {
+        if self == Scanner::Scalar {
+            return std::str::from_utf8(bytes).map(|_| ()).map_err(|e| e.valid_up_to());
+        }
+        let width = self.width();
+        let mut i = 0;
+        loop {
+            i = self.find(bytes, i, b'"', |m| m.non_ascii, |b| b >= 0x80);
+            if i >= bytes.len() {
+                return Ok(());
+            }
+            let mut end = i + 4096 - width;
+            loop {
+                end += width;
+                if end >= bytes.len() {
+                    end = bytes.len();
+                    break;
+                }
+                if end + width <= bytes.len() && self.classify(&bytes[end..], b'"').non_ascii == 0 {
+                    break;
+                }
+            }
+            std::str::from_utf8(&bytes[i..end]).map_err(|e| i + e.valid_up_to())?;
+            i = end;
+        }
+    }
+}
+
+// Scanner::Avx2可以由调用者直接构造，每次使用前都要确认CPU支持，标准库会缓存检测结果
+#[cfg(target_arch = "x86_64")]
+fn has_avx2() -> bool {
+    is_x86_feature_detected!("avx2")
+}
+
+fn classify_portable(block: &[u8], quote: u8) -> Masks {
+    let mut masks = Masks::default();
+    for (i, b) in block.iter().enumerate() {
+        let bit = 1 << i;
+        masks.quote |= ((*b == quote) as u32) * bit;
+        masks.backslash |= ((*b == b'\\') as u32) * bit;
+        masks.structural |= (is_structural(*b) as u32) * bit;
+        masks.whitespace |= (is_whitespace(*b) as u32) * bit;
+        masks.non_ascii |= ((*b >= 0x80) as u32) * bit;
+    }
+    masks
+}
+
+fn find_portable(bytes: &[u8], mut i: usize, quote: u8, select: &impl Fn(Masks) -> u32) -> usize {
+    while i + 16 <= bytes.len() {
+        let m = select(classify_portable(&bytes[i..i + 16], quote)) & 0xFFFF;
+        if m != 0 {
+            return i + m.trailing_zeros() as usize;
+        }
+        i += 16;
+    }
+    i
+}
+
+#[cfg(target_arch = "x86_64")]
+mod x86 {
+    use std::arch::x86_64::*;
+    use super::Masks;
+
+    macro_rules! classify {
+        ($v:expr, $quote:expr, $set1:ident, $cmpeq:ident, $movemask:ident) => {{
+            let v = $v;
+            let eq = |b: u8| $movemask($cmpeq(v, $set1(b as i8))) as u32;
+            Masks {
+                quote: eq($quote),
+                backslash: eq(b'\\'),
+                structural: eq(b'{') | eq(b'}') | eq(b'[') | eq(b']') | eq(b':') | eq(b','),
+                whitespace: eq(b' ') | eq(b'\t') | eq(b'\n') | eq(b'\r'),
+                non_ascii: $movemask(v) as u32,
+            }
+        }};
+    }
+
+    #[target_feature(enable = "sse2")]
+    pub unsafe fn classify_sse2(p: *const u8, quote: u8) -> Masks {
+        classify!(_mm_loadu_si128(p as *const __m128i), quote, _mm_set1_epi8, _mm_cmpeq_epi8, _mm_movemask_epi8)
+    }
+
+    #[target_feature(enable = "avx2")]
+    pub unsafe fn classify_avx2(p: *const u8, quote: u8) -> Masks {
+        classify!(_mm256_loadu_si256(p as *const __m256i), quote, _mm256_set1_epi8, _mm256_cmpeq_epi8, _mm256_movemask_epi8)
+    }
+
+    #[target_feature(enable = "sse2")]
+    pub unsafe fn find_sse2(bytes: &[u8], mut i: usize, quote: u8, select: &impl Fn(Masks) -> u32) -> usize {
+        while i + 16 <= bytes.len() {
+            let m = select(classify_sse2(bytes.as_ptr().add(i), quote)) & 0xFFFF;
+            if m != 0 {
+                return i + m.trailing_zeros() as usize;
+            }
+            i += 16;
+        }
+        i
+    }
+
+    #[target_feature(enable = "avx2")]
+    pub unsafe fn find_avx2(bytes: &[u8], mut i: usize, quote: u8, select: &impl Fn(Masks) -> u32) -> usize {
+        while i + 32 <= bytes.len() {
+            let m = select(classify_avx2(bytes.as_ptr().add(i), quote));
+            if m != 0 {
+                return i + m.trailing_zeros() as usize;
+            }
+            i += 32;
+        }
+        i
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::json::Lexer;
+
+    const SCANNERS: [Scanner; 4] = [Scanner::Scalar, Scanner::Portable, Scanner::Sse2, Scanner::Avx2];
+
+    // 覆盖各类字节的输入，长度不是块宽度的整数倍
+    fn sample() -> Vec<u8> {
+        let mut bytes = "{\"k\\\"ey\": [1, 2.5e3, true, null],\t\r\n 'x' : \"é中\"}".repeat(7).into_bytes();
+        bytes.extend((0..=255u8).rev());
+        bytes
+    }
+
+    fn scalar_masks(block: &[u8], quote: u8) -> Masks {
+        let mut masks = Masks::default();
+        for (i, b) in block.iter().enumerate() {
+            let bit = 1u32 << i;
+            if *b == quote {
+                masks.quote |= bit;
+            }
+            if *b == b'\\' {
+                masks.backslash |= bit;
+            }
+            if is_structural(*b) {
+                masks.structural |= bit;
+            }
+            if is_whitespace(*b) {
+                masks.whitespace |= bit;
+            }
+            if *b >= 0x80 {
+                masks.non_ascii |= bit;
+            }
+        }
+        masks
+    }
+
+    #[test]
+    fn classify_matches_scalar() {
+        let bytes = sample();
+        for scanner in &SCANNERS[1..] {
+            let width = scanner.width();
+            for start in 0..=bytes.len() - width {
+                for quote in [b'"', b'\''] {
+                    let block = &bytes[start..start + width];
+                    assert_eq!(scanner.classify(block, quote), scalar_masks(block, quote), "{:?} at {}", scanner, start);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn find_matches_scalar() {
+        let bytes = sample();
+        for scanner in SCANNERS {
+            for i in 0..=bytes.len() {
+                assert_eq!(scanner.skip_whitespace(&bytes, i), Scanner::Scalar.skip_whitespace(&bytes, i));
+                assert_eq!(scanner.find_quote_or_escape(&bytes, i, b'\''), Scanner::Scalar.find_quote_or_escape(&bytes, i, b'\''));
+                assert_eq!(scanner.find_quote_or_escape(&bytes, i, b'"'), Scanner::Scalar.find_quote_or_escape(&bytes, i, b'"'));
+                assert_eq!(scanner.find_structural_or_quote(&bytes, i), Scanner::Scalar.find_structural_or_quote(&bytes, i));
+            }
+        }
+    }
+
+    #[test]
+    fn validate_utf8_matches_scalar() {
+        let mut text = "a".repeat(5000).into_bytes();
+        text.extend("é中".as_bytes());
+        text.extend(b" ".repeat(100));
+        for scanner in SCANNERS {
+            assert_eq!(scanner.validate_utf8(&text), Ok(()));
+            let mut invalid = text.clone();
+            invalid[5001] = b'x';
+            assert_eq!(scanner.validate_utf8(&invalid), Err(5000), "{:?}", scanner);
+            invalid.truncate(5000);
+            invalid.push(0xFF);
+            assert_eq!(scanner.validate_utf8(&invalid), Err(5000), "{:?}", scanner);
+        }
+    }
+
+    #[test]
+    fn lexer_with_each_scanner() {
+        let src = format!("{{\"a\": [{}],\n{}\"b\\\"c\": \"{}\"}}", "1,   ".repeat(20), " ".repeat(40), "é\\n".repeat(30));
+        let tokens = |scanner: Scanner| {
+            let mut lexer = Lexer::new(&src).with_scanner(scanner);
+            let mut tokens = vec![];
+            while !lexer.at_end().unwrap() {
+                tokens.push((lexer.lex().unwrap(), lexer.index()));
+            }
+            tokens
+        };
+        let expect = tokens(Scanner::Scalar);
+        assert_eq!(expect.len(), 50);
+        for scanner in SCANNERS {
+            assert_eq!(tokens(scanner), expect, "{:?}", scanner);
+        }
+    }
+}
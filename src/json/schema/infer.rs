@@ -0,0 +1,286 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::{Display, Formatter};
+use super::{Regex, Value, unescape};
+use super::super::TAB;
+
+// 从样本推断结构
+// 同一路径上观察到的所有值合并为一个Shape:
+// - 记录出现过的类型，数字全为整数时为integer
+// - 对象的键在该路径的所有对象中都出现时为必需，否则为可选
+// - 数组的所有元素合并为一个Shape
+// - 所有字符串都符合同一种格式时记录该格式
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    DateTime,
+    Date,
+    // 形如"2024-04-01 17:47:11"，不是RFC 3339，以pattern表示
+    LocalDateTime,
+    Uri,
+    Email,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Shape<'a> {
+    // 该路径上出现的次数
+    count: usize,
+    null: bool,
+    boolean: bool,
+    integer: bool,
+    number: bool,
+    // None表示没有出现过字符串，Some(None)表示出现过但格式不一致
+    string: Option<Option<Format>>,
+    items: Option<Box<Shape<'a>>>,
+    // (对象出现的次数, 各个键的Shape)
+    object: Option<(usize, BTreeMap<&'a str, Shape<'a>>)>,
+}
+
+struct Formats {
+    patterns: Vec<(Format, Regex)>,
+}
+
+impl Formats {
+    fn new() -> Formats {
+        let patterns = [
+            (Format::DateTime, r"^\d{4}-\d{2}-\d{2}[Tt]\d{2}:\d{2}:\d{2}(\.\d+)?([Zz]|[+-]\d{2}:\d{2})$"),
+            (Format::Date, r"^\d{4}-\d{2}-\d{2}$"),
+            (Format::LocalDateTime, r"^\d{4}-\d{2}-\d{2} \d{2}:\d{2}:\d{2}$"),
+            (Format::Uri, r"^[a-zA-Z][a-zA-Z0-9+.-]*://\S+$"),
+            (Format::Email, r"^[^@\s]+@[^@\s]+\.[^@\s]+$"),
+        ];
+        Formats {
+            patterns: patterns.iter().map(|(f, p)| (*f, Regex::new(p).unwrap())).collect(),
+        }
+    }
+
+    fn detect(&self, s: &str) -> Option<Format> {
+        let s = unescape(s).ok()?;
+        self.patterns.iter().find(|(_, regex)| regex.is_match(&s)).map(|(f, _)| *f)
+    }
+}
+
+/// 合并所有样本，推断出它们共同的结构
+pub fn infer<'a>(samples: &[Value<'a>]) -> Shape<'a> {
+    let formats = Formats::new();
+    let mut shape = Shape::default();
+    for sample in samples {
+        shape.observe(sample, &formats);
+    }
+    shape
+}
+
+impl<'a> Shape<'a> {
+    fn observe(&mut self, value: &Value<'a>, formats: &Formats) {
+        self.count += 1;
+        match value {
+            Value::Null => self.null = true,
+            Value::Bool(_) => self.boolean = true,
+            Value::Number(n) if n.fract() == 0.0 => self.integer = true,
+            Value::Number(_) => self.number = true,
+            Value::String(s) => {
+                let format = formats.detect(s);
+                self.string = match self.string {
+                    None => Some(format),
+                    Some(seen) => Some(if seen == format { seen } else { None }),
+                };
+            }
+            Value::Array(arr) => {
+                let items = self.items.get_or_insert_with(Box::default);
+                for item in arr {
+                    items.observe(item, formats);
+                }
+            }
+            Value::Object(obj) => {
+                let (count, properties) = self.object.get_or_insert_with(Default::default);
+                *count += 1;
+                for (key, value) in obj {
+                    properties.entry(*key).or_default().observe(value, formats);
+                }
+            }
+        }
+    }
+
+    fn types(&self) -> Vec<&'static str> {
+        let mut types = vec![];
+        if self.null {
+            types.push("null");
+        }
+        if self.boolean {
+            types.push("boolean");
+        }
+        if self.number {
+            types.push("number");
+        } else if self.integer {
+            types.push("integer");
+        }
+        if self.string.is_some() {
+            types.push("string");
+        }
+        if self.items.is_some() {
+            types.push("array");
+        }
+        if self.object.is_some() {
+            types.push("object");
+        }
+        types
+    }
+
+    /// 转为JSON Schema (draft 2020-12)
+    pub fn to_schema(&self) -> Value<'a> {
+        let mut schema = self.to_subschema();
+        if let Value::Object(obj) = &mut schema {
+            obj.insert("$schema", Value::String("https://json-schema.org/draft/2020-12/schema"));
+        }
+        schema
+    }
+
+    fn to_subschema(&self) -> Value<'a> {
+        let mut schema = HashMap::new();
+        let types = self.types();
+        match types.len() {
+            // 没有观察到任何值，如空数组的元素
+            0 => return Value::Bool(true),
+            1 => {
+                schema.insert("type", Value::String(types[0]));
+            }
+            _ => {
+                schema.insert("type", Value::Array(types.into_iter().map(Value::String).collect()));
+            }
+        }
+        match self.string {
+            Some(Some(Format::DateTime)) => {
+                schema.insert("format", Value::String("date-time"));
+            }
+            Some(Some(Format::Date)) => {
+                schema.insert("format", Value::String("date"));
+            }
+            Some(Some(Format::Uri)) => {
+                schema.insert("format", Value::String("uri"));
+            }
+            Some(Some(Format::Email)) => {
+                schema.insert("format", Value::String("email"));
+            }
+            // Value::String保存的是转义后的原文
+            Some(Some(Format::LocalDateTime)) => {
+                schema.insert("pattern", Value::String(r"^\\d{4}-\\d{2}-\\d{2} \\d{2}:\\d{2}:\\d{2}$"));
+            }
+            _ => {}
+        }
+        if let Some(items) = &self.items {
+            schema.insert("items", items.to_subschema());
+        }
+        if let Some((count, properties)) = &self.object {
+            let mut required = vec![];
+            let mut props = HashMap::new();
+            for (key, shape) in properties {
+                if shape.count == *count {
+                    required.push(Value::String(key));
+                }
+                props.insert(*key, shape.to_subschema());
+            }
+            schema.insert("properties", Value::Object(props));
+            if !required.is_empty() {
+                schema.insert("required", Value::Array(required));
+            }
+        }
+        Value::Object(schema)
+    }
+
+    fn fmt_shape(&self, f: &mut Formatter<'_>, width: usize) -> std::fmt::Result {
+        let types = self.types();
+        if types.is_empty() {
+            return write!(f, "unknown");
+        }
+        for (i, t) in types.into_iter().enumerate() {
+            if i > 0 {
+                write!(f, " | ")?;
+            }
+            match t {
+                "string" => match self.string.unwrap() {
+                    Some(format) => write!(f, "string<{:?}>", format)?,
+                    None => write!(f, "string")?,
+                },
+                "array" => {
+                    write!(f, "[")?;
+                    self.items.as_ref().unwrap().fmt_shape(f, width)?;
+                    write!(f, "]")?;
+                }
+                "object" => {
+                    let (count, properties) = self.object.as_ref().unwrap();
+                    writeln!(f, "{{")?;
+                    for (key, shape) in properties {
+                        tab(f, width + 1)?;
+                        let optional = if shape.count == *count { "" } else { "?" };
+                        write!(f, "\"{}\"{}: ", key, optional)?;
+                        shape.fmt_shape(f, width + 1)?;
+                        writeln!(f, ",")?;
+                    }
+                    tab(f, width)?;
+                    write!(f, "}}")?;
+                }
+                t => write!(f, "{}", t)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+fn tab(f: &mut Formatter<'_>, width: usize) -> std::fmt::Result {
+    for _ in 0..width {
+        write!(f, "{}", TAB)?;
+    }
+    Ok(())
+}
+
+/// 以类似TypeScript类型的形式输出，可选的键以`?`标记
+impl Display for Shape<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        self.fmt_shape(f, 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::json::from_str;
+
+    #[test]
+    fn formats() {
+        let formats = Formats::new();
+        let cases = [
+            ("2024-04-01T17:47:11.5+08:00", Some(Format::DateTime)),
+            ("2024-04-01t17:47:11Z", Some(Format::DateTime)),
+            ("2024-04-01", Some(Format::Date)),
+            ("2024-04-01 17:47:11", Some(Format::LocalDateTime)),
+            ("https://example.com/a?b=c", Some(Format::Uri)),
+            ("a.b@example.com", Some(Format::Email)),
+            ("2024-4-1", None),
+            ("http://a b", None),
+            ("a@b", None),
+        ];
+        for (s, format) in cases {
+            assert_eq!(formats.detect(s), format, "{}", s);
+        }
+    }
+
+    #[test]
+    fn long_strings() {
+        let formats = Formats::new();
+        let uri = format!("https://example.com/{}", "a".repeat(200_000));
+        assert_eq!(formats.detect(&uri), Some(Format::Uri));
+        assert_eq!(formats.detect(&"@".repeat(200_000)), None);
+    }
+
+    #[test]
+    fn infer_schema() {
+        let samples = [
+            from_str(r#"{"id": 1, "at": "2024-04-01", "tags": ["a"], "score": 1.5}"#).unwrap(),
+            from_str(r#"{"id": 2, "at": "2024-04-02", "tags": [], "score": null}"#).unwrap(),
+        ];
+        let schema = infer(&samples).to_schema();
+        assert_eq!(schema.pointer("/required").unwrap().to_canonical_string().unwrap(), r#"["at","id","score","tags"]"#);
+        assert_eq!(schema.pointer("/properties/id/type"), Some(&Value::String("integer")));
+        assert_eq!(schema.pointer("/properties/at/format"), Some(&Value::String("date")));
+        assert_eq!(schema.pointer("/properties/tags/items/type"), Some(&Value::String("string")));
+    }
+}
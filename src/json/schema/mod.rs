@@ -0,0 +1,669 @@
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use super::{Result, Value, unescape};
+use super::span::pointer;
+
+mod regex;
+pub mod infer;
+
+pub use regex::Regex;
+pub use infer::{infer, Shape};
+
+// JSON Schema (draft 2020-12) 校验
+// 支持core和validation词汇表中的以下关键字，其余关键字视为注解忽略
+// - type enum const
+// - minimum maximum exclusiveMinimum exclusiveMaximum multipleOf
+// - minLength maxLength pattern
+// - items minItems maxItems
+// - properties required additionalProperties minProperties maxProperties
+// - allOf anyOf oneOf not
+// - $ref，仅支持指向同一文档内的JSON Pointer，如`#/$defs/item`
+//
+// 编译时每个子schema对应nodes中的一项，子schema之间以下标引用，$ref可以形成环
+// 校验时记录经由$ref正在求值的(子schema, 值的路径)，再次进入时报错，不会无限递归
+
+pub struct Schema<'a> {
+    nodes: Vec<SchemaNode<'a>>,
+}
+
+enum SchemaNode<'a> {
+    Bool(bool),
+    Keywords(Vec<Keyword<'a>>),
+}
+
+enum Keyword<'a> {
+    Type(Vec<String>),
+    Enum(Vec<Value<'a>>),
+    Const(Value<'a>),
+    Minimum(f64),
+    Maximum(f64),
+    ExclusiveMinimum(f64),
+    ExclusiveMaximum(f64),
+    MultipleOf(f64),
+    MinLength(usize),
+    MaxLength(usize),
+    Pattern(String, Regex),
+    Items(usize),
+    MinItems(usize),
+    MaxItems(usize),
+    Properties(Vec<(String, usize)>),
+    Required(Vec<String>),
+    // 不在properties中的属性需要满足的schema
+    AdditionalProperties(usize, Vec<String>),
+    MinProperties(usize),
+    MaxProperties(usize),
+    AllOf(Vec<usize>),
+    AnyOf(Vec<usize>),
+    OneOf(Vec<usize>),
+    Not(usize),
+    Ref(usize),
+}
+
+/// 一处校验失败
+/// - `instance_path`为被校验值的JSON Pointer
+/// - `schema_path`为失败关键字的求值路径，经过`$ref`时包含`/$ref`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Violation {
+    pub instance_path: String,
+    pub schema_path: String,
+    pub message: String,
+}
+
+impl Display for Violation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {} (schema: {})", self.instance_path, self.message, self.schema_path)
+    }
+}
+
+struct Compiler<'a, 'v> {
+    root: &'v Value<'a>,
+    nodes: Vec<SchemaNode<'a>>,
+    refs: HashMap<String, usize>,
+    pending: Vec<(String, usize)>,
+}
+
+impl<'a> Schema<'a> {
+    pub fn compile(schema: &Value<'a>) -> Result<Schema<'a>> {
+        let mut compiler = Compiler {
+            root: schema,
+            nodes: vec![],
+            refs: HashMap::new(),
+            pending: vec![],
+        };
+        compiler.refs.insert(String::new(), 0);
+        compiler.compile(schema, "")?;
+        while let Some((target, index)) = compiler.pending.pop() {
+            let value = compiler.root.pointer(&target)
+                .ok_or(format!("$ref target #{} not found", target))?;
+            compiler.nodes[index] = compiler.build(value, &target)?;
+        }
+        Ok(Schema { nodes: compiler.nodes })
+    }
+
+    /// 校验`instance`，返回所有失败之处，通过时为空
+    pub fn validate(&self, instance: &Value) -> Vec<Violation> {
+        let mut violations = vec![];
+        self.validate_node(0, instance, "", "", &mut vec![], &mut violations);
+        violations
+    }
+
+    pub fn is_valid(&self, instance: &Value) -> bool {
+        self.validate(instance).is_empty()
+    }
+
+    fn validate_node(
+        &self,
+        index: usize,
+        instance: &Value,
+        instance_path: &str,
+        schema_path: &str,
+        active: &mut Vec<(usize, String)>,
+        out: &mut Vec<Violation>,
+    ) {
+        let fail = |keyword: &str, message: String| Violation {
+            instance_path: instance_path.to_string(),
+            schema_path: format!("{}/{}", schema_path, keyword),
+            message,
+        };
+        let keywords = match &self.nodes[index] {
+            SchemaNode::Bool(true) => return,
+            SchemaNode::Bool(false) => {
+                out.push(Violation {
+                    instance_path: instance_path.to_string(),
+                    schema_path: schema_path.to_string(),
+                    message: "no value is allowed here".to_string(),
+                });
+                return;
+            }
+            SchemaNode::Keywords(keywords) => keywords,
+        };
+        for keyword in keywords {
+            match keyword {
+                Keyword::Type(types) => {
+                    if !types.iter().any(|t| is_type(instance, t)) {
+                        out.push(fail("type", format!("expect {}, found {}", types.join(" | "), type_of(instance))));
+                    }
+                }
+                Keyword::Enum(values) => {
+                    if !values.contains(instance) {
+                        out.push(fail("enum", "value is not one of the enumerated values".to_string()));
+                    }
+                }
+                Keyword::Const(value) => {
+                    if value != instance {
+                        out.push(fail("const", "value does not equal the const value".to_string()));
+                    }
+                }
+                Keyword::Minimum(min) => {
+                    if let Value::Number(n) = instance {
+                        if n < min {
+                            out.push(fail("minimum", format!("expect number >= {}, found {}", min, n)));
+                        }
+                    }
+                }
+                Keyword::Maximum(max) => {
+                    if let Value::Number(n) = instance {
+                        if n > max {
+                            out.push(fail("maximum", format!("expect number <= {}, found {}", max, n)));
+                        }
+                    }
+                }
+                Keyword::ExclusiveMinimum(min) => {
+                    if let Value::Number(n) = instance {
+                        if n <= min {
+                            out.push(fail("exclusiveMinimum", format!("expect number > {}, found {}", min, n)));
+                        }
+                    }
+                }
+                Keyword::ExclusiveMaximum(max) => {
+                    if let Value::Number(n) = instance {
+                        if n >= max {
+                            out.push(fail("exclusiveMaximum", format!("expect number < {}, found {}", max, n)));
+                        }
+                    }
+                }
+                Keyword::MultipleOf(m) => {
+                    if let Value::Number(n) = instance {
+                        let q = n / m;
+                        if (q - q.round()).abs() > 1e-9 {
+                            out.push(fail("multipleOf", format!("expect a multiple of {}, found {}", m, n)));
+                        }
+                    }
+                }
+                Keyword::MinLength(min) => {
+                    if let Some(len) = string_len(instance) {
+                        if len < *min {
+                            out.push(fail("minLength", format!("expect at least {} characters, found {}", min, len)));
+                        }
+                    }
+                }
+                Keyword::MaxLength(max) => {
+                    if let Some(len) = string_len(instance) {
+                        if len > *max {
+                            out.push(fail("maxLength", format!("expect at most {} characters, found {}", max, len)));
+                        }
+                    }
+                }
+                Keyword::Pattern(pattern, regex) => {
+                    if let Value::String(s) = instance {
+                        if !unescape(s).is_ok_and(|s| regex.is_match(&s)) {
+                            out.push(fail("pattern", format!("does not match pattern {:?}", pattern)));
+                        }
+                    }
+                }
+                Keyword::Items(items) => {
+                    if let Value::Array(arr) = instance {
+                        for (i, item) in arr.iter().enumerate() {
+                            self.validate_node(
+                                *items, item, &join(instance_path, &i.to_string()),
+                                &format!("{}/items", schema_path), active, out,
+                            );
+                        }
+                    }
+                }
+                Keyword::MinItems(min) => {
+                    if let Value::Array(arr) = instance {
+                        if arr.len() < *min {
+                            out.push(fail("minItems", format!("expect at least {} items, found {}", min, arr.len())));
+                        }
+                    }
+                }
+                Keyword::MaxItems(max) => {
+                    if let Value::Array(arr) = instance {
+                        if arr.len() > *max {
+                            out.push(fail("maxItems", format!("expect at most {} items, found {}", max, arr.len())));
+                        }
+                    }
+                }
+                Keyword::Properties(properties) => {
+                    if let Value::Object(_) = instance {
+                        for (name, schema) in properties {
+                            if let Some(value) = instance.get(name) {
+                                self.validate_node(
+                                    *schema, value, &join(instance_path, name),
+                                    &join(&format!("{}/properties", schema_path), name), active, out,
+                                );
+                            }
+                        }
+                    }
+                }
+                Keyword::Required(required) => {
+                    if let Value::Object(_) = instance {
+                        for name in required {
+                            if instance.get(name).is_none() {
+                                out.push(fail("required", format!("missing required property {:?}", name)));
+                            }
+                        }
+                    }
+                }
+                Keyword::AdditionalProperties(schema, known) => {
+                    if let Value::Object(obj) = instance {
+                        let mut extra: Vec<(String, &Value)> = obj.iter()
+                            .map(|(k, v)| (unescape(k).map_or(k.to_string(), |k| k.into_owned()), v))
+                            .filter(|(k, _)| !known.contains(k))
+                            .collect();
+                        extra.sort_by(|a, b| a.0.cmp(&b.0));
+                        for (name, value) in extra {
+                            self.validate_node(
+                                *schema, value, &join(instance_path, &name),
+                                &format!("{}/additionalProperties", schema_path), active, out,
+                            );
+                        }
+                    }
+                }
+                Keyword::MinProperties(min) => {
+                    if let Value::Object(obj) = instance {
+                        if obj.len() < *min {
+                            out.push(fail("minProperties", format!("expect at least {} properties, found {}", min, obj.len())));
+                        }
+                    }
+                }
+                Keyword::MaxProperties(max) => {
+                    if let Value::Object(obj) = instance {
+                        if obj.len() > *max {
+                            out.push(fail("maxProperties", format!("expect at most {} properties, found {}", max, obj.len())));
+                        }
+                    }
+                }
+                Keyword::AllOf(schemas) => {
+                    for (i, schema) in schemas.iter().enumerate() {
+                        let path = format!("{}/allOf/{}", schema_path, i);
+                        self.validate_node(*schema, instance, instance_path, &path, active, out);
+                    }
+                }
+                Keyword::AnyOf(schemas) => {
+                    let matched = schemas.iter().enumerate()
+                        .filter(|(i, schema)| self.matches(**schema, instance, instance_path, &format!("{}/anyOf/{}", schema_path, i), active))
+                        .count();
+                    if matched == 0 {
+                        out.push(fail("anyOf", "value does not match any schema in anyOf".to_string()));
+                    }
+                }
+                Keyword::OneOf(schemas) => {
+                    let matched = schemas.iter().enumerate()
+                        .filter(|(i, schema)| self.matches(**schema, instance, instance_path, &format!("{}/oneOf/{}", schema_path, i), active))
+                        .count();
+                    if matched != 1 {
+                        out.push(fail("oneOf", format!("value matches {} schemas in oneOf, expect exactly 1", matched)));
+                    }
+                }
+                Keyword::Not(schema) => {
+                    if self.matches(*schema, instance, instance_path, &format!("{}/not", schema_path), active) {
+                        out.push(fail("not", "value must not match the schema in not".to_string()));
+                    }
+                }
+                Keyword::Ref(schema) => {
+                    // 同一个值再次经由$ref进入正在求值的schema，说明$ref形成了不消耗输入的环
+                    let key = (*schema, instance_path.to_string());
+                    if active.contains(&key) {
+                        out.push(fail("$ref", "schema recursion without consuming input".to_string()));
+                        continue;
+                    }
+                    active.push(key);
+                    let path = format!("{}/$ref", schema_path);
+                    self.validate_node(*schema, instance, instance_path, &path, active, out);
+                    active.pop();
+                }
+            }
+        }
+    }
+
+    // 只判断是否满足，不收集失败
+    fn matches(&self, schema: usize, instance: &Value, instance_path: &str, schema_path: &str, active: &mut Vec<(usize, String)>) -> bool {
+        let mut violations = vec![];
+        self.validate_node(schema, instance, instance_path, schema_path, active, &mut violations);
+        violations.is_empty()
+    }
+}
+
+impl<'a, 'v> Compiler<'a, 'v> {
+    fn compile(&mut self, value: &Value<'a>, location: &str) -> Result<usize> {
+        let index = self.nodes.len();
+        self.nodes.push(SchemaNode::Bool(true));
+        self.nodes[index] = self.build(value, location)?;
+        Ok(index)
+    }
+
+    fn build(&mut self, value: &Value<'a>, location: &str) -> Result<SchemaNode<'a>> {
+        let obj = match value {
+            Value::Bool(b) => return Ok(SchemaNode::Bool(*b)),
+            Value::Object(obj) => obj,
+            _ => return Err(format!("invalid schema at #{}: expect object or bool, found {}", location, value.type_name())),
+        };
+        let error = |keyword: &str, msg: &str| format!("invalid schema at #{}/{}: {}", location, keyword, msg);
+        let mut keywords = vec![];
+        if let Some(value) = obj.get("$ref") {
+            let target = match value {
+                Value::String(s) => s.strip_prefix('#').ok_or(error("$ref", "only local references are supported"))?,
+                _ => return Err(error("$ref", "expect string")),
+            };
+            let index = match self.refs.get(target) {
+                Some(index) => *index,
+                None => {
+                    let index = self.nodes.len();
+                    self.nodes.push(SchemaNode::Bool(true));
+                    self.refs.insert(target.to_string(), index);
+                    self.pending.push((target.to_string(), index));
+                    index
+                }
+            };
+            keywords.push(Keyword::Ref(index));
+        }
+        if let Some(value) = obj.get("type") {
+            let types = match value {
+                Value::String(s) => vec![s.to_string()],
+                Value::Array(arr) => arr.iter().map(|t| match t {
+                    Value::String(s) => Ok(s.to_string()),
+                    _ => Err(error("type", "expect string")),
+                }).collect::<Result<Vec<_>>>()?,
+                _ => return Err(error("type", "expect string or array")),
+            };
+            let known = ["null", "boolean", "object", "array", "number", "string", "integer"];
+            if let Some(t) = types.iter().find(|t| !known.contains(&t.as_str())) {
+                return Err(error("type", &format!("unknown type {:?}", t)));
+            }
+            keywords.push(Keyword::Type(types));
+        }
+        if let Some(value) = obj.get("enum") {
+            match value {
+                Value::Array(arr) => keywords.push(Keyword::Enum(arr.clone())),
+                _ => return Err(error("enum", "expect array")),
+            }
+        }
+        if let Some(value) = obj.get("const") {
+            keywords.push(Keyword::Const(value.clone()));
+        }
+        for (name, make) in [
+            ("minimum", Keyword::Minimum as fn(f64) -> Keyword<'a>),
+            ("maximum", Keyword::Maximum),
+            ("exclusiveMinimum", Keyword::ExclusiveMinimum),
+            ("exclusiveMaximum", Keyword::ExclusiveMaximum),
+            ("multipleOf", Keyword::MultipleOf),
+        ] {
+            if let Some(value) = obj.get(name) {
+                match value {
+                    Value::Number(n) if name != "multipleOf" || *n > 0.0 => keywords.push(make(*n)),
+                    _ => return Err(error(name, "expect number")),
+                }
+            }
+        }
+        for (name, make) in [
+            ("minLength", Keyword::MinLength as fn(usize) -> Keyword<'a>),
+            ("maxLength", Keyword::MaxLength),
+            ("minItems", Keyword::MinItems),
+            ("maxItems", Keyword::MaxItems),
+            ("minProperties", Keyword::MinProperties),
+            ("maxProperties", Keyword::MaxProperties),
+        ] {
+            if let Some(value) = obj.get(name) {
+                match value {
+                    Value::Number(n) if *n >= 0.0 && n.fract() == 0.0 => keywords.push(make(*n as usize)),
+                    _ => return Err(error(name, "expect non-negative integer")),
+                }
+            }
+        }
+        if let Some(value) = obj.get("pattern") {
+            match value {
+                Value::String(s) => {
+                    let pattern = unescape(s)?.into_owned();
+                    let regex = Regex::new(&pattern).map_err(|msg| error("pattern", &msg))?;
+                    keywords.push(Keyword::Pattern(pattern, regex));
+                }
+                _ => return Err(error("pattern", "expect string")),
+            }
+        }
+        if let Some(value) = obj.get("items") {
+            let items = self.compile(value, &format!("{}/items", location))?;
+            keywords.push(Keyword::Items(items));
+        }
+        let mut known = vec![];
+        if let Some(value) = obj.get("properties") {
+            let properties = match value {
+                Value::Object(properties) => properties,
+                _ => return Err(error("properties", "expect object")),
+            };
+            let mut compiled = vec![];
+            for (name, schema) in properties {
+                let name = unescape(name)?.into_owned();
+                let schema = self.compile(schema, &join(&format!("{}/properties", location), &name))?;
+                known.push(name.clone());
+                compiled.push((name, schema));
+            }
+            // HashMap的遍历顺序不确定，排序使报告的顺序稳定
+            compiled.sort_by(|a, b| a.0.cmp(&b.0));
+            keywords.push(Keyword::Properties(compiled));
+        }
+        if let Some(value) = obj.get("required") {
+            let required = match value {
+                Value::Array(arr) => arr.iter().map(|name| match name {
+                    Value::String(s) => Ok(unescape(s)?.into_owned()),
+                    _ => Err(error("required", "expect string")),
+                }).collect::<Result<Vec<_>>>()?,
+                _ => return Err(error("required", "expect array")),
+            };
+            keywords.push(Keyword::Required(required));
+        }
+        if let Some(value) = obj.get("additionalProperties") {
+            let schema = self.compile(value, &format!("{}/additionalProperties", location))?;
+            keywords.push(Keyword::AdditionalProperties(schema, known));
+        }
+        for (name, make) in [
+            ("allOf", Keyword::AllOf as fn(Vec<usize>) -> Keyword<'a>),
+            ("anyOf", Keyword::AnyOf),
+            ("oneOf", Keyword::OneOf),
+        ] {
+            if let Some(value) = obj.get(name) {
+                let schemas = match value {
+                    Value::Array(arr) if !arr.is_empty() => arr,
+                    _ => return Err(error(name, "expect non-empty array")),
+                };
+                let mut compiled = vec![];
+                for (i, schema) in schemas.iter().enumerate() {
+                    compiled.push(self.compile(schema, &format!("{}/{}/{}", location, name, i))?);
+                }
+                keywords.push(make(compiled));
+            }
+        }
+        if let Some(value) = obj.get("not") {
+            let schema = self.compile(value, &format!("{}/not", location))?;
+            keywords.push(Keyword::Not(schema));
+        }
+        Ok(SchemaNode::Keywords(keywords))
+    }
+}
+
+fn join(path: &str, token: &str) -> String {
+    format!("{}{}", path, pointer(&[token]))
+}
+
+fn type_of(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Object(_) => "object",
+        Value::Array(_) => "array",
+        Value::Number(n) if n.fract() == 0.0 => "integer",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+    }
+}
+
+fn is_type(value: &Value, t: &str) -> bool {
+    match (value, t) {
+        (Value::Number(_), "number") => true,
+        (Value::Number(n), "integer") => n.fract() == 0.0,
+        _ => type_of(value) == t,
+    }
+}
+
+fn string_len(value: &Value) -> Option<usize> {
+    match value {
+        Value::String(s) => Some(unescape(s).map_or(s.chars().count(), |s| s.chars().count())),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::json::from_str;
+
+    fn violations(schema: &str, instance: &str) -> Vec<Violation> {
+        let schema = from_str(schema).unwrap();
+        Schema::compile(&schema).unwrap().validate(&from_str(instance).unwrap())
+    }
+
+    #[test]
+    fn ref_cycles() {
+        let start = std::time::Instant::now();
+        let found = violations(r##"{"anyOf": [{"$ref": "#"}, {"$ref": "#"}]}"##, "1");
+        assert!(start.elapsed().as_secs() < 1);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].schema_path, "/anyOf");
+        assert_eq!(violations(r##"{"$ref": "#"}"##, "1")[0].schema_path, "/$ref/$ref");
+        // 每次经由$ref都进入更深的值，不是环
+        let tree = r##"{"type": "object", "properties": {"children": {"type": "array", "items": {"$ref": "#"}}}}"##;
+        assert!(violations(tree, r#"{"children": [{"children": [{}]}]}"#).is_empty());
+        let found = violations(tree, r#"{"children": [{"children": [1]}]}"#);
+        assert_eq!(found[0].instance_path, "/children/0/children/0");
+    }
+
+    fn paths(schema: &str, instance: &str) -> Vec<(String, String)> {
+        violations(schema, instance).into_iter().map(|v| (v.instance_path, v.schema_path)).collect()
+    }
+
+    fn valid(schema: &str, instance: &str) -> bool {
+        violations(schema, instance).is_empty()
+    }
+
+    #[test]
+    fn type_enum_const() {
+        assert!(valid(r#"{"type": "integer"}"#, "3"));
+        assert!(!valid(r#"{"type": "integer"}"#, "3.5"));
+        assert!(valid(r#"{"type": "number"}"#, "3"));
+        assert!(valid(r#"{"type": ["string", "null"]}"#, "null"));
+        assert!(!valid(r#"{"type": ["string", "null"]}"#, "false"));
+        assert_eq!(violations(r#"{"type": "string"}"#, "1")[0].message, "expect string, found integer");
+        assert!(valid(r#"{"enum": [1, "a", [true]]}"#, "[true]"));
+        assert!(!valid(r#"{"enum": [1, "a", [true]]}"#, "2"));
+        // 字符串按还原转义后的内容比较
+        assert!(valid(r#"{"const": {"a": "\u0041"}}"#, r#"{"a": "A"}"#));
+        assert!(!valid(r#"{"const": {"a": 1}}"#, r#"{"a": 1, "b": 2}"#));
+        assert!(valid("true", "1"));
+        assert_eq!(paths("false", "1"), [("".to_string(), "".to_string())]);
+    }
+
+    #[test]
+    fn numeric_bounds() {
+        let schema = r#"{"minimum": 1, "maximum": 10, "multipleOf": 0.5}"#;
+        assert!(valid(schema, "1"));
+        assert!(valid(schema, "10"));
+        assert!(valid(schema, "2.5"));
+        assert!(!valid(schema, "0"));
+        assert!(!valid(schema, "10.5"));
+        assert!(!valid(schema, "2.2"));
+        let schema = r#"{"exclusiveMinimum": 1, "exclusiveMaximum": 10}"#;
+        assert!(!valid(schema, "1"));
+        assert!(!valid(schema, "10"));
+        assert!(valid(schema, "9.99"));
+        // 只约束数值
+        assert!(valid(schema, r#""x""#));
+    }
+
+    #[test]
+    fn length_and_pattern() {
+        let schema = r#"{"minLength": 2, "maxLength": 3, "pattern": "^[a-zé]+$"}"#;
+        assert!(valid(schema, r#""ab""#));
+        // 长度按字符计算，转义还原后再匹配
+        assert!(valid(schema, r#""\u00e9\u00e9é""#));
+        assert!(!valid(schema, r#""a""#));
+        assert!(!valid(schema, r#""abcd""#));
+        assert!(!valid(schema, r#""a1""#));
+        assert!(valid(schema, "1"));
+        assert!(valid(r#"{"pattern": "b"}"#, r#""abc""#));
+    }
+
+    #[test]
+    fn items() {
+        let schema = r#"{"items": {"type": "number"}, "minItems": 1, "maxItems": 2}"#;
+        assert!(valid(schema, "[1, 2]"));
+        assert!(!valid(schema, "[]"));
+        assert!(!valid(schema, "[1, 2, 3]"));
+        assert_eq!(paths(schema, r#"[1, "x"]"#), [("/1".to_string(), "/items/type".to_string())]);
+    }
+
+    #[test]
+    fn properties() {
+        let schema = r#"{
+            "properties": {"a": {"type": "number"}, "b/c": {"type": "string"}},
+            "required": ["a"],
+            "additionalProperties": false,
+            "minProperties": 1,
+            "maxProperties": 2
+        }"#;
+        assert!(valid(schema, r#"{"a": 1, "b/c": "x"}"#));
+        assert!(valid(schema, r#"{"\u0061": 1}"#));
+        assert_eq!(
+            paths(schema, r#"{"a": "x", "b/c": 1}"#),
+            [("/a".to_string(), "/properties/a/type".to_string()), ("/b~1c".to_string(), "/properties/b~1c/type".to_string())],
+        );
+        assert_eq!(violations(schema, r#"{"b/c": "x"}"#)[0].message, r#"missing required property "a""#);
+        assert_eq!(paths(schema, r#"{"a": 1, "d": 2}"#), [("/d".to_string(), "/additionalProperties".to_string())]);
+        assert!(!valid(schema, "{}"));
+        assert!(!valid(schema, r#"{"a": 1, "b/c": "x", "d": 2}"#));
+        assert!(valid(r#"{"additionalProperties": {"type": "number"}}"#, r#"{"x": 1, "y": 2}"#));
+    }
+
+    #[test]
+    fn combinators() {
+        let all = r#"{"allOf": [{"type": "number"}, {"minimum": 2}]}"#;
+        assert!(valid(all, "3"));
+        assert_eq!(paths(all, "1"), [("".to_string(), "/allOf/1/minimum".to_string())]);
+        let any = r#"{"anyOf": [{"type": "string"}, {"minimum": 2}]}"#;
+        assert!(valid(any, r#""x""#));
+        assert!(valid(any, "3"));
+        assert_eq!(paths(any, "1"), [("".to_string(), "/anyOf".to_string())]);
+        let one = r#"{"oneOf": [{"type": "number"}, {"type": "integer"}]}"#;
+        assert!(valid(one, "1.5"));
+        assert_eq!(violations(one, "1")[0].message, "value matches 2 schemas in oneOf, expect exactly 1");
+        assert!(!valid(one, r#""x""#));
+        let not = r#"{"not": {"type": "null"}}"#;
+        assert!(valid(not, "0"));
+        assert_eq!(paths(not, "null"), [("".to_string(), "/not".to_string())]);
+    }
+
+    #[test]
+    fn refs() {
+        let schema = r##"{
+            "$defs": {"a/b": {"type": "integer"}, "list": {"type": "array", "items": {"$ref": "#/$defs/a~1b"}}},
+            "properties": {"x": {"$ref": "#/$defs/list"}}
+        }"##;
+        assert!(valid(schema, r#"{"x": [1, 2]}"#));
+        assert_eq!(
+            paths(schema, r#"{"x": [1, 2.5]}"#),
+            [("/x/1".to_string(), "/properties/x/$ref/items/$ref/type".to_string())],
+        );
+        assert!(Schema::compile(&from_str(r##"{"$ref": "#/$defs/missing"}"##).unwrap()).is_err());
+        assert!(Schema::compile(&from_str(r#"{"$ref": "other.json"}"#).unwrap()).is_err());
+    }
+}
@@ -0,0 +1,455 @@
+use super::Result;
+
+// 用于`pattern`关键字的小型正则引擎，支持ECMA-262语法的常用子集
+// - 字符、`.`、转义`\d \D \w \W \s \S`及其他字符的字面转义
+// - 字符类`[abc]` `[^a-z]`
+// - 分组`( )` `(?: )`、选择`|`
+// - 量词`* + ? {n} {n,} {n,m}`，后接`?`为非贪婪
+// - 锚点`^ $`
+//
+// 解析文法:
+// Alt -> Concat ( | Concat )*
+// Concat -> Repeat*
+// Repeat -> Atom Quantifier?
+// Atom -> char | . | \escape | [class] | ( Alt ) | ^ | $
+//
+// 语法树编译为指令序列，匹配时用Pike VM同时推进所有线程，不回溯
+// - 每个位置每条指令最多一个线程，时间为O(输入长度 × 指令数)，空间与输入长度无关
+// - 只判断是否匹配，量词是否贪婪不影响结果
+// JSON Schema中的pattern不隐含锚点，在任意位置匹配即可
+
+#[derive(Debug, Clone)]
+enum Node {
+    Char(char),
+    Any,
+    Class(Vec<(char, char)>, bool),
+    Start,
+    End,
+    Concat(Vec<Node>),
+    Alt(Vec<Node>),
+    Repeat {
+        node: Box<Node>,
+        min: usize,
+        max: Option<usize>,
+    },
+}
+
+#[derive(Debug, Clone)]
+enum Inst {
+    Char(char),
+    Any,
+    Class(Vec<(char, char)>, bool),
+    Start,
+    End,
+    // 同时尝试两个分支
+    Split(usize, usize),
+    Jmp(usize),
+    Match,
+}
+
+// {n,m}按次数展开，限制展开后的指令数
+const MAX_INSTS: usize = 100_000;
+
+#[derive(Debug, Clone)]
+pub struct Regex {
+    insts: Vec<Inst>,
+}
+
+struct RegexParser<'p> {
+    chars: Vec<char>,
+    index: usize,
+    pattern: &'p str,
+}
+
+impl Regex {
+    pub fn new(pattern: &str) -> Result<Regex> {
+        let mut parser = RegexParser {
+            chars: pattern.chars().collect(),
+            index: 0,
+            pattern,
+        };
+        let node = parser.parse_alt()?;
+        if parser.index < parser.chars.len() {
+            return Err(parser.error("unmatched )"));
+        }
+        let mut insts = vec![];
+        compile(&node, &mut insts);
+        if insts.len() > MAX_INSTS {
+            return Err(format!("invalid pattern {:?}: pattern too large", pattern));
+        }
+        insts.push(Inst::Match);
+        Ok(Regex { insts })
+    }
+
+    /// 在`text`的任意位置找到匹配即返回true
+    pub fn is_match(&self, text: &str) -> bool {
+        let input: Vec<char> = text.chars().collect();
+        // 当前位置待推进的线程(只含消耗字符的指令)和消耗字符后到达的指令
+        let mut threads: Vec<usize> = vec![];
+        let mut next: Vec<usize> = vec![];
+        // seen[pc]为pc最近一次加入线程时的位置+1
+        let mut seen = vec![0; self.insts.len()];
+        let mut stack = vec![];
+        for pos in 0..=input.len() {
+            threads.clear();
+            // 每个位置都从头开始一个线程，相当于在开头加上.*?
+            stack.extend(next.drain(..).rev());
+            stack.push(0);
+            while let Some(pc) = stack.pop() {
+                if seen[pc] == pos + 1 {
+                    continue;
+                }
+                seen[pc] = pos + 1;
+                match self.insts[pc] {
+                    Inst::Match => return true,
+                    Inst::Jmp(to) => stack.push(to),
+                    Inst::Split(a, b) => {
+                        stack.push(b);
+                        stack.push(a);
+                    }
+                    Inst::Start if pos == 0 => stack.push(pc + 1),
+                    Inst::End if pos == input.len() => stack.push(pc + 1),
+                    Inst::Start | Inst::End => {}
+                    _ => threads.push(pc),
+                }
+            }
+            let Some(&c) = input.get(pos) else {
+                break;
+            };
+            for &pc in &threads {
+                let matched = match &self.insts[pc] {
+                    Inst::Char(expect) => c == *expect,
+                    Inst::Any => c != '\n' && c != '\r',
+                    Inst::Class(ranges, negated) => match_class(ranges, *negated, c),
+                    _ => false,
+                };
+                if matched {
+                    next.push(pc + 1);
+                }
+            }
+        }
+        false
+    }
+}
+
+impl RegexParser<'_> {
+    fn error(&self, msg: &str) -> String {
+        format!("invalid pattern {:?}: {} at position {}", self.pattern, msg, self.index)
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.index).copied()
+    }
+
+    fn next(&mut self) -> Result<char> {
+        let c = self.peek().ok_or_else(|| self.error("unexpected end of pattern"))?;
+        self.index += 1;
+        Ok(c)
+    }
+
+    fn parse_alt(&mut self) -> Result<Node> {
+        let mut branches = vec![self.parse_concat()?];
+        while self.peek() == Some('|') {
+            self.index += 1;
+            branches.push(self.parse_concat()?);
+        }
+        Ok(if branches.len() == 1 { branches.pop().unwrap() } else { Node::Alt(branches) })
+    }
+
+    fn parse_concat(&mut self) -> Result<Node> {
+        let mut nodes = vec![];
+        while let Some(c) = self.peek() {
+            if c == '|' || c == ')' {
+                break;
+            }
+            let atom = self.parse_atom()?;
+            nodes.push(self.parse_quantifier(atom)?);
+        }
+        Ok(Node::Concat(nodes))
+    }
+
+    fn parse_atom(&mut self) -> Result<Node> {
+        match self.next()? {
+            '.' => Ok(Node::Any),
+            '^' => Ok(Node::Start),
+            '$' => Ok(Node::End),
+            '(' => {
+                if self.peek() == Some('?') {
+                    self.index += 1;
+                    if self.next()? != ':' {
+                        return Err(self.error("only (?: ) groups are supported"));
+                    }
+                }
+                let node = self.parse_alt()?;
+                if self.next()? != ')' {
+                    return Err(self.error("expect )"));
+                }
+                Ok(node)
+            }
+            '[' => self.parse_class(),
+            '\\' => self.parse_escape(),
+            '*' | '+' | '?' => Err(self.error("nothing to repeat")),
+            c => Ok(Node::Char(c)),
+        }
+    }
+
+    fn parse_escape(&mut self) -> Result<Node> {
+        let c = self.next()?;
+        Ok(match escape_class(c) {
+            Some((ranges, negated)) => Node::Class(ranges, negated),
+            None => Node::Char(escape_char(c)),
+        })
+    }
+
+    fn parse_class(&mut self) -> Result<Node> {
+        let mut negated = false;
+        if self.peek() == Some('^') {
+            negated = true;
+            self.index += 1;
+        }
+        let mut ranges = vec![];
+        loop {
+            let c = self.next()?;
+            if c == ']' {
+                break;
+            }
+            let low = if c == '\\' {
+                let e = self.next()?;
+                if let Some((mut class, false)) = escape_class(e) {
+                    ranges.append(&mut class);
+                    continue;
+                }
+                escape_char(e)
+            } else {
+                c
+            };
+            if self.peek() == Some('-') && self.chars.get(self.index + 1).is_some_and(|c| *c != ']') {
+                self.index += 1;
+                let mut high = self.next()?;
+                if high == '\\' {
+                    high = escape_char(self.next()?);
+                }
+                if high < low {
+                    return Err(self.error("range out of order"));
+                }
+                ranges.push((low, high));
+            } else {
+                ranges.push((low, low));
+            }
+        }
+        Ok(Node::Class(ranges, negated))
+    }
+
+    fn parse_quantifier(&mut self, atom: Node) -> Result<Node> {
+        let (min, max) = match self.peek() {
+            Some('*') => (0, None),
+            Some('+') => (1, None),
+            Some('?') => (0, Some(1)),
+            Some('{') => {
+                let start = self.index;
+                self.index += 1;
+                match self.parse_bounds() {
+                    Some(bounds) => {
+                        self.index -= 1;
+                        bounds
+                    }
+                    // 不构成量词的 { 按字面字符处理
+                    None => {
+                        self.index = start;
+                        return Ok(atom);
+                    }
+                }
+            }
+            _ => return Ok(atom),
+        };
+        self.index += 1;
+        // 非贪婪的?，不影响是否匹配
+        if self.peek() == Some('?') {
+            self.index += 1;
+        }
+        Ok(Node::Repeat { node: Box::new(atom), min, max })
+    }
+
+    // {n} {n,} {n,m}，成功时停在 } 之后
+    fn parse_bounds(&mut self) -> Option<(usize, Option<usize>)> {
+        let min = self.parse_int()?;
+        let max = match self.peek()? {
+            '}' => Some(min),
+            ',' => {
+                self.index += 1;
+                if self.peek()? == '}' { None } else { Some(self.parse_int()?) }
+            }
+            _ => return None,
+        };
+        if self.peek()? != '}' || max.is_some_and(|max| max < min) {
+            return None;
+        }
+        self.index += 1;
+        Some((min, max))
+    }
+
+    fn parse_int(&mut self) -> Option<usize> {
+        let start = self.index;
+        while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            self.index += 1;
+        }
+        self.chars[start..self.index].iter().collect::<String>().parse().ok()
+    }
+}
+
+fn escape_class(c: char) -> Option<(Vec<(char, char)>, bool)> {
+    let digit = vec![('0', '9')];
+    let word = vec![('0', '9'), ('A', 'Z'), ('a', 'z'), ('_', '_')];
+    let space = vec![(' ', ' '), ('\t', '\r'), ('\u{a0}', '\u{a0}'), ('\u{2028}', '\u{2029}'), ('\u{feff}', '\u{feff}')];
+    match c {
+        'd' => Some((digit, false)),
+        'D' => Some((digit, true)),
+        'w' => Some((word, false)),
+        'W' => Some((word, true)),
+        's' => Some((space, false)),
+        'S' => Some((space, true)),
+        _ => None,
+    }
+}
+
+fn escape_char(c: char) -> char {
+    match c {
+        'n' => '\n',
+        'r' => '\r',
+        't' => '\t',
+        'f' => '\u{c}',
+        'v' => '\u{b}',
+        '0' => '\0',
+        c => c,
+    }
+}
+
+fn match_class(ranges: &[(char, char)], negated: bool, c: char) -> bool {
+    ranges.iter().any(|(low, high)| *low <= c && c <= *high) != negated
+}
+
+// 将node编译为指令追加到insts，执行完后继续下一条指令
+fn compile(node: &Node, insts: &mut Vec<Inst>) {
+    if insts.len() > MAX_INSTS {
+        return;
+    }
+    match node {
+        Node::Char(c) => insts.push(Inst::Char(*c)),
+        Node::Any => insts.push(Inst::Any),
+        Node::Class(ranges, negated) => insts.push(Inst::Class(ranges.clone(), *negated)),
+        Node::Start => insts.push(Inst::Start),
+        Node::End => insts.push(Inst::End),
+        Node::Concat(nodes) => nodes.iter().for_each(|node| compile(node, insts)),
+        Node::Alt(branches) => {
+            // Split(分支, 下一个Split) ... 最后一个分支，每个分支结束后跳到末尾
+            let mut jumps = vec![];
+            for (i, branch) in branches.iter().enumerate() {
+                if i + 1 < branches.len() {
+                    let split = insts.len();
+                    insts.push(Inst::Split(split + 1, 0));
+                    compile(branch, insts);
+                    jumps.push(insts.len());
+                    insts.push(Inst::Jmp(0));
+                    insts[split] = Inst::Split(split + 1, insts.len());
+                } else {
+                    compile(branch, insts);
+                }
+            }
+            let end = insts.len();
+            for jump in jumps {
+                insts[jump] = Inst::Jmp(end);
+            }
+        }
+        Node::Repeat { node, min, max } => {
+            for _ in 0..*min {
+                compile(node, insts);
+            }
+            match max {
+                // L: Split(L+1, end) node Jmp(L) end:
+                None => {
+                    let split = insts.len();
+                    insts.push(Inst::Split(split + 1, 0));
+                    compile(node, insts);
+                    insts.push(Inst::Jmp(split));
+                    insts[split] = Inst::Split(split + 1, insts.len());
+                }
+                // 每一次可选的重复都可以直接跳到末尾
+                Some(max) => {
+                    let mut splits = vec![];
+                    for _ in *min..*max {
+                        if insts.len() > MAX_INSTS {
+                            break;
+                        }
+                        splits.push(insts.len());
+                        insts.push(Inst::Split(insts.len() + 1, 0));
+                        compile(node, insts);
+                    }
+                    let end = insts.len();
+                    for split in splits {
+                        insts[split] = Inst::Split(split + 1, end);
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn is_match(pattern: &str, text: &str) -> bool {
+        Regex::new(pattern).unwrap().is_match(text)
+    }
+
+    #[test]
+    fn syntax() {
+        assert!(is_match("b", "abc"));
+        assert!(!is_match("^b", "abc"));
+        assert!(is_match("^a.c$", "abc"));
+        assert!(!is_match("^a.c$", "a\nc"));
+        assert!(is_match("^[a-c]+[^a-c]$", "abcd"));
+        assert!(is_match(r"^\d{3}-\w+\s?$", "123-ab_9"));
+        assert!(is_match(r"^[\d.]+$", "1.5"));
+        assert!(is_match("^(?:ab|cd)*$", "abcdab"));
+        assert!(!is_match("^(ab|cd)*$", "abc"));
+        assert!(is_match("^a{2,3}$", "aaa"));
+        assert!(!is_match("^a{2,3}$", "aaaa"));
+        assert!(is_match("^a{2,}$", "aaaaa"));
+        assert!(is_match("^a{2}?b$", "aab"));
+        assert!(is_match("^a+?$", "aaa"));
+        assert!(is_match("^a{,2}$", "a{,2}"));
+        assert!(is_match("", ""));
+        assert!(is_match("^$", ""));
+        assert!(is_match("^é+$", "éé"));
+    }
+
+    #[test]
+    fn invalid_patterns() {
+        assert!(Regex::new("(a").is_err());
+        assert!(Regex::new("a)").is_err());
+        assert!(Regex::new("*a").is_err());
+        assert!(Regex::new("[b-a]").is_err());
+        assert!(Regex::new("(?=a)").is_err());
+        assert!(Regex::new("(a{1000}){1000}").is_err());
+    }
+
+    #[test]
+    fn long_input() {
+        let text = "a".repeat(200_000);
+        assert!(is_match("^[a-z]*$", &text));
+        assert!(!is_match("^[a-z]*$", &(text.clone() + "!")));
+        assert!(is_match(r"^\S+$", &text));
+        assert!(is_match("a$", &text));
+    }
+
+    #[test]
+    fn nested_quantifiers() {
+        let text = "a".repeat(10_000) + "b";
+        assert!(!is_match("^(a+)+$", &text));
+        assert!(!is_match("^(a|aa)*$", &text));
+        assert!(!is_match("^(a*)*$", &text));
+        assert!(is_match("^(a*)*b$", &text));
+        assert!(is_match("^(a?){3}a{3}$", "aaa"));
+    }
+}
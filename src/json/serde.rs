@@ -0,0 +1,927 @@
+use std::borrow::Cow;
+use std::fmt::{Display, Formatter};
+use ::serde::de::{self, DeserializeSeed, EnumAccess, IntoDeserializer, MapAccess, SeqAccess, VariantAccess, Visitor};
+use ::serde::ser::{self, Serialize, SerializeMap, SerializeSeq, SerializeStruct, SerializeStructVariant, SerializeTuple,
+                   SerializeTupleStruct, SerializeTupleVariant};
+use ::serde::{forward_to_deserialize_any, Deserialize};
+use super::{Lexer, Options, TOKEN, TAB, Value, Writable, Writer, unescape, write_canonical_string};
+
+// serde集成，需要开启`serde` feature
+// - Value实现Serialize和Deserialize
+// - Deserializer直接读取Lexer输出的token，from_str::<T>()不构造中间的Value
+// - Serializer先生成字符串已转义的OwnedValue，OwnedValue实现Writable，由Writer以4个空格缩进美化输出，对象保持成员顺序，整数原样输出
+//
+// Value::String借用的是转义后的原文。从本模块的Deserializer读取Value时，以newtype名RAW通知Deserializer
+// 把字符串原文以borrowed bytes的形式交给visitor；其他Deserializer给出的是还原转义后的字符串，
+// 只有不含需要转义的字符时才能直接借用
+
+const RAW: &str = "$json::Value";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Error(String);
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl de::Error for Error {
+    fn custom<T: Display>(msg: T) -> Self {
+        Error(msg.to_string())
+    }
+}
+
+impl ser::Error for Error {
+    fn custom<T: Display>(msg: T) -> Self {
+        Error(msg.to_string())
+    }
+}
+
+impl From<String> for Error {
+    fn from(msg: String) -> Self {
+        Error(msg)
+    }
+}
+
+impl Serialize for Value<'_> {
+    fn serialize<S: ser::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Value::String(s) => serializer.serialize_str(&unescape(s).map_err(ser::Error::custom)?),
+            // 能精确表示的整数按整数输出
+            Value::Number(n) if n.fract() == 0.0 && n.abs() < (1u64 << 53) as f64 => serializer.serialize_i64(*n as i64),
+            Value::Number(n) => serializer.serialize_f64(*n),
+            Value::Bool(b) => serializer.serialize_bool(*b),
+            Value::Null => serializer.serialize_unit(),
+            Value::Array(arr) => serializer.collect_seq(arr),
+            Value::Object(obj) => {
+                let mut map = serializer.serialize_map(Some(obj.len()))?;
+                for (key, value) in obj {
+                    map.serialize_entry(&*unescape(key).map_err(ser::Error::custom)?, value)?;
+                }
+                map.end()
+            }
+        }
+    }
+}
+
+impl<'de: 'a, 'a> Deserialize<'de> for Value<'a> {
+    fn deserialize<D: de::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_newtype_struct(RAW, ValueVisitor)
+    }
+}
+
+// 还原转义后的字符串只有不含需要转义的字符时才与原文相同
+fn borrow_str<E: de::Error>(s: &str) -> Result<&str, E> {
+    if s.bytes().any(|b| b == b'"' || b == b'\\' || b < 0x20) {
+        return Err(E::custom(format!("string {:?} needs escaping and cannot be borrowed into Value", s)));
+    }
+    Ok(s)
+}
+
+fn borrow_raw<E: de::Error>(b: &[u8]) -> Result<&str, E> {
+    std::str::from_utf8(b).map_err(E::custom)
+}
+
+struct ValueVisitor;
+
+impl<'de> Visitor<'de> for ValueVisitor {
+    type Value = Value<'de>;
+
+    fn expecting(&self, f: &mut Formatter) -> std::fmt::Result {
+        write!(f, "any JSON value")
+    }
+
+    fn visit_bool<E: de::Error>(self, v: bool) -> Result<Self::Value, E> {
+        Ok(Value::Bool(v))
+    }
+
+    fn visit_i64<E: de::Error>(self, v: i64) -> Result<Self::Value, E> {
+        Ok(Value::Number(v as f64))
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> Result<Self::Value, E> {
+        Ok(Value::Number(v as f64))
+    }
+
+    fn visit_f64<E: de::Error>(self, v: f64) -> Result<Self::Value, E> {
+        Ok(Value::Number(v))
+    }
+
+    fn visit_borrowed_str<E: de::Error>(self, v: &'de str) -> Result<Self::Value, E> {
+        borrow_str(v).map(Value::String)
+    }
+
+    fn visit_borrowed_bytes<E: de::Error>(self, v: &'de [u8]) -> Result<Self::Value, E> {
+        borrow_raw(v).map(Value::String)
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<Self::Value, E> {
+        Ok(Value::Null)
+    }
+
+    fn visit_none<E: de::Error>(self) -> Result<Self::Value, E> {
+        Ok(Value::Null)
+    }
+
+    fn visit_some<D: de::Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        Deserialize::deserialize(deserializer)
+    }
+
+    fn visit_newtype_struct<D: de::Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_any(self)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut arr = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+        while let Some(value) = seq.next_element()? {
+            arr.push(value);
+        }
+        Ok(Value::Array(arr))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut members = vec![];
+        while let Some(key) = map.next_key_seed(KeySeed)? {
+            members.push((key, map.next_value()?));
+        }
+        Ok(Value::from_members(members))
+    }
+}
+
+struct KeySeed;
+
+impl<'de> DeserializeSeed<'de> for KeySeed {
+    type Value = &'de str;
+
+    fn deserialize<D: de::Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_str(self)
+    }
+}
+
+impl<'de> Visitor<'de> for KeySeed {
+    type Value = &'de str;
+
+    fn expecting(&self, f: &mut Formatter) -> std::fmt::Result {
+        write!(f, "an object key")
+    }
+
+    fn visit_borrowed_str<E: de::Error>(self, v: &'de str) -> Result<Self::Value, E> {
+        borrow_str(v)
+    }
+
+    fn visit_borrowed_bytes<E: de::Error>(self, v: &'de [u8]) -> Result<Self::Value, E> {
+        borrow_raw(v)
+    }
+}
+
+pub struct Deserializer<'de> {
+    lexer: Lexer<'de>,
+    // 正在读取Value，字符串以原文交出
+    raw: bool,
+}
+
+/// 从JSON文本直接反序列化为任意实现了Deserialize的类型
+/// 使用默认的Options，Deserializer每层嵌套递归一次，嵌套深度受max_depth限制
+pub fn from_str<'de, T: Deserialize<'de>>(json_str: &'de str) -> Result<T, Error> {
+    let mut deserializer = Deserializer::new(Options::default().lexer(json_str).map_err(Error)?);
+    let value = T::deserialize(&mut deserializer)?;
+    deserializer.end()?;
+    Ok(value)
+}
+
+impl<'de> Deserializer<'de> {
+    pub fn new(lexer: Lexer<'de>) -> Deserializer<'de> {
+        Deserializer { lexer, raw: false }
+    }
+
+    /// 检查值之后只剩空白
+    pub fn end(&mut self) -> Result<(), Error> {
+        if !self.lexer.at_end()? {
+            return Err(Error(format!("trailing characters at position {}", self.lexer.index())));
+        }
+        Ok(())
+    }
+
+    fn expect(&mut self, expected: &str, is: fn(&TOKEN) -> bool) -> Result<(), Error> {
+        let token = self.lexer.lex()?;
+        if !is(&token) {
+            return Err(Error(format!("expect {} at position {}", expected, self.lexer.token_start())));
+        }
+        Ok(())
+    }
+
+    // 数组或对象中是否还有下一个元素，停在元素之前；宽松模式下允许末尾多余的`,`
+    fn more(&mut self, first: &mut bool, close: &str, is_close: fn(&TOKEN) -> bool) -> Result<bool, Error> {
+        let token = self.lexer.lex()?;
+        if is_close(&token) {
+            self.lexer.push_back();
+            return Ok(false);
+        }
+        if !*first {
+            if !matches!(token, TOKEN::COMMA) {
+                return Err(Error(format!("expect , or {} at position {}", close, self.lexer.token_start())));
+            }
+            if is_close(&self.lexer.lex()?) {
+                if !self.lexer.is_lenient() {
+                    return Err(Error(format!("unexpected {} after , at position {}", close, self.lexer.token_start())));
+                }
+                self.lexer.push_back();
+                return Ok(false);
+            }
+        }
+        *first = false;
+        self.lexer.push_back();
+        Ok(true)
+    }
+
+    fn visit_string<V: Visitor<'de>>(&self, raw: &'de str, visitor: V) -> Result<V::Value, Error> {
+        if self.raw {
+            return visitor.visit_borrowed_bytes(raw.as_bytes());
+        }
+        match unescape(raw)? {
+            Cow::Borrowed(s) => visitor.visit_borrowed_str(s),
+            Cow::Owned(s) => visitor.visit_string(s),
+        }
+    }
+
+    // 整数按原文解析，避免超过2^53时经f64损失精度
+    fn visit_number<V: Visitor<'de>>(&self, n: f64, visitor: V) -> Result<V::Value, Error> {
+        let text = self.lexer.slice(self.lexer.token_start(), self.lexer.index());
+        if let Ok(u) = text.parse::<u64>() {
+            return visitor.visit_u64(u);
+        }
+        if let Ok(i) = text.parse::<i64>() {
+            return visitor.visit_i64(i);
+        }
+        visitor.visit_f64(n)
+    }
+}
+
+impl<'de> de::Deserializer<'de> for &mut Deserializer<'de> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.lexer.lex()? {
+            TOKEN::LBRACE => {
+                let value = visitor.visit_map(Members { de: &mut *self, first: true })?;
+                self.expect("}", |t| matches!(t, TOKEN::RBRACE))?;
+                Ok(value)
+            }
+            TOKEN::LBRACKET => {
+                let value = visitor.visit_seq(Elements { de: &mut *self, first: true })?;
+                self.expect("]", |t| matches!(t, TOKEN::RBRACKET))?;
+                Ok(value)
+            }
+            TOKEN::STRING(s) | TOKEN::IDENT(s) => self.visit_string(s, visitor),
+            TOKEN::NUMBER(n) => self.visit_number(n, visitor),
+            TOKEN::BOOL(b) => visitor.visit_bool(b),
+            TOKEN::NULL => visitor.visit_unit(),
+            _ => Err(Error(format!("unexpected token at position {}", self.lexer.token_start()))),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        if let TOKEN::NULL = self.lexer.lex()? {
+            return visitor.visit_none();
+        }
+        self.lexer.push_back();
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(self, name: &'static str, visitor: V) -> Result<V::Value, Error> {
+        if name != RAW {
+            return visitor.visit_newtype_struct(self);
+        }
+        let raw = std::mem::replace(&mut self.raw, true);
+        let value = visitor.visit_newtype_struct(&mut *self);
+        self.raw = raw;
+        value
+    }
+
+    // 单元变体为字符串，其他变体为只有一个键的对象 {"Variant": value}
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        match self.lexer.lex()? {
+            TOKEN::STRING(s) => visitor.visit_enum(unescape(s)?.into_owned().into_deserializer()),
+            TOKEN::LBRACE => {
+                let value = visitor.visit_enum(Variant { de: &mut *self })?;
+                self.expect("}", |t| matches!(t, TOKEN::RBRACE))?;
+                Ok(value)
+            }
+            _ => Err(Error(format!("expect string or {{ at position {}", self.lexer.token_start()))),
+        }
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct seq tuple tuple_struct map struct identifier ignored_any
+    }
+}
+
+struct Elements<'a, 'de> {
+    de: &'a mut Deserializer<'de>,
+    first: bool,
+}
+
+impl<'de> SeqAccess<'de> for Elements<'_, 'de> {
+    type Error = Error;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(&mut self, seed: T) -> Result<Option<T::Value>, Error> {
+        if !self.de.more(&mut self.first, "]", |t| matches!(t, TOKEN::RBRACKET))? {
+            return Ok(None);
+        }
+        seed.deserialize(&mut *self.de).map(Some)
+    }
+}
+
+struct Members<'a, 'de> {
+    de: &'a mut Deserializer<'de>,
+    first: bool,
+}
+
+impl<'de> MapAccess<'de> for Members<'_, 'de> {
+    type Error = Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>, Error> {
+        if !self.de.more(&mut self.first, "}", |t| matches!(t, TOKEN::RBRACE))? {
+            return Ok(None);
+        }
+        if !matches!(self.de.lexer.lex()?, TOKEN::STRING(_) | TOKEN::IDENT(_)) {
+            return Err(Error(format!("expect string at position {}", self.de.lexer.token_start())));
+        }
+        self.de.lexer.push_back();
+        seed.deserialize(&mut *self.de).map(Some)
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, Error> {
+        self.de.expect(":", |t| matches!(t, TOKEN::COLON))?;
+        seed.deserialize(&mut *self.de)
+    }
+}
+
+struct Variant<'a, 'de> {
+    de: &'a mut Deserializer<'de>,
+}
+
+impl<'de> EnumAccess<'de> for Variant<'_, 'de> {
+    type Error = Error;
+    type Variant = Self;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(self, seed: V) -> Result<(V::Value, Self), Error> {
+        let variant = seed.deserialize(&mut *self.de)?;
+        self.de.expect(":", |t| matches!(t, TOKEN::COLON))?;
+        Ok((variant, self))
+    }
+}
+
+impl<'de> VariantAccess<'de> for Variant<'_, 'de> {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<(), Error> {
+        Deserialize::deserialize(self.de)
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value, Error> {
+        seed.deserialize(self.de)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value, Error> {
+        de::Deserializer::deserialize_seq(self.de, visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(self, _fields: &'static [&'static str], visitor: V) -> Result<V::Value, Error> {
+        de::Deserializer::deserialize_map(self.de, visitor)
+    }
+}
+
+/// 序列化的结果，字符串保存转义后的内容，对象保留成员的顺序
+#[derive(Debug, Clone, PartialEq)]
+pub enum OwnedValue {
+    String(String),
+    // i64和u64不经过f64，保证输出精确
+    Integer(i128),
+    Number(f64),
+    Bool(bool),
+    Null,
+    Object(Vec<(String, OwnedValue)>),
+    Array(Vec<OwnedValue>),
+}
+
+impl OwnedValue {
+    pub fn as_value(&self) -> Value<'_> {
+        match self {
+            OwnedValue::String(s) => Value::String(s),
+            OwnedValue::Integer(n) => Value::Number(*n as f64),
+            OwnedValue::Number(n) => Value::Number(*n),
+            OwnedValue::Bool(b) => Value::Bool(*b),
+            OwnedValue::Null => Value::Null,
+            OwnedValue::Object(members) => Value::from_members(members.iter().map(|(k, v)| (k.as_str(), v.as_value())).collect()),
+            OwnedValue::Array(arr) => Value::Array(arr.iter().map(OwnedValue::as_value).collect()),
+        }
+    }
+}
+
+// 对象按成员的写入顺序输出
+impl Writable for OwnedValue {
+    fn write_to(&self, writer: &Writer, depth: usize, out: &mut String) -> Result<(), String> {
+        match self {
+            OwnedValue::String(s) => writer.write_string(s, out)?,
+            OwnedValue::Integer(n) => out.push_str(&n.to_string()),
+            OwnedValue::Number(n) => writer.write_number(*n, out)?,
+            OwnedValue::Bool(b) => out.push_str(if *b { "true" } else { "false" }),
+            OwnedValue::Null => out.push_str("null"),
+            OwnedValue::Array(arr) => writer.write_array(arr, depth, out)?,
+            OwnedValue::Object(members) => {
+                let members = members.iter().map(|(k, v)| Ok((unescape(k)?, v))).collect::<Result<Vec<_>, String>>()?;
+                writer.write_object(members, depth, out)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+// 与Value的Display相同使用4个空格缩进
+impl Display for OwnedValue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&Writer::pretty(TAB).write(self).map_err(|_| std::fmt::Error)?)
+    }
+}
+
+/// 序列化为美化后的JSON文本
+pub fn to_string<T: Serialize + ?Sized>(value: &T) -> Result<String, Error> {
+    Writer::pretty(TAB).write(&value.serialize(Serializer)?).map_err(Error)
+}
+
+fn escape(s: &str) -> String {
+    let mut out = String::new();
+    write_canonical_string(s, &mut out);
+    out[1..out.len() - 1].to_string()
+}
+
+fn wrap(variant: Option<&'static str>, value: OwnedValue) -> OwnedValue {
+    match variant {
+        Some(variant) => OwnedValue::Object(vec![(escape(variant), value)]),
+        None => value,
+    }
+}
+
+pub struct Serializer;
+
+impl ser::Serializer for Serializer {
+    type Ok = OwnedValue;
+    type Error = Error;
+    type SerializeSeq = Items;
+    type SerializeTuple = Items;
+    type SerializeTupleStruct = Items;
+    type SerializeTupleVariant = Items;
+    type SerializeMap = Fields;
+    type SerializeStruct = Fields;
+    type SerializeStructVariant = Fields;
+
+    fn serialize_bool(self, v: bool) -> Result<OwnedValue, Error> {
+        Ok(OwnedValue::Bool(v))
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<OwnedValue, Error> {
+        Ok(OwnedValue::Integer(v as i128))
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<OwnedValue, Error> {
+        Ok(OwnedValue::Integer(v as i128))
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<OwnedValue, Error> {
+        Ok(OwnedValue::Integer(v as i128))
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<OwnedValue, Error> {
+        Ok(OwnedValue::Integer(v as i128))
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<OwnedValue, Error> {
+        Ok(OwnedValue::Integer(v as i128))
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<OwnedValue, Error> {
+        Ok(OwnedValue::Integer(v as i128))
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<OwnedValue, Error> {
+        Ok(OwnedValue::Integer(v as i128))
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<OwnedValue, Error> {
+        Ok(OwnedValue::Integer(v as i128))
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<OwnedValue, Error> {
+        self.serialize_f64(v as f64)
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<OwnedValue, Error> {
+        if !v.is_finite() {
+            return Err(Error(format!("cannot serialize {}", v)));
+        }
+        Ok(OwnedValue::Number(v))
+    }
+
+    fn serialize_char(self, v: char) -> Result<OwnedValue, Error> {
+        self.serialize_str(v.encode_utf8(&mut [0; 4]))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<OwnedValue, Error> {
+        Ok(OwnedValue::String(escape(v)))
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<OwnedValue, Error> {
+        Ok(OwnedValue::Array(v.iter().map(|b| OwnedValue::Integer(*b as i128)).collect()))
+    }
+
+    fn serialize_none(self) -> Result<OwnedValue, Error> {
+        Ok(OwnedValue::Null)
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<OwnedValue, Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<OwnedValue, Error> {
+        Ok(OwnedValue::Null)
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<OwnedValue, Error> {
+        Ok(OwnedValue::Null)
+    }
+
+    fn serialize_unit_variant(self, _name: &'static str, _index: u32, variant: &'static str) -> Result<OwnedValue, Error> {
+        self.serialize_str(variant)
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(self, _name: &'static str, value: &T) -> Result<OwnedValue, Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<OwnedValue, Error> {
+        Ok(wrap(Some(variant), value.serialize(Serializer)?))
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<Items, Error> {
+        Ok(Items { variant: None, items: Vec::with_capacity(len.unwrap_or(0)) })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<Items, Error> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(self, _name: &'static str, len: usize) -> Result<Items, Error> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Items, Error> {
+        Ok(Items { variant: Some(variant), items: Vec::with_capacity(len) })
+    }
+
+    fn serialize_map(self, len: Option<usize>) -> Result<Fields, Error> {
+        Ok(Fields { variant: None, members: Vec::with_capacity(len.unwrap_or(0)), key: None })
+    }
+
+    fn serialize_struct(self, _name: &'static str, len: usize) -> Result<Fields, Error> {
+        self.serialize_map(Some(len))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Fields, Error> {
+        Ok(Fields { variant: Some(variant), members: Vec::with_capacity(len), key: None })
+    }
+}
+
+pub struct Items {
+    variant: Option<&'static str>,
+    items: Vec<OwnedValue>,
+}
+
+impl SerializeSeq for Items {
+    type Ok = OwnedValue;
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.items.push(value.serialize(Serializer)?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<OwnedValue, Error> {
+        Ok(wrap(self.variant, OwnedValue::Array(self.items)))
+    }
+}
+
+impl SerializeTuple for Items {
+    type Ok = OwnedValue;
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<OwnedValue, Error> {
+        SerializeSeq::end(self)
+    }
+}
+
+impl SerializeTupleStruct for Items {
+    type Ok = OwnedValue;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<OwnedValue, Error> {
+        SerializeSeq::end(self)
+    }
+}
+
+impl SerializeTupleVariant for Items {
+    type Ok = OwnedValue;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<OwnedValue, Error> {
+        SerializeSeq::end(self)
+    }
+}
+
+pub struct Fields {
+    variant: Option<&'static str>,
+    members: Vec<(String, OwnedValue)>,
+    key: Option<String>,
+}
+
+impl SerializeMap for Fields {
+    type Ok = OwnedValue;
+    type Error = Error;
+
+    // 数字和布尔值作为键时转为字符串
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), Error> {
+        self.key = Some(match key.serialize(Serializer)? {
+            OwnedValue::String(s) => s,
+            OwnedValue::Integer(n) => n.to_string(),
+            OwnedValue::Number(n) => n.to_string(),
+            OwnedValue::Bool(b) => b.to_string(),
+            _ => return Err(Error("object key must be a string".to_string())),
+        });
+        Ok(())
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        let key = self.key.take().ok_or_else(|| Error("serialize_value called before serialize_key".to_string()))?;
+        self.members.push((key, value.serialize(Serializer)?));
+        Ok(())
+    }
+
+    fn end(self) -> Result<OwnedValue, Error> {
+        Ok(wrap(self.variant, OwnedValue::Object(self.members)))
+    }
+}
+
+impl SerializeStruct for Fields {
+    type Ok = OwnedValue;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, key: &'static str, value: &T) -> Result<(), Error> {
+        self.members.push((escape(key), value.serialize(Serializer)?));
+        Ok(())
+    }
+
+    fn end(self) -> Result<OwnedValue, Error> {
+        SerializeMap::end(self)
+    }
+}
+
+impl SerializeStructVariant for Fields {
+    type Ok = OwnedValue;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, key: &'static str, value: &T) -> Result<(), Error> {
+        SerializeStruct::serialize_field(self, key, value)
+    }
+
+    fn end(self) -> Result<OwnedValue, Error> {
+        SerializeMap::end(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use super::*;
+
+    struct Record {
+        zeta: u64,
+        alpha: i64,
+        name: &'static str,
+        items: Vec<f64>,
+    }
+
+    impl Serialize for Record {
+        fn serialize<S: ser::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            let mut s = serializer.serialize_struct("Record", 4)?;
+            s.serialize_field("zeta", &self.zeta)?;
+            s.serialize_field("alpha", &self.alpha)?;
+            s.serialize_field("name", &self.name)?;
+            s.serialize_field("items", &self.items)?;
+            s.end()
+        }
+    }
+
+    #[test]
+    fn struct_fields_keep_order() {
+        let record = Record { zeta: 1, alpha: -2, name: "a\"b", items: vec![0.5, 2.0] };
+        let text = to_string(&record).unwrap();
+        assert_eq!(text, "{\n    \"zeta\": 1,\n    \"alpha\": -2,\n    \"name\": \"a\\\"b\",\n    \"items\": [\n        0.5,\n        2\n    ]\n}");
+        for _ in 0..10 {
+            assert_eq!(to_string(&record).unwrap(), text);
+        }
+    }
+
+    #[test]
+    fn exact_integers() {
+        assert_eq!(to_string(&u64::MAX).unwrap(), "18446744073709551615");
+        assert_eq!(to_string(&i64::MIN).unwrap(), "-9223372036854775808");
+        assert_eq!(to_string(&((1u64 << 53) + 1)).unwrap(), "9007199254740993");
+        let map: HashMap<u64, bool> = [(u64::MAX, true)].into_iter().collect();
+        assert_eq!(to_string(&map).unwrap(), "{\n    \"18446744073709551615\": true\n}");
+        assert!(to_string(&f64::NAN).is_err());
+    }
+
+    #[test]
+    fn empty_and_nested() {
+        assert_eq!(to_string(&Vec::<u8>::new()).unwrap(), "[]");
+        assert_eq!(to_string(&HashMap::<String, u8>::new()).unwrap(), "{}");
+        assert_eq!(to_string(&vec![vec![1u8]]).unwrap(), "[\n    [\n        1\n    ]\n]");
+        assert_eq!(to_string(&Some("x")).unwrap(), "\"x\"");
+    }
+
+    #[test]
+    fn round_trip_value() {
+        let value: Value = from_str(r#"{"a": [1, "x\ny", null, true]}"#).unwrap();
+        let text = to_string(&value).unwrap();
+        assert_eq!(from_str::<Value>(&text).unwrap(), value);
+        // 与各个parser相同，重复的键取最后一个
+        let value: Value = from_str(r#"{"b": 1, "\u0062": 2}"#).unwrap();
+        assert_eq!(value.get("b"), Some(&Value::Number(2.0)));
+    }
+
+    #[test]
+    fn deep_input() {
+        let deep = "[".repeat(100_000);
+        assert!(from_str::<de::IgnoredAny>(&deep).is_err());
+        let nested = format!("{}{}", "[".repeat(100), "]".repeat(100));
+        assert!(from_str::<de::IgnoredAny>(&nested).is_ok());
+    }
+
+    #[derive(Debug, PartialEq)]
+    struct Point {
+        x: i64,
+        label: Option<String>,
+    }
+
+    impl<'de> Deserialize<'de> for Point {
+        fn deserialize<D: de::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            struct PointVisitor;
+
+            impl<'de> Visitor<'de> for PointVisitor {
+                type Value = Point;
+
+                fn expecting(&self, f: &mut Formatter) -> std::fmt::Result {
+                    write!(f, "struct Point")
+                }
+
+                fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Point, A::Error> {
+                    let (mut x, mut label) = (None, None);
+                    while let Some(key) = map.next_key::<String>()? {
+                        match key.as_str() {
+                            "x" => x = Some(map.next_value()?),
+                            "label" => label = map.next_value()?,
+                            _ => map.next_value::<de::IgnoredAny>().map(|_| ())?,
+                        }
+                    }
+                    Ok(Point { x: x.ok_or_else(|| de::Error::missing_field("x"))?, label })
+                }
+            }
+
+            deserializer.deserialize_struct("Point", &["x", "label"], PointVisitor)
+        }
+    }
+
+    #[derive(Debug, PartialEq)]
+    enum Shape {
+        Empty,
+        Line(Vec<u8>),
+    }
+
+    impl<'de> Deserialize<'de> for Shape {
+        fn deserialize<D: de::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            struct ShapeVisitor;
+
+            impl<'de> Visitor<'de> for ShapeVisitor {
+                type Value = Shape;
+
+                fn expecting(&self, f: &mut Formatter) -> std::fmt::Result {
+                    write!(f, "enum Shape")
+                }
+
+                fn visit_enum<A: EnumAccess<'de>>(self, data: A) -> Result<Shape, A::Error> {
+                    let (name, variant): (String, _) = data.variant()?;
+                    match name.as_str() {
+                        "Empty" => variant.unit_variant().map(|_| Shape::Empty),
+                        "Line" => variant.newtype_variant().map(Shape::Line),
+                        _ => Err(de::Error::unknown_variant(&name, &["Empty", "Line"])),
+                    }
+                }
+            }
+
+            deserializer.deserialize_enum("Shape", &["Empty", "Line"], ShapeVisitor)
+        }
+    }
+
+    fn lenient<'de, T: Deserialize<'de>>(s: &'de str) -> Result<T, Error> {
+        let options = Options { lenient: true, ..Options::default() };
+        let mut deserializer = Deserializer::new(options.lexer(s).map_err(Error)?);
+        let value = T::deserialize(&mut deserializer)?;
+        deserializer.end()?;
+        Ok(value)
+    }
+
+    #[test]
+    fn deserialize_structs() {
+        let point: Point = from_str(r#"{"label": "a\u0062", "skip": [1, {}], "x": -3}"#).unwrap();
+        assert_eq!(point, Point { x: -3, label: Some("ab".to_string()) });
+        assert_eq!(from_str::<Point>(r#"{"x": 9007199254740993, "label": null}"#).unwrap(), Point { x: 9007199254740993, label: None });
+        assert!(from_str::<Point>(r#"{"label": "a"}"#).is_err());
+        assert!(from_str::<Point>(r#"{"x": "1"}"#).is_err());
+        assert!(from_str::<Point>("[1]").is_err());
+    }
+
+    #[test]
+    fn deserialize_enums() {
+        assert_eq!(from_str::<Shape>(r#""Empty""#).unwrap(), Shape::Empty);
+        assert_eq!(from_str::<Shape>(r#"{"Line": [1, 2]}"#).unwrap(), Shape::Line(vec![1, 2]));
+        assert_eq!(from_str::<Vec<Shape>>(r#"["Empty", {"Line": []}]"#).unwrap(), [Shape::Empty, Shape::Line(vec![])]);
+        assert!(from_str::<Shape>(r#""Circle""#).is_err());
+        assert!(from_str::<Shape>(r#"{"Line": [1], "Empty": null}"#).is_err());
+        assert!(from_str::<Shape>("1").is_err());
+    }
+
+    #[test]
+    fn deserialize_options() {
+        assert_eq!(from_str::<Option<u8>>("null").unwrap(), None);
+        assert_eq!(from_str::<Option<u8>>(" 7 ").unwrap(), Some(7));
+        assert_eq!(from_str::<Vec<Option<bool>>>("[true, null]").unwrap(), [Some(true), None]);
+        assert!(from_str::<Option<u8>>("300").is_err());
+    }
+
+    #[test]
+    fn trailing_input() {
+        assert!(from_str::<u8>("1 2").is_err());
+        assert!(from_str::<Vec<u8>>("[1] x").is_err());
+        assert!(from_str::<Point>(r#"{"x": 1}}"#).is_err());
+        assert_eq!(from_str::<Vec<u8>>("[1]\n").unwrap(), [1]);
+    }
+
+    #[test]
+    fn trailing_commas() {
+        assert!(from_str::<Vec<u8>>("[1,]").is_err());
+        assert!(from_str::<Point>(r#"{"x": 1,}"#).is_err());
+        assert!(from_str::<Vec<u8>>("[,]").is_err());
+        assert_eq!(lenient::<Vec<u8>>("[1, 2,]").unwrap(), [1, 2]);
+        assert_eq!(lenient::<Point>("{x: 1, label: 'y',}").unwrap(), Point { x: 1, label: Some("y".to_string()) });
+        assert!(lenient::<Vec<u8>>("[1,,]").is_err());
+    }
+}
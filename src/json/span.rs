@@ -0,0 +1,47 @@
+use std::collections::HashMap;
+
+/// 源文本中`[start, end)`的字节范围
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    /// 起始位置所在的行号和列号，均从1开始，列号按字符计
+    pub fn line_col(&self, src: &str) -> (usize, usize) {
+        let before = &src[..self.start];
+        let line = before.matches('\n').count() + 1;
+        let col = before.rsplit('\n').next().unwrap().chars().count() + 1;
+        (line, col)
+    }
+}
+
+/// 解析时记录的位置表，以JSON Pointer(RFC 6901)为键
+/// - `values`记录每个值的范围，根节点的键为""
+/// - `keys`记录对象中每个键(含引号)的范围，键为该成员的值的JSON Pointer
+#[derive(Debug, Default)]
+pub struct SpanTable {
+    pub values: HashMap<String, Span>,
+    pub keys: HashMap<String, Span>,
+}
+
+impl SpanTable {
+    pub fn value(&self, pointer: &str) -> Option<Span> {
+        self.values.get(pointer).copied()
+    }
+
+    pub fn key(&self, pointer: &str) -> Option<Span> {
+        self.keys.get(pointer).copied()
+    }
+}
+
+/// 由路径中的各个键或下标拼出JSON Pointer
+pub fn pointer<S: AsRef<str>>(path: &[S]) -> String {
+    let mut out = String::new();
+    for token in path {
+        out.push('/');
+        out.push_str(&token.as_ref().replace('~', "~0").replace('/', "~1"));
+    }
+    out
+}
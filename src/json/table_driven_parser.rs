@@ -1,210 +1,208 @@
-use std::collections::HashMap;
+use std::sync::OnceLock;
 use super::{TOKEN, Value, Result, Lexer};
+use super::grammar::{Grammar, PredictTable, Symbol};
+use super::parser::Parser;
+use super::trace::{input, Step, StepKind, Tracer};
 
-// TOKEN: [ { } ] , : string number bool null
-// Value -> [ Array' | { Object' | string | number | bool | null
-// Array' -> ] | Value ValueList ]
-// ValueList -> , Value ValueList | ε
-// Object' -> } | Pair PairList }
-// PairList -> , Pair PairList | ε
-// Pair -> string : Value
-mod SYMBOLS {
-    pub const Value: u8 = 0;
-    pub const Array1: u8 = 1;
-    pub const ValueList: u8 = 2;
-    pub const Object1: u8 = 3;
-    pub const PairList: u8 = 4;
-    pub const Pair: u8 = 5;
+// 预测分析表由README中DefiniteParser一节的LL1文法生成，见grammar模块
+// 产生式按文法中的顺序编号，push_value依赖这些编号:
+// Value(0-5)
+// - Value -> [ Array' | { Object' | string | number | bool | null
+// Array'(6-7)
+// - Array' -> ] | Value ValueList ]
+// ValueList(8-9)
+// - ValueList -> , Value ValueList | ε
+// Object'(10-11)
+// - Object' -> } | Pair PairList }
+// PairList(12-13)
+// - PairList -> , Pair PairList | ε
+// Pair(14)
+// - Pair -> string : Value
 
-    pub const LBRACE: u8 = 6;
-    pub const RBRACE: u8 = 7;
-    pub const LBRACKET: u8 = 8;
-    pub const RBRACKET: u8 = 9;
-    pub const COMMA: u8 = 10;
-    pub const COLON: u8 = 11;
-    pub const STRING: u8 = 12;
-    pub const NUMBER: u8 = 13;
-    pub const BOOL: u8 = 14;
-    pub const NULL: u8 = 15;
-    pub const EPSILON: u8 = 16;
+// README中以`TOKEN:`开头的代码块
+fn json_grammar() -> &'static str {
+    let readme = include_str!("../../README.md");
+    let start = readme.find("TOKEN:").expect("JSON grammar not found in README");
+    let end = start + readme[start..].find("```").unwrap();
+    &readme[start..end]
+}
 
-    pub const STRING_TABLE: [&str; 17] = [
-        "Value",
-        "Array1",
-        "ValueList",
-        "Object1",
-        "PairList",
-        "Pair",
-        "{",
-        "}",
-        "[",
-        "]",
-        ",",
-        ":",
-        "string",
-        "number",
-        "bool",
-        "null",
-        "epsilon",
-    ];
+// 由文法生成的分析表，第一次使用时构造
+struct Tables {
+    grammar: Grammar,
+    table: PredictTable,
+    // 各个TOKEN对应的终结符，下标由token_index给出
+    terminals: [Symbol; 11],
+    value: Symbol,
+    value_list: Symbol,
+    pair: Symbol,
+    pair_list: Symbol,
 }
-// TABLE[NONTERMINAL][TERMINAL] = index of PRODUCTION
-// COL:     Value Array1 ValueList Object1 PairList Pair
-// ROW:     LBRACE  RBRACE LBRACKET RBRACKET COMMA COLON  STRING NUMBER BOOL NULL EPSILON
 
-/// Value(0-5)
-/// - SELECT(Value -> [ Array') = [
-/// - SELECT(Value -> { Object') = {
-/// - SELECT(Value -> string) = string
-/// - SELECT(Value -> number) = number
-/// - SELECT(Value -> bool) = bool
-/// - SELECT(Value -> null) = null
-///
-/// Array'(6-7)
-/// - SELECT(Array' -> ]) = ]
-/// - SELECT(Array' -> Value ValueList]) = string number bool null [ {
-///
-/// ValueList(8-9)
-/// - SELECT(ValueList -> , Value ValueList) = ,
-/// - SELECT(ValueList -> ε) = \emptyset and FOLLOW(ValueList) = ]
-///
-/// Object'(10-11)
-/// - SELECT(Object' -> }) = }
-/// - SELECT(Object' -> Pair PairList}) = string
-///
-/// PairList(12-13)
-/// - SELECT(PairList -> , Pair PairList) = ,
-/// - SELECT(PairList -> ε) = \emptyset and FOLLOW(PairList) = }
-///
-/// Pair(14)
-/// - SELECT(Pair -> string : Value) = string
-///
-/// predict table
-///
-/// | noterminal | LBRACE | RBRACE| LBRACKET |RBRACKET| COMMA |COLON | STRING| NUMBER| BOOL| NULL |EPSILON|
-/// | ---------- |--------|-------|----------|--------|-------|------|-------|-------|-----|------|-------|
-/// | Value      |   1    |   -1  |    0     |   -1   |   -1  |  -1  |   2   |   3   |  4  |   5  |   -1  |
-/// | Array1     |   7    |   -1  |    7     |    6   |   -1  |  -1  |   7   |   7   |  7  |   7  |   -1  |
-/// | ValueList  |  -1    |   -1  |   -1     |    9   |    8  |  -1  |  -1   |  -1   | -1  |  -1  |   -1  |
-/// | Object1    |  -1    |   10  |   -1     |   -1   |   -1  |  -1  |  11   |  -1   | -1  |  -1  |   -1  |
-/// | PairList   |  -1    |   13  |   -1     |   -1   |   12  |  -1  |  -1   |  -1   | -1  |  -1  |   -1  |
-/// | Pair       |  -1    |   -1  |   -1     |   -1   |   -1  |  -1  |  14   |  -1   | -1  |  -1  |   -1  |
-const TABLE: [[i8; 10]; 6] = [
-//                LBRACE  RBRACE LBRACKET RBRACKET COMMA COLON  STRING NUMBER BOOL NULL EPSILON
-    /*   Value */    [1, -1, 0, -1, -1, -1, 2, 3, 4, 5],
-    /* Array1  */    [7, -1, 7, 6, -1, -1, 7, 7, 7, 7],
-    /*ValueList*/    [-1, -1, -1, 9, 8, -1, -1, -1, -1, -1],
-    /*Object1  */    [-1, 10, -1, -1, -1, -1, 11, -1, -1, -1],
-    /*PairList */    [-1, 13, -1, -1, 12, -1, -1, -1, -1, -1],
-    /*  Pair  */     [-1, -1, -1, -1, -1, -1, 14, -1, -1, -1],
-];
+static TABLES: OnceLock<Tables> = OnceLock::new();
 
-pub struct TableDrivenParser<'s> {
-    lexer: Lexer<'s>,
-    symbol_stack: Vec<u8>,
-    value_stack: Vec<Value<'s>>,
-    rules_stack: Vec<i8>,
-    PRODUCTION: [Vec<u8>; 15],
+fn tables() -> &'static Tables {
+    TABLES.get_or_init(|| {
+        let grammar = Grammar::parse(json_grammar()).unwrap();
+        let table = match grammar.predict_table() {
+            Ok(table) => table,
+            Err(conflicts) => panic!("{}", conflicts[0]),
+        };
+        let symbol = |name: &str| grammar.symbol(name).unwrap();
+        let terminals = ["{", "}", "[", "]", ",", ":", "string", "number", "bool", "null", "#"].map(symbol);
+        let (value, value_list, pair, pair_list) = (symbol("Value"), symbol("ValueList"), symbol("Pair"), symbol("PairList"));
+        Tables { grammar, table, terminals, value, value_list, pair, pair_list }
+    })
 }
 
-fn token2symbol(token: &TOKEN) -> u8 {
+fn token_index(token: &TOKEN) -> usize {
     match token {
-        TOKEN::LBRACE => SYMBOLS::LBRACE,
-        TOKEN::RBRACE => SYMBOLS::RBRACE,
-        TOKEN::LBRACKET => SYMBOLS::LBRACKET,
-        TOKEN::RBRACKET => SYMBOLS::RBRACKET,
-        TOKEN::COMMA => SYMBOLS::COMMA,
-        TOKEN::COLON => SYMBOLS::COLON,
-        TOKEN::STRING(_) => SYMBOLS::STRING,
-        TOKEN::NUMBER(_) => SYMBOLS::NUMBER,
-        TOKEN::BOOL(_) => SYMBOLS::BOOL,
-        TOKEN::NULL => SYMBOLS::NULL,
+        TOKEN::LBRACE => 0,
+        TOKEN::RBRACE => 1,
+        TOKEN::LBRACKET => 2,
+        TOKEN::RBRACKET => 3,
+        TOKEN::COMMA => 4,
+        TOKEN::COLON => 5,
+        TOKEN::STRING(_) | TOKEN::IDENT(_) => 6,
+        TOKEN::NUMBER(_) => 7,
+        TOKEN::BOOL(_) => 8,
+        TOKEN::NULL => 9,
+        TOKEN::EOF => 10,
     }
 }
 
-fn is_terminal(symbol: u8) -> bool {
-    return symbol >= 6 && symbol <= 16;
+pub struct TableDrivenParser<'s> {
+    lexer: Lexer<'s>,
+    tables: &'static Tables,
+    symbol_stack: Vec<Symbol>,
+    value_stack: Vec<Value<'s>>,
+    rules_stack: Vec<usize>,
+    tracer: Option<Tracer>,
 }
 
 impl<'s> TableDrivenParser<'s> {
     pub fn new(lexer: Lexer) -> TableDrivenParser {
+        let tables = tables();
         TableDrivenParser {
             lexer,
-            symbol_stack: vec![SYMBOLS::Value],
+            tables,
+            // 栈底为#
+            symbol_stack: vec![tables.grammar.eof(), tables.grammar.start()],
             value_stack: vec![],
             rules_stack: vec![],
-            PRODUCTION: [
-                vec![SYMBOLS::Value, SYMBOLS::LBRACKET, SYMBOLS::Array1],
-                vec![SYMBOLS::Value, SYMBOLS::LBRACE, SYMBOLS::Object1],
-                vec![SYMBOLS::Value, SYMBOLS::STRING],
-                vec![SYMBOLS::Value, SYMBOLS::NUMBER],
-                vec![SYMBOLS::Value, SYMBOLS::BOOL],
-                vec![SYMBOLS::Value, SYMBOLS::NULL],
-                vec![SYMBOLS::Array1, SYMBOLS::RBRACKET],
-                vec![SYMBOLS::Array1, SYMBOLS::Value, SYMBOLS::ValueList, SYMBOLS::RBRACKET],
-                vec![SYMBOLS::ValueList, SYMBOLS::COMMA, SYMBOLS::Value, SYMBOLS::ValueList],
-                vec![SYMBOLS::ValueList, SYMBOLS::EPSILON],
-                vec![SYMBOLS::Object1, SYMBOLS::RBRACE],
-                vec![SYMBOLS::Object1, SYMBOLS::Pair, SYMBOLS::PairList, SYMBOLS::RBRACE],
-                vec![SYMBOLS::PairList, SYMBOLS::COMMA, SYMBOLS::Pair, SYMBOLS::PairList],
-                vec![SYMBOLS::PairList, SYMBOLS::EPSILON],
-                vec![SYMBOLS::Pair, SYMBOLS::STRING, SYMBOLS::COLON, SYMBOLS::Value],
-            ],
+            tracer: None,
         }
     }
+
+    /// 记录每一步的符号栈、剩余输入和应用的产生式
+    pub fn with_tracer(self, tracer: Tracer) -> TableDrivenParser<'s> {
+        TableDrivenParser { tracer: Some(tracer), ..self }
+    }
+
+    pub fn take_tracer(&mut self) -> Option<Tracer> {
+        self.tracer.take()
+    }
+
+    /// 生成分析表所用的文法
+    pub fn grammar() -> &'static Grammar {
+        &tables().grammar
+    }
+
     pub fn parse(&mut self) -> Result<Value<'s>> {
-        while let Ok(token) = self.lexer.lex() {
-            let symbol = token2symbol(&token);
-            while let Some(expected) = self.symbol_stack.pop() {
-                if is_terminal(expected) {
-                    if expected == symbol {
-                        self.push_value(token);
-                        break;
-                    } else if expected == SYMBOLS::EPSILON {
-                        continue;
-                    } else {
-                        let msg = format!(
-                            "expect {} at position: {}, found {}",
-                            SYMBOLS::STRING_TABLE[expected as usize],
-                            self.lexer.index(), SYMBOLS::STRING_TABLE[symbol as usize]
-                        );
-                        return Err(msg);
-                    }
-                } else {
-                    let index = TABLE[expected as usize][(symbol - 6) as usize];
-                    if index == -1 {
-                        let mut valid_symbols: Vec<&str> = vec![];
-                        for i in 0..10 {
-                            if TABLE[expected as usize][i] != -1 {
-                                valid_symbols.push(SYMBOLS::STRING_TABLE[6 + i]);
-                            }
-                        }
-                        let msg = format!(
-                            "expect {} at position: {}, found {}",
-                            valid_symbols.join(" | "),
-                            self.lexer.index(),
-                            SYMBOLS::STRING_TABLE[6 + symbol as usize],
-                        );
-                        return Err(msg);
-                    } else {
-                        let production = &self.PRODUCTION[index as usize];
-                        for i in (1..production.len()).rev() {
-                            self.symbol_stack.push(production[i]);
-                        }
-                        self.rules_stack.push(index);
-                    }
+        let grammar: &'static Grammar = &self.tables.grammar;
+        let mut token = self.lexer.lex_with_eof()?;
+        loop {
+            let symbol = self.tables.terminals[token_index(&token)];
+            // #在栈底，匹配之前栈不会为空
+            let expected = self.symbol_stack.pop().unwrap();
+            if grammar.is_terminal(expected) {
+                if expected != symbol {
+                    return Err(self.error(self.name(expected), symbol));
                 }
+                // 栈顶为#，输入符号也为#，分析完成
+                if symbol == grammar.eof() {
+                    self.trace(expected, || StepKind::Accept);
+                    break;
+                }
+                let name = self.name(symbol);
+                self.trace(expected, || StepKind::Match(name));
+                self.push_value(token)?;
+                token = self.lexer.lex_with_eof()?;
+            } else if !self.trailing_comma(expected, &token) {
+                let index = match self.tables.table.get(expected, symbol) {
+                    Some(index) => index,
+                    None => {
+                        let valid_symbols: Vec<&str> = self.tables.table.expected(expected)
+                            .into_iter()
+                            .map(|s| self.name(s))
+                            .collect();
+                        return Err(self.error(&valid_symbols.join(" | "), symbol));
+                    }
+                };
+                self.trace(expected, || StepKind::Predict(index, grammar.production_string(index)));
+                // 右部逆序压栈，ε不压栈
+                self.symbol_stack.extend(grammar.productions()[index].body.iter().rev());
+                self.rules_stack.push(index);
             }
         }
-        if (self.value_stack.len() != 1) {
-            panic!("---")
+        // 分析完成时Value已经归约为一个值
+        Ok(self.value_stack.pop().unwrap())
+    }
+
+    // top为刚刚弹出的栈顶，当前的token已经读取
+    fn trace(&mut self, top: Symbol, kind: impl FnOnce() -> StepKind) {
+        if self.tracer.is_none() {
+            return;
+        }
+        let grammar = &self.tables.grammar;
+        let stack: Vec<&str> = self.symbol_stack.iter().chain([&top]).map(|s| grammar.name(*s)).collect();
+        let step = Step {
+            stack: stack.join(" "),
+            input: input(self.lexer.rest(self.lexer.token_start())),
+            kind: kind(),
+        };
+        self.tracer.as_mut().unwrap().push(step);
+    }
+
+    fn name(&self, symbol: Symbol) -> &'static str {
+        if symbol == self.tables.grammar.eof() {
+            "end of input"
         } else {
-            let value = self.value_stack.pop().unwrap();
-            return Ok(value);
+            self.tables.grammar.name(symbol)
         }
     }
 
-    fn push_value(&mut self, token: TOKEN<'s>) -> () {
+    fn error(&self, expected: &str, symbol: Symbol) -> String {
+        if symbol == self.tables.grammar.eof() {
+            format!("expect {} before end of input", expected)
+        } else {
+            format!("expect {} at position: {}, found {}", expected, self.lexer.index(), self.name(symbol))
+        }
+    }
+
+    // 宽松模式下，`,`之后期望Value/Pair却遇到了`]`/`}`，说明是末尾多余的`,`
+    // 此时撤销刚刚应用的产生式8/12，改为应用产生式9/13，使push_value中的产生式计数保持正确
+    fn trailing_comma(&mut self, expected: Symbol, token: &TOKEN) -> bool {
+        if !self.lexer.is_lenient() || self.rules_stack.last().is_none() {
+            return false;
+        }
+        let rule = *self.rules_stack.last().unwrap();
+        let t = self.tables;
+        let (empty_rule, list) = match (token, rule) {
+            (TOKEN::RBRACKET, 8) if expected == t.value => (9, t.value_list),
+            (TOKEN::RBRACE, 12) if expected == t.pair => (13, t.pair_list),
+            _ => return false,
+        };
+        if self.symbol_stack.last() != Some(&list) {
+            return false;
+        }
+        self.symbol_stack.pop();
+        self.rules_stack.pop();
+        self.rules_stack.push(empty_rule);
+        true
+    }
+
+    fn push_value(&mut self, token: TOKEN<'s>) -> Result<()> {
         match token {
             TOKEN::STRING(s) => {
                 match self.rules_stack.pop().unwrap() {
@@ -212,6 +210,12 @@ impl<'s> TableDrivenParser<'s> {
                     _ => panic!("unexpected production")
                 }
             }
+            TOKEN::IDENT(s) => {
+                match self.rules_stack.pop().unwrap() {
+                    14 => self.value_stack.push(Value::String(s)),
+                    _ => return Err(format!("unexpected identifier {} at position: {}", s, self.lexer.index()))
+                }
+            }
             TOKEN::NUMBER(n) => {
                 match self.rules_stack.pop().unwrap() {
                     3 => self.value_stack.push(Value::Number(n)),
@@ -231,17 +235,18 @@ impl<'s> TableDrivenParser<'s> {
                 }
             }
             TOKEN::RBRACE => {
-                let mut map = HashMap::new();
+                let mut members = vec![];
                 self.rules_stack.pop();
                 while [11, 12].contains(&self.rules_stack.pop().unwrap())  {
                     // 13 12 12 12 12 ... 11
-                    if let value = self.value_stack.pop().unwrap() {
-                        if let Value::String(s) = self.value_stack.pop().unwrap() {
-                            map.insert(s, value);
-                        }
+                    // 从最后一个成员向前弹出
+                    let value = self.value_stack.pop().unwrap();
+                    if let Value::String(s) = self.value_stack.pop().unwrap() {
+                        members.push((s, value));
                     }
                 }
-                self.value_stack.push(Value::Object(map));
+                members.reverse();
+                self.value_stack.push(Value::from_members(members));
             }
             TOKEN::RBRACKET => {
                 let mut list = vec![];
@@ -249,13 +254,55 @@ impl<'s> TableDrivenParser<'s> {
                 // 9 8 8 8 8 8 8 ... 7
                 while [8, 7].contains(&self.rules_stack.pop().unwrap()) {
                     let value = self.value_stack.pop().unwrap();
-                    list.insert(0, value);
+                    list.push(value);
                 }
+                // 元素从后向前弹出
+                list.reverse();
                 self.value_stack.push(Value::Array(list));
             }
             _ => {}
         };
+        Ok(())
     }
 }
 
+// 分析到#才接受，值之后的多余内容已经报错
+impl<'s> Parser<'s> for TableDrivenParser<'s> {
+    fn parse(&mut self) -> Result<Value<'s>> {
+        TableDrivenParser::parse(self)
+    }
 
+    fn error_position(&self) -> usize {
+        self.lexer.error_position()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use super::*;
+
+    fn parse(src: &str) -> Result<Value<'_>> {
+        TableDrivenParser::new(Lexer::new(src)).parse()
+    }
+
+    #[test]
+    fn ends_at_eof() {
+        assert_eq!(parse(r#" {"a": [1, true, null]} "#).unwrap(), Value::Object(HashMap::from([
+            ("a", Value::Array(vec![Value::Number(1.0), Value::Bool(true), Value::Null])),
+        ])));
+        assert_eq!(parse("1").unwrap(), Value::Number(1.0));
+    }
+
+    #[test]
+    fn expected_symbols_before_eof() {
+        assert_eq!(parse("[1, 2").unwrap_err(), "expect ] | , before end of input");
+        assert_eq!(parse(r#"{"a":"#).unwrap_err(), "expect [ | { | string | number | bool | null before end of input");
+        assert_eq!(parse("").unwrap_err(), "expect [ | { | string | number | bool | null before end of input");
+    }
+
+    #[test]
+    fn trailing_input() {
+        assert_eq!(parse("[1] 2").unwrap_err(), "expect end of input at position: 5, found number");
+    }
+}
@@ -0,0 +1,437 @@
+use std::collections::HashMap;
+use std::fmt::{Debug, Display, Formatter};
+use super::{Lexer, Result, TOKEN, Value, Writable, Writer, unescape};
+
+// 扁平的tape表示，用于大批量解析
+// - 所有节点按先序存放在nodes中，根节点下标为0
+// - 数组的元素下标连续存放在items中，对象的(键, 值下标)连续存放在members中，节点只记录起点和长度
+// - 字符串和键借用源文本(转义后的原文)，不复制
+// 整个文档只有三块连续的缓冲区，不再为每个数组和对象分配Vec和HashMap
+// 解析是非递归的，容器关闭时才把它的子节点从pending移入items/members，保证同一容器的子节点连续
+
+#[derive(Debug, Clone, Copy)]
+enum Node<'s> {
+    String(&'s str),
+    Number(f64),
+    Bool(bool),
+    Null,
+    Array { start: u32, len: u32 },
+    Object { start: u32, len: u32 },
+}
+
+pub struct Tape<'s> {
+    nodes: Vec<Node<'s>>,
+    items: Vec<u32>,
+    members: Vec<(&'s str, u32)>,
+}
+
+// 尚未关闭的容器
+struct Open<'s> {
+    object: bool,
+    // 子节点在pending中的起点
+    pending: usize,
+    // 容器自身的键和节点下标
+    key: &'s str,
+    index: u32,
+}
+
+impl<'s> Tape<'s> {
+    pub fn parse(json_str: &'s str) -> Result<Tape<'s>> {
+        Tape::parse_lexer(Lexer::new(json_str))
+    }
+
+    pub fn parse_lexer(mut lexer: Lexer<'s>) -> Result<Tape<'s>> {
+        let tape = Tape::parse_value(&mut lexer)?;
+        if !lexer.at_end()? {
+            return Err(format!("unexpected token at position {}", lexer.index()));
+        }
+        Ok(tape)
+    }
+
+    /// 只解析lexer处的一个值，之后的内容留在lexer中
+    pub fn parse_value(lexer: &mut Lexer<'s>) -> Result<Tape<'s>> {
+        let mut tape = Tape {
+            nodes: vec![],
+            items: vec![],
+            members: vec![],
+        };
+        let mut open: Vec<Open<'s>> = vec![];
+        let mut pending: Vec<(&'s str, u32)> = vec![];
+        let mut key = "";
+        let mut token = lexer.lex()?;
+        loop {
+            // token是一个值的开始
+            let index = tape.nodes.len() as u32;
+            let mut closed = false;
+            match token {
+                TOKEN::LBRACE | TOKEN::LBRACKET => {
+                    let object = matches!(token, TOKEN::LBRACE);
+                    // 占位，关闭时回填
+                    tape.nodes.push(Node::Null);
+                    open.push(Open { object, pending: pending.len(), key, index });
+                    token = lexer.lex()?;
+                    if is_close(object, &token) {
+                        closed = true;
+                    } else {
+                        if object {
+                            key = member_key(lexer, token)?;
+                            token = lexer.lex()?;
+                        }
+                        continue;
+                    }
+                }
+                TOKEN::STRING(s) => tape.push_scalar(Node::String(s), key, &open, &mut pending),
+                TOKEN::NUMBER(n) => tape.push_scalar(Node::Number(n), key, &open, &mut pending),
+                TOKEN::BOOL(b) => tape.push_scalar(Node::Bool(b), key, &open, &mut pending),
+                TOKEN::NULL => tape.push_scalar(Node::Null, key, &open, &mut pending),
+                _ => return Err(format!("unexpected token at position {}", lexer.token_start())),
+            }
+            // 一个值结束，关闭所有已结束的容器，直到遇到`,`
+            loop {
+                let object = match open.last() {
+                    Some(top) => top.object,
+                    None => return Ok(tape),
+                };
+                if !closed {
+                    token = lexer.lex()?;
+                }
+                closed = false;
+                match token {
+                    TOKEN::COMMA => {
+                        token = lexer.lex()?;
+                        if is_close(object, &token) && lexer.is_lenient() {
+                            closed = true;
+                            continue;
+                        }
+                        if object {
+                            key = member_key(lexer, token)?;
+                            token = lexer.lex()?;
+                        }
+                        break;
+                    }
+                    _ if is_close(object, &token) => {
+                        let top = open.pop().unwrap();
+                        tape.close(&top, &mut pending);
+                        if !open.is_empty() {
+                            pending.push((top.key, top.index));
+                        }
+                    }
+                    _ => {
+                        let close = if object { "}" } else { "]" };
+                        return Err(format!("expect , or {} at position {}", close, lexer.token_start()));
+                    }
+                }
+            }
+        }
+    }
+
+    fn push_scalar(&mut self, node: Node<'s>, key: &'s str, open: &[Open<'s>], pending: &mut Vec<(&'s str, u32)>) {
+        if !open.is_empty() {
+            pending.push((key, self.nodes.len() as u32));
+        }
+        self.nodes.push(node);
+    }
+
+    fn close(&mut self, top: &Open<'s>, pending: &mut Vec<(&'s str, u32)>) {
+        let children = pending.drain(top.pending..);
+        let len = children.len() as u32;
+        self.nodes[top.index as usize] = if top.object {
+            let start = self.members.len() as u32;
+            self.members.extend(children);
+            Node::Object { start, len }
+        } else {
+            let start = self.items.len() as u32;
+            self.items.extend(children.map(|(_, index)| index));
+            Node::Array { start, len }
+        };
+    }
+
+    pub fn root(&self) -> ValueRef<'_, 's> {
+        ValueRef { tape: self, index: 0 }
+    }
+
+    /// 节点总数
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+}
+
+fn is_close(object: bool, token: &TOKEN) -> bool {
+    if object {
+        matches!(token, TOKEN::RBRACE)
+    } else {
+        matches!(token, TOKEN::RBRACKET)
+    }
+}
+
+// 读取对象成员的键和其后的`:`
+fn member_key<'s>(lexer: &mut Lexer<'s>, token: TOKEN<'s>) -> Result<&'s str> {
+    let key = match token {
+        TOKEN::STRING(s) | TOKEN::IDENT(s) => s,
+        _ => return Err(format!("expect string at position {}", lexer.token_start())),
+    };
+    match lexer.lex()? {
+        TOKEN::COLON => Ok(key),
+        _ => Err(format!("expect : at position {}", lexer.token_start())),
+    }
+}
+
+/// tape中一个节点的视图，访问方法与Value一致
+#[derive(Clone, Copy)]
+pub struct ValueRef<'t, 's> {
+    tape: &'t Tape<'s>,
+    index: u32,
+}
+
+impl<'t, 's> ValueRef<'t, 's> {
+    fn node(&self) -> &'t Node<'s> {
+        &self.tape.nodes[self.index as usize]
+    }
+
+    fn at(&self, index: u32) -> ValueRef<'t, 's> {
+        ValueRef { tape: self.tape, index }
+    }
+
+    pub fn type_name(&self) -> &'static str {
+        match self.node() {
+            Node::String(_) => "string",
+            Node::Number(_) => "number",
+            Node::Bool(_) => "bool",
+            Node::Null => "null",
+            Node::Object { .. } => "object",
+            Node::Array { .. } => "array",
+        }
+    }
+
+    // 与AsRef相同，但返回值不受视图本身生命周期的限制
+
+    pub fn as_str(&self) -> &'s str {
+        match self.node() {
+            Node::String(s) => s,
+            _ => panic!("expect string, found {}", self.type_name()),
+        }
+    }
+
+    pub fn as_f64(&self) -> f64 {
+        *self.as_ref()
+    }
+
+    pub fn as_bool(&self) -> bool {
+        *self.as_ref()
+    }
+
+    pub fn is_null(&self) -> bool {
+        matches!(self.node(), Node::Null)
+    }
+
+    /// 数组元素或对象成员的个数，标量为0
+    pub fn len(&self) -> usize {
+        match self.node() {
+            Node::Array { len, .. } | Node::Object { len, .. } => *len as usize,
+            _ => 0,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// 数组的第index个元素，不是数组时返回None
+    pub fn index(&self, index: usize) -> Option<ValueRef<'t, 's>> {
+        match self.node() {
+            Node::Array { start, len } => {
+                if index >= *len as usize {
+                    return None;
+                }
+                Some(self.at(self.tape.items[*start as usize + index]))
+            }
+            _ => None,
+        }
+    }
+
+    /// 对象中键为key的成员，键按还原转义后的内容比较，键重复时与完整解析相同取最后一个
+    /// 不是对象时返回None
+    pub fn get(&self, key: &str) -> Option<ValueRef<'t, 's>> {
+        if !matches!(self.node(), Node::Object { .. }) {
+            return None;
+        }
+        self.members().filter(|(k, _)| unescape(k).is_ok_and(|k| k == key)).last().map(|(_, v)| v)
+    }
+
+    /// 数组的元素
+    pub fn iter(&self) -> impl Iterator<Item = ValueRef<'t, 's>> + 't {
+        let (tape, items) = match self.node() {
+            Node::Array { start, len } => (self.tape, &self.tape.items[*start as usize..(*start + *len) as usize]),
+            _ => panic!("expect array, found {}", self.type_name()),
+        };
+        items.iter().map(move |index| ValueRef { tape, index: *index })
+    }
+
+    /// 对象的成员，按源文本中的顺序
+    pub fn members(&self) -> impl Iterator<Item = (&'s str, ValueRef<'t, 's>)> + 't {
+        let (tape, members) = match self.node() {
+            Node::Object { start, len } => (self.tape, &self.tape.members[*start as usize..(*start + *len) as usize]),
+            _ => panic!("expect object, found {}", self.type_name()),
+        };
+        members.iter().map(move |(key, index)| (*key, ValueRef { tape, index: *index }))
+    }
+
+    /// 按JSON Pointer(RFC 6901)取值，对象的键按还原转义后的内容比较
+    pub fn pointer(&self, pointer: &str) -> Option<ValueRef<'t, 's>> {
+        if pointer.is_empty() {
+            return Some(*self);
+        }
+        let mut value = *self;
+        for token in pointer.strip_prefix('/')?.split('/') {
+            let token = token.replace("~1", "/").replace("~0", "~");
+            value = match value.node() {
+                Node::Array { .. } => value.index(token.parse::<usize>().ok()?)?,
+                _ => value.get(&token)?,
+            };
+        }
+        Some(value)
+    }
+
+    /// 构造等价的Value
+    pub fn to_value(&self) -> Value<'s> {
+        match *self.node() {
+            Node::String(s) => Value::String(s),
+            Node::Number(n) => Value::Number(n),
+            Node::Bool(b) => Value::Bool(b),
+            Node::Null => Value::Null,
+            Node::Array { .. } => Value::Array(self.iter().map(|v| v.to_value()).collect()),
+            Node::Object { .. } => Value::from_members(self.members().map(|(k, v)| (k, v.to_value())).collect()),
+        }
+    }
+}
+
+impl Writable for ValueRef<'_, '_> {
+    fn write_to(&self, writer: &Writer, depth: usize, out: &mut String) -> Result<()> {
+        match *self.node() {
+            Node::String(s) => writer.write_string(s, out)?,
+            Node::Number(n) => writer.write_number(n, out)?,
+            Node::Bool(b) => out.push_str(if b { "true" } else { "false" }),
+            Node::Null => out.push_str("null"),
+            Node::Array { .. } => writer.write_array(self.iter(), depth, out)?,
+            Node::Object { .. } => {
+                // 与完整解析相同，重复的键取最后一个，位于最后一次出现的位置
+                let mut members = vec![];
+                let mut seen = HashMap::new();
+                for (key, value) in self.members() {
+                    let key = unescape(key)?;
+                    if let Some(i) = seen.insert(key.clone(), members.len()) {
+                        members[i] = None;
+                    }
+                    members.push(Some((key, value)));
+                }
+                writer.write_object(members.into_iter().flatten().collect(), depth, out)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<'s> AsRef<str> for ValueRef<'_, 's> {
+    fn as_ref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl AsRef<f64> for ValueRef<'_, '_> {
+    fn as_ref(&self) -> &f64 {
+        match self.node() {
+            Node::Number(n) => n,
+            _ => panic!("expect number, found {}", self.type_name()),
+        }
+    }
+}
+
+impl AsRef<bool> for ValueRef<'_, '_> {
+    fn as_ref(&self) -> &bool {
+        match self.node() {
+            Node::Bool(b) => b,
+            _ => panic!("expect bool, found {}", self.type_name()),
+        }
+    }
+}
+
+impl Debug for ValueRef<'_, '_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        Debug::fmt(&self.to_value(), f)
+    }
+}
+
+impl Display for ValueRef<'_, '_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        Display::fmt(&self.to_value(), f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::json::from_str;
+
+    const SRC: &str = r#"{"a": [1, 2.5, {"b": null}], "c": "x", "d": {}, "e": [], "f": true}"#;
+
+    #[test]
+    fn same_as_value() {
+        let tape = Tape::parse(SRC).unwrap();
+        assert_eq!(tape.root().to_value(), from_str(SRC).unwrap());
+        assert_eq!(tape.len(), 10);
+        for src in ["1", "\"s\"", "[]", "{}", "[[[]]]", "[{\"a\": [{}]}]"] {
+            assert_eq!(Tape::parse(src).unwrap().root().to_value(), from_str(src).unwrap(), "{}", src);
+        }
+    }
+
+    #[test]
+    fn access() {
+        let tape = Tape::parse(SRC).unwrap();
+        let root = tape.root();
+        assert_eq!(root.type_name(), "object");
+        assert_eq!(root.len(), 5);
+        let keys: Vec<&str> = root.members().map(|(k, _)| k).collect();
+        assert_eq!(keys, ["a", "c", "d", "e", "f"]);
+        let a = root.get("a").unwrap();
+        assert_eq!(a.iter().map(|v| v.type_name()).collect::<Vec<_>>(), ["number", "number", "object"]);
+        assert_eq!(a.index(1).unwrap().as_f64(), 2.5);
+        assert!(a.index(3).is_none());
+        assert!(root.pointer("/a/2/b").unwrap().is_null());
+        assert_eq!(root.pointer("/c").unwrap().as_str(), "x");
+        assert!(root.get("d").unwrap().is_empty());
+        assert!(root.get("e").unwrap().is_empty());
+        assert!(root.pointer("/f").unwrap().as_bool());
+        assert!(root.pointer("/a/9").is_none());
+        assert!(root.pointer("/c/0").is_none());
+        // 类型不符时与Value相同返回None
+        assert!(root.index(0).is_none());
+        assert!(a.get("0").is_none());
+        assert!(root.get("c").unwrap().get("x").is_none());
+        assert!(root.get("f").unwrap().index(0).is_none());
+    }
+
+    #[test]
+    fn errors() {
+        for src in ["", "[1,]", "[1 2]", "{\"a\" 1}", "{1: 2}", "[1]]", "{\"a\": 1"] {
+            assert!(Tape::parse(src).is_err(), "{}", src);
+        }
+        let tape = Tape::parse_lexer(Lexer::lenient("{a: [1, 2,],}")).unwrap();
+        assert_eq!(tape.root().pointer("/a/1").unwrap().as_f64(), 2.0);
+    }
+
+    #[test]
+    fn keys() {
+        let tape = Tape::parse(r#"{"a": 1, "\u0062": 2, "a": 3}"#).unwrap();
+        let root = tape.root();
+        assert_eq!(root.get("a").unwrap().as_f64(), 3.0);
+        assert_eq!(root.get("b").unwrap().as_f64(), 2.0);
+        assert!(root.get("\\u0062").is_none());
+        assert_eq!(root.pointer("/a").unwrap().as_f64(), 3.0);
+        assert!(root.pointer("/\\u0062").is_none());
+        assert_eq!(root.to_value().get("a"), Some(&Value::Number(3.0)));
+    }
+}
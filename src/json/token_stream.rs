@@ -0,0 +1,189 @@
+use std::collections::VecDeque;
+use super::{Lexer, Result, Span, TOKEN};
+
+// 在Lexer之上缓冲token，支持向前看任意个token而不需要退回重新读取
+// - peek/peek_nth只读取不消耗，读取的token和位置依次存放在peeked和buffer中，next时取出
+//   LL1分析只向前看一个token，单独存放第一个token，避免每个token都经过VecDeque
+// - 作为Iterator时逐个产生token，出错后停止，可用于语法高亮等只需要token的场合
+
+/// 一个token和它在源文本中的范围，范围不含之前的空白和注释
+#[derive(Debug, Clone, Copy)]
+pub struct Token<'s> {
+    pub kind: TOKEN<'s>,
+    pub span: Span,
+}
+
+pub struct TokenStream<'s> {
+    lexer: Lexer<'s>,
+    peeked: Option<Token<'s>>,
+    // peeked之后的token
+    buffer: VecDeque<Token<'s>>,
+    // 上一个被消耗的token的范围
+    last: Span,
+    failed: bool,
+}
+
+impl<'s> TokenStream<'s> {
+    pub fn new(lexer: Lexer<'s>) -> TokenStream<'s> {
+        TokenStream {
+            lexer,
+            peeked: None,
+            buffer: VecDeque::new(),
+            last: Span { start: 0, end: 0 },
+            failed: false,
+        }
+    }
+
+    fn lex(&mut self) -> Result<Token<'s>> {
+        let kind = self.lexer.lex()?;
+        Ok(Token { kind, span: Span { start: self.lexer.token_start(), end: self.lexer.index() } })
+    }
+
+    /// 下一个token，输入结束时为None
+    pub fn peek(&mut self) -> Result<Option<&Token<'s>>> {
+        self.peek_nth(0)
+    }
+
+    /// 之后的第k个token，k从0开始
+    pub fn peek_nth(&mut self, k: usize) -> Result<Option<&Token<'s>>> {
+        while self.buffered() <= k {
+            if self.lexer.at_end()? {
+                return Ok(None);
+            }
+            let token = self.lex()?;
+            match self.peeked {
+                None => self.peeked = Some(token),
+                Some(_) => self.buffer.push_back(token),
+            }
+        }
+        Ok(if k == 0 { self.peeked.as_ref() } else { self.buffer.get(k - 1) })
+    }
+
+    fn buffered(&self) -> usize {
+        self.peeked.is_some() as usize + self.buffer.len()
+    }
+
+    /// 与peek相同，但输入结束时返回与`Lexer::lex`相同的错误
+    pub fn peek_token(&mut self) -> Result<Token<'s>> {
+        if let Some(token) = self.peeked {
+            return Ok(token);
+        }
+        let token = self.lex()?;
+        self.peeked = Some(token);
+        Ok(token)
+    }
+
+    /// 消耗下一个token，输入结束时返回与`Lexer::lex`相同的错误
+    pub fn next_token(&mut self) -> Result<Token<'s>> {
+        let token = match self.peeked.take() {
+            Some(token) => {
+                self.peeked = self.buffer.pop_front();
+                token
+            }
+            None => self.lex()?,
+        };
+        self.last = token.span;
+        Ok(token)
+    }
+
+    /// 下一个token的起始位置，还没有读取时为lexer的当前位置(可能在空白之前)
+    pub fn position(&self) -> usize {
+        match &self.peeked {
+            Some(token) => token.span.start,
+            None => self.lexer.index(),
+        }
+    }
+
+    /// 上一个被消耗的token的范围
+    pub fn last_span(&self) -> Span {
+        self.last
+    }
+
+    /// 跳过空白和注释后是否已到达输入末尾
+    pub fn at_end(&mut self) -> Result<bool> {
+        Ok(self.peeked.is_none() && self.lexer.at_end()?)
+    }
+
+    /// 跳过一个字符，用于错误恢复，只在没有缓冲的token(即刚刚读取出错)时调用
+    pub fn skip_char(&mut self) {
+        debug_assert!(self.peeked.is_none());
+        self.lexer.skip_char();
+        self.failed = false;
+    }
+
+    pub fn lexer(&self) -> &Lexer<'s> {
+        &self.lexer
+    }
+}
+
+impl<'s> Iterator for TokenStream<'s> {
+    type Item = Result<Token<'s>>;
+
+    fn next(&mut self) -> Option<Result<Token<'s>>> {
+        if self.failed {
+            return None;
+        }
+        match self.peek() {
+            Ok(None) => return None,
+            Ok(Some(_)) => {}
+            Err(msg) => {
+                self.failed = true;
+                return Some(Err(msg));
+            }
+        }
+        Some(self.next_token())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn span(start: usize, end: usize) -> Span {
+        Span { start, end }
+    }
+
+    #[test]
+    fn peek_does_not_consume() {
+        let mut stream = TokenStream::new(Lexer::new(r#" {"a": 1} "#));
+        assert_eq!(stream.peek_nth(3).unwrap().unwrap().kind, TOKEN::NUMBER(1.0));
+        assert_eq!(stream.peek().unwrap().unwrap().kind, TOKEN::LBRACE);
+        assert_eq!(stream.position(), 1);
+        let token = stream.next_token().unwrap();
+        assert_eq!((token.kind, token.span), (TOKEN::LBRACE, span(1, 2)));
+        assert_eq!(stream.last_span(), span(1, 2));
+        assert_eq!(stream.peek().unwrap().unwrap().span, span(2, 5));
+        assert_eq!(stream.peek_nth(3).unwrap().unwrap().kind, TOKEN::RBRACE);
+        assert!(stream.peek_nth(4).unwrap().is_none());
+        // 已经向前看过的token不需要重新读取
+        assert_eq!(stream.lexer().relexed(), 0);
+    }
+
+    #[test]
+    fn iterator_yields_tokens_with_spans() {
+        let tokens: Vec<_> = TokenStream::new(Lexer::new("[1, true]")).map(|t| t.unwrap()).collect();
+        let kinds: Vec<_> = tokens.iter().map(|t| t.kind).collect();
+        let spans: Vec<_> = tokens.iter().map(|t| t.span).collect();
+        assert_eq!(kinds, vec![TOKEN::LBRACKET, TOKEN::NUMBER(1.0), TOKEN::COMMA, TOKEN::BOOL(true), TOKEN::RBRACKET]);
+        assert_eq!(spans, vec![span(0, 1), span(1, 2), span(2, 3), span(4, 8), span(8, 9)]);
+    }
+
+    #[test]
+    fn iterator_stops_after_error() {
+        let mut stream = TokenStream::new(Lexer::new("[1, x, 2]"));
+        assert_eq!(stream.next().unwrap().unwrap().kind, TOKEN::LBRACKET);
+        assert_eq!(stream.next().unwrap().unwrap().kind, TOKEN::NUMBER(1.0));
+        assert_eq!(stream.next().unwrap().unwrap().kind, TOKEN::COMMA);
+        assert_eq!(stream.next().unwrap().unwrap_err(), "unexpected token at position 4");
+        assert!(stream.next().is_none());
+    }
+
+    #[test]
+    fn next_token_at_end() {
+        let mut stream = TokenStream::new(Lexer::new("1 "));
+        assert_eq!(stream.next_token().unwrap().kind, TOKEN::NUMBER(1.0));
+        assert!(stream.at_end().unwrap());
+        assert!(stream.next_token().is_err());
+        assert!(stream.peek_token().is_err());
+    }
+}
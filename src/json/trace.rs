@@ -0,0 +1,288 @@
+use super::grammar::{Grammar, Symbol};
+
+// 记录分析过程中的每一步，用于教学演示和调试
+// - TableDrivenParser: 每次用产生式展开栈顶的非终结符(predict)，或栈顶终结符与输入匹配(match)
+// - DefiniteParser/IndefiniteParser: 每次进入一个非终结符对应的函数(enter)，栈为调用栈
+// - LRParser: 每次移进(shift)和归约(reduce)，栈为状态和符号交替排列
+// 各parser的with_tracer设置Tracer，分析结束后由take_tracer取回
+//
+// 最左推导由记录中的产生式得到: predict的顺序即为最左推导的顺序；
+// reduce的顺序是最右推导的逆序，先按归约重建语法树，再先序遍历得到最左推导
+
+/// 分析过程中的一步
+#[derive(Debug, Clone)]
+pub struct Step {
+    // 栈底在左
+    pub stack: String,
+    // 剩余的输入，过长时截断
+    pub input: String,
+    pub kind: StepKind,
+}
+
+#[derive(Debug, Clone)]
+pub enum StepKind {
+    // 产生式编号和产生式
+    Predict(usize, String),
+    // 匹配的终结符
+    Match(&'static str),
+    // 进入的非终结符
+    Enter(&'static str),
+    // 移进后的状态
+    Shift(usize),
+    Reduce(usize, String),
+    Accept,
+}
+
+impl std::fmt::Display for StepKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StepKind::Predict(index, production) => write!(f, "predict {}: {}", index, production),
+            StepKind::Match(terminal) => write!(f, "match {}", terminal),
+            StepKind::Enter(rule) => write!(f, "enter {}", rule),
+            StepKind::Shift(state) => write!(f, "shift {}", state),
+            StepKind::Reduce(index, production) => write!(f, "reduce {}: {}", index, production),
+            StepKind::Accept => write!(f, "accept"),
+        }
+    }
+}
+
+// 剩余输入最多显示的字符数
+const INPUT_WIDTH: usize = 24;
+
+type Callback = Box<dyn FnMut(&Step)>;
+
+/// 记录每一步，或对每一步调用回调，或两者都有
+pub struct Tracer {
+    steps: Vec<Step>,
+    record: bool,
+    callback: Option<Callback>,
+}
+
+impl Default for Tracer {
+    fn default() -> Self {
+        Tracer::new()
+    }
+}
+
+impl Tracer {
+    /// 记录所有步骤
+    pub fn new() -> Tracer {
+        Tracer { steps: vec![], record: true, callback: None }
+    }
+
+    /// 只对每一步调用callback，不记录
+    pub fn callback(callback: impl FnMut(&Step) + 'static) -> Tracer {
+        Tracer { steps: vec![], record: false, callback: Some(Box::new(callback)) }
+    }
+
+    /// 记录的同时调用callback
+    pub fn with_callback(self, callback: impl FnMut(&Step) + 'static) -> Tracer {
+        Tracer { callback: Some(Box::new(callback)), ..self }
+    }
+
+    pub fn push(&mut self, step: Step) {
+        if let Some(callback) = &mut self.callback {
+            callback(&step);
+        }
+        if self.record {
+            self.steps.push(step);
+        }
+    }
+
+    pub fn steps(&self) -> &[Step] {
+        &self.steps
+    }
+
+    /// 输出为markdown表格: 步骤、栈、剩余输入、动作
+    pub fn table(&self) -> String {
+        let mut out = String::from("| step | stack | input | action |\n|------|-------|-------|--------|");
+        for (i, step) in self.steps.iter().enumerate() {
+            out.push_str(&format!(
+                "\n| {} | {} | {} | {} |",
+                i + 1,
+                escape(&step.stack),
+                escape(&step.input),
+                escape(&step.kind.to_string()),
+            ));
+        }
+        out
+    }
+
+    /// 最左推导的各个句型，第一个为起始符号
+    /// 只有predict和reduce记录了产生式，递归下降的parser的记录没有推导
+    pub fn derivation(&self, grammar: &Grammar) -> Vec<String> {
+        let mut productions: Vec<usize> = self.steps.iter()
+            .filter_map(|step| match step.kind {
+                StepKind::Predict(index, _) => Some(index),
+                _ => None,
+            })
+            .collect();
+        if productions.is_empty() {
+            productions = leftmost(grammar, self.steps.iter().filter_map(|step| match step.kind {
+                StepKind::Reduce(index, _) => Some(index),
+                _ => None,
+            }));
+        }
+        if productions.is_empty() {
+            return vec![];
+        }
+        let mut form: Vec<Symbol> = vec![grammar.start()];
+        let mut lines = vec![sentential_form(grammar, &form)];
+        for index in productions {
+            let production = &grammar.productions()[index];
+            // 展开最左边的非终结符，与产生式左部不符时说明记录不完整(如宽松模式下改写过产生式)
+            match form.iter().position(|s| !grammar.is_terminal(*s)) {
+                Some(i) if form[i] == production.head => {
+                    form.splice(i..i + 1, production.body.iter().copied());
+                }
+                _ => break,
+            }
+            lines.push(sentential_form(grammar, &form));
+        }
+        lines
+    }
+}
+
+// 按归约的顺序重建语法树，返回先序遍历的产生式序列
+fn leftmost(grammar: &Grammar, reductions: impl Iterator<Item=usize>) -> Vec<usize> {
+    // (产生式, 子树)，子树只包含非终结符
+    struct Node {
+        production: usize,
+        children: Vec<Node>,
+    }
+    let mut stack: Vec<Node> = vec![];
+    for production in reductions {
+        let count = grammar.productions()[production].body.iter().filter(|s| !grammar.is_terminal(**s)).count();
+        if count > stack.len() {
+            return vec![];
+        }
+        let children = stack.split_off(stack.len() - count);
+        stack.push(Node { production, children });
+    }
+    let mut productions = vec![];
+    let mut pending: Vec<&Node> = stack.iter().rev().collect();
+    while let Some(node) = pending.pop() {
+        productions.push(node.production);
+        pending.extend(node.children.iter().rev());
+    }
+    productions
+}
+
+fn sentential_form(grammar: &Grammar, form: &[Symbol]) -> String {
+    if form.is_empty() {
+        return "ε".to_string();
+    }
+    form.iter().map(|s| grammar.name(*s)).collect::<Vec<_>>().join(" ")
+}
+
+fn escape(s: &str) -> String {
+    s.replace('|', "\\|")
+}
+
+/// 从rest开始的剩余输入，空白压缩为一个空格，末尾加上#
+pub fn input(rest: &str) -> String {
+    let mut out = String::new();
+    let mut blank = false;
+    for (i, c) in rest.trim_start().chars().enumerate() {
+        if i == INPUT_WIDTH {
+            out.push('…');
+            return out;
+        }
+        if c.is_whitespace() {
+            if !blank {
+                out.push(' ');
+            }
+            blank = true;
+        } else {
+            out.push(c);
+            blank = false;
+        }
+    }
+    out.push('#');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use std::rc::Rc;
+    use super::*;
+    use super::super::{DefiniteParser, Lexer, LRParser, TableDrivenParser};
+
+    fn kinds(tracer: &Tracer) -> Vec<String> {
+        tracer.steps().iter().map(|step| step.kind.to_string()).collect()
+    }
+
+    #[test]
+    fn table_driven() {
+        let mut parser = TableDrivenParser::new(Lexer::new("[1]")).with_tracer(Tracer::new());
+        parser.parse().unwrap();
+        let tracer = parser.take_tracer().unwrap();
+        assert_eq!(kinds(&tracer), [
+            "predict 0: Value -> [ Array'",
+            "match [",
+            "predict 7: Array' -> Value ValueList ]",
+            "predict 3: Value -> number",
+            "match number",
+            "predict 9: ValueList -> ε",
+            "match ]",
+            "accept",
+        ]);
+        assert_eq!(tracer.steps()[0].stack, "# Value");
+        assert_eq!(tracer.steps()[0].input, "[1]#");
+        assert_eq!(tracer.derivation(TableDrivenParser::grammar()), [
+            "Value",
+            "[ Array'",
+            "[ Value ValueList ]",
+            "[ number ValueList ]",
+            "[ number ]",
+        ]);
+    }
+
+    #[test]
+    fn lr() {
+        let mut parser = LRParser::new(Lexer::new("[1]")).with_tracer(Tracer::new());
+        parser.parse().unwrap();
+        let tracer = parser.take_tracer().unwrap();
+        assert!(kinds(&tracer).iter().any(|k| k == "reduce 10: Elements -> Value"));
+        assert_eq!(kinds(&tracer).last().unwrap(), "accept");
+        // 由归约重建的最左推导
+        assert_eq!(tracer.derivation(LRParser::grammar()), [
+            "Value",
+            "Array",
+            "[ Elements ]",
+            "[ Value ]",
+            "[ number ]",
+        ]);
+    }
+
+    #[test]
+    fn recursive_descent() {
+        let mut parser = DefiniteParser::new(Lexer::new("[1]")).with_tracer(Tracer::new());
+        parser.parse().unwrap();
+        let tracer = parser.take_tracer().unwrap();
+        assert_eq!(kinds(&tracer)[..2], ["enter Value", "enter Array'"]);
+        assert_eq!(tracer.steps()[1].stack, "Value Array'");
+        assert!(tracer.derivation(TableDrivenParser::grammar()).is_empty());
+    }
+
+    #[test]
+    fn callback_without_recording() {
+        let count = Rc::new(Cell::new(0));
+        let counter = count.clone();
+        let mut parser = TableDrivenParser::new(Lexer::new("[1]"))
+            .with_tracer(Tracer::callback(move |_| counter.set(counter.get() + 1)));
+        parser.parse().unwrap();
+        assert_eq!(count.get(), 8);
+        assert!(parser.take_tracer().unwrap().steps().is_empty());
+    }
+
+    #[test]
+    fn table_and_input() {
+        let mut tracer = Tracer::new();
+        tracer.push(Step { stack: "#".to_string(), input: "#".to_string(), kind: StepKind::Predict(0, "A -> a | b".to_string()) });
+        assert_eq!(tracer.table(), "| step | stack | input | action |\n|------|-------|-------|--------|\n| 1 | # | # | predict 0: A -> a \\| b |");
+        assert_eq!(input("  [1,\n   2] "), "[1, 2] #");
+        assert_eq!(input(&"1".repeat(30)), format!("{}…", "1".repeat(24)));
+    }
+}
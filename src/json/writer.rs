@@ -0,0 +1,160 @@
+use std::borrow::Cow;
+use super::{canonical_number, unescape, write_canonical_string, Result, Value};
+
+// 可配置的输出，Display固定使用4个空格缩进且键的顺序取决于HashMap
+// - 对象的成员按Writable给出的顺序输出，也可以按键排序
+//   Value的对象是HashMap，没有成员的顺序，按键还原转义后的内容排序；Tape的ValueRef按源文本中的顺序
+// - 字符串还原转义后重新转义，宽松模式下的单引号字符串和标识符键也输出为合法的JSON
+// - 数字使用最短的往返表示(同规范化输出)，NaN和Infinity报错
+
+pub struct Writer {
+    // None表示紧凑输出
+    indent: Option<String>,
+    sort_keys: bool,
+}
+
+/// 可以由Writer输出的值，对象的成员由实现给出顺序
+pub trait Writable {
+    fn write_to(&self, writer: &Writer, depth: usize, out: &mut String) -> Result<()>;
+}
+
+impl<T: Writable + ?Sized> Writable for &T {
+    fn write_to(&self, writer: &Writer, depth: usize, out: &mut String) -> Result<()> {
+        (**self).write_to(writer, depth, out)
+    }
+}
+
+impl Writer {
+    pub fn pretty(indent: &str) -> Writer {
+        Writer { indent: Some(indent.to_string()), sort_keys: false }
+    }
+
+    /// 不输出任何空白
+    pub fn compact() -> Writer {
+        Writer { indent: None, sort_keys: false }
+    }
+
+    /// 按键排序，默认保持成员的顺序
+    pub fn sort_keys(self, sort_keys: bool) -> Writer {
+        Writer { sort_keys, ..self }
+    }
+
+    pub fn write<T: Writable + ?Sized>(&self, value: &T) -> Result<String> {
+        let mut out = String::new();
+        value.write_to(self, 0, &mut out)?;
+        Ok(out)
+    }
+
+    /// 输出字符串，`raw`为转义后的原文
+    pub fn write_string(&self, raw: &str, out: &mut String) -> Result<()> {
+        write_canonical_string(&unescape(raw)?, out);
+        Ok(())
+    }
+
+    pub fn write_number(&self, n: f64, out: &mut String) -> Result<()> {
+        out.push_str(&canonical_number(n)?);
+        Ok(())
+    }
+
+    pub fn write_array<T: Writable>(&self, items: impl IntoIterator<Item = T>, depth: usize, out: &mut String) -> Result<()> {
+        out.push('[');
+        let mut empty = true;
+        for (i, value) in items.into_iter().enumerate() {
+            if i > 0 {
+                out.push(',');
+            }
+            self.newline(depth + 1, out);
+            value.write_to(self, depth + 1, out)?;
+            empty = false;
+        }
+        if !empty {
+            self.newline(depth, out);
+        }
+        out.push(']');
+        Ok(())
+    }
+
+    /// 输出对象，`members`的键为还原转义后的内容
+    pub fn write_object<T: Writable>(&self, mut members: Vec<(Cow<'_, str>, T)>, depth: usize, out: &mut String) -> Result<()> {
+        if members.is_empty() {
+            out.push_str("{}");
+            return Ok(());
+        }
+        if self.sort_keys {
+            members.sort_by(|a, b| a.0.cmp(&b.0));
+        }
+        out.push('{');
+        for (i, (key, value)) in members.iter().enumerate() {
+            if i > 0 {
+                out.push(',');
+            }
+            self.newline(depth + 1, out);
+            write_canonical_string(key, out);
+            out.push(':');
+            if self.indent.is_some() {
+                out.push(' ');
+            }
+            value.write_to(self, depth + 1, out)?;
+        }
+        self.newline(depth, out);
+        out.push('}');
+        Ok(())
+    }
+
+    fn newline(&self, depth: usize, out: &mut String) {
+        if let Some(indent) = &self.indent {
+            out.push('\n');
+            for _ in 0..depth {
+                out.push_str(indent);
+            }
+        }
+    }
+}
+
+impl Writable for Value<'_> {
+    fn write_to(&self, writer: &Writer, depth: usize, out: &mut String) -> Result<()> {
+        match self {
+            Value::String(s) => writer.write_string(s, out)?,
+            Value::Number(n) => writer.write_number(*n, out)?,
+            Value::Bool(b) => out.push_str(if *b { "true" } else { "false" }),
+            Value::Null => out.push_str("null"),
+            Value::Array(arr) => writer.write_array(arr, depth, out)?,
+            Value::Object(obj) => {
+                let mut members = obj.iter().map(|(k, v)| Ok((unescape(k)?, v))).collect::<Result<Vec<_>>>()?;
+                members.sort();
+                writer.write_object(members, depth, out)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use super::*;
+    use crate::json::{from_str, Lexer, Tape};
+
+    #[test]
+    fn value_keys_by_content() {
+        // 键来自不同的字符串，与所在的地址无关
+        let (b, a) = ("b".to_string(), "a".to_string());
+        let value = Value::Object(HashMap::from([(b.as_str(), Value::Number(1.0)), (a.as_str(), Value::Null)]));
+        assert_eq!(Writer::compact().write(&value).unwrap(), r#"{"a":null,"b":1}"#);
+        let value = from_str(r#"{"z": [], "\u0061": {}, "b": ["x", 1.50]}"#).unwrap();
+        assert_eq!(Writer::compact().write(&value).unwrap(), r#"{"a":{},"b":["x",1.5],"z":[]}"#);
+        assert_eq!(Writer::pretty("  ").write(&value).unwrap(), "{\n  \"a\": {},\n  \"b\": [\n    \"x\",\n    1.5\n  ],\n  \"z\": []\n}");
+    }
+
+    #[test]
+    fn tape_keeps_source_order() {
+        let tape = Tape::parse(r#"{"z": 1, "a": {"y": 2, "b": 3}, "\u0061": 4}"#).unwrap();
+        // 重复的键取最后一个
+        assert_eq!(Writer::compact().write(&tape.root()).unwrap(), r#"{"z":1,"a":4}"#);
+        let tape = Tape::parse(r#"{"z": 1, "a": {"y": 2, "b": 3}}"#).unwrap();
+        assert_eq!(Writer::compact().write(&tape.root()).unwrap(), r#"{"z":1,"a":{"y":2,"b":3}}"#);
+        assert_eq!(Writer::compact().sort_keys(true).write(&tape.root()).unwrap(), r#"{"a":{"b":3,"y":2},"z":1}"#);
+        let tape = Tape::parse_lexer(Lexer::lenient("{b: 'x\\'y', a: [1e2,],}")).unwrap();
+        assert_eq!(Writer::compact().write(&tape.root()).unwrap(), r#"{"b":"x'y","a":[100]}"#);
+    }
+}
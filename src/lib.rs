@@ -0,0 +1,6 @@
+// 库的根重新导出json模块，外部以json::Value、json::Lexer等使用
+pub mod json;
+pub mod expr;
+pub mod cli;
+
+pub use json::*;
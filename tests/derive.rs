@@ -0,0 +1,99 @@
+use std::collections::HashMap;
+use json::convert::{FromJson, ToJson};
+use json::from_str;
+
+#[derive(Debug, PartialEq, ToJson, FromJson)]
+struct Item {
+    id: u64,
+    #[json(rename = "type")]
+    kind: String,
+    #[json(default)]
+    tags: Vec<String>,
+    #[json(skip)]
+    cached: bool,
+}
+
+#[derive(Debug, PartialEq, ToJson, FromJson)]
+struct Page {
+    results: Vec<Item>,
+    next: Option<String>,
+    #[json(flatten)]
+    extra: HashMap<String, String>,
+}
+
+#[derive(Debug, PartialEq, ToJson, FromJson)]
+struct Point<T> {
+    x: T,
+    y: T,
+}
+
+#[derive(Debug, PartialEq, ToJson, FromJson)]
+struct Labeled {
+    label: String,
+    #[json(flatten)]
+    point: Option<Point<i32>>,
+}
+
+fn parse<T: FromJson>(src: &str) -> Result<T, String> {
+    T::from_json(&from_str(src).unwrap()).map_err(|e| e.to_string())
+}
+
+#[test]
+fn round_trip() {
+    let item = Item { id: 7, kind: "a\"b".to_string(), tags: vec!["x".to_string()], cached: false };
+    let text = item.to_json();
+    assert_eq!(text, r#"{"id":7,"type":"a\"b","tags":["x"]}"#);
+    assert_eq!(parse::<Item>(&text).unwrap(), item);
+    let point = Point { x: 1.5, y: -2.0 };
+    assert_eq!(parse::<Point<f64>>(&point.to_json()).unwrap(), point);
+}
+
+#[test]
+fn defaults_and_missing_fields() {
+    let item: Item = parse(r#"{"id": 1, "type": "t"}"#).unwrap();
+    assert_eq!(item.tags, Vec::<String>::new());
+    assert_eq!(parse::<Item>(r#"{"id": 1}"#).unwrap_err(), r#"missing field "type""#);
+    let page: Page = parse(r#"{"results": []}"#).unwrap();
+    assert_eq!(page.next, None);
+}
+
+#[test]
+fn error_path() {
+    let src = r#"{"results": [{"id": 1, "type": "a"}, {"id": "2", "type": "b"}], "next": null}"#;
+    assert_eq!(parse::<Page>(src).unwrap_err(), "results[1].id: expect number, found string");
+    assert_eq!(parse::<Page>("[]").unwrap_err(), "expect object, found array");
+}
+
+#[test]
+fn flatten_sees_unclaimed_keys() {
+    let src = r#"{"results": [{"id": 1, "type": "a"}], "next": "p2", "source": "api", "region": "eu"}"#;
+    let page: Page = parse(src).unwrap();
+    assert_eq!(page.results.len(), 1);
+    assert_eq!(page.next.as_deref(), Some("p2"));
+    let extra: HashMap<String, String> = [("source", "api"), ("region", "eu")]
+        .into_iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+    assert_eq!(page.extra, extra);
+    let again: Page = parse(&page.to_json()).unwrap();
+    assert_eq!(again, page);
+}
+
+#[test]
+fn flatten_none_writes_no_members() {
+    let labeled = Labeled { label: "p".to_string(), point: Some(Point { x: 1, y: 2 }) };
+    assert_eq!(labeled.to_json(), r#"{"label":"p","x":1,"y":2}"#);
+    let labeled = Labeled { label: "p".to_string(), point: None };
+    assert_eq!(labeled.to_json(), r#"{"label":"p"}"#);
+}
+
+#[test]
+fn integer_bounds() {
+    assert_eq!(parse::<u8>("255").unwrap(), 255);
+    assert!(parse::<u8>("256").is_err());
+    assert!(parse::<i8>("-129").is_err());
+    assert_eq!(parse::<i8>("-128").unwrap(), -128);
+    assert!(parse::<u64>("18446744073709551616").is_err());
+    assert!(parse::<i64>("9223372036854775808").is_err());
+    assert_eq!(parse::<i64>("-9223372036854775808").unwrap(), i64::MIN);
+    assert!(parse::<u32>("-1").is_err());
+    assert!(parse::<u32>("1.5").is_err());
+}